@@ -0,0 +1,256 @@
+use std::error::Error;
+
+use reqwest::StatusCode;
+use url::Url;
+
+use crate::article_meta::format_date;
+use crate::fetcher::{FetchError, FetchResponse, Fetcher};
+use crate::{AppConfig, escape_html};
+
+/// The Internet Archive's Wayback availability API.
+pub const DEFAULT_AVAILABILITY_ENDPOINT: &str = "https://archive.org/wayback/available";
+
+/// An archived capture of a URL, as reported by an availability endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub url: String,
+    /// Wayback timestamp, `YYYYMMDDhhmmss`.
+    pub timestamp: String,
+}
+
+impl Snapshot {
+    /// The capture date as `YYYY-MM-DD`, when the timestamp is well formed.
+    pub fn date(&self) -> Option<String> {
+        let ts = self.timestamp.as_str();
+        if ts.len() < 8 || !ts[..8].bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        Some(format!("{}-{}-{}", &ts[..4], &ts[4..6], &ts[6..8]))
+    }
+
+    /// Notice shown with a recovered article's byline.
+    pub fn notice_html(&self) -> String {
+        let when = self
+            .date()
+            .map(|date| format!(" from {}", format_date(&date)))
+            .unwrap_or_default();
+        format!(
+            "<p class=\"archive-notice\">Archived copy{}; the original page was unavailable. \
+             Snapshot: <a href=\"{url}\">{url}</a></p>",
+            when,
+            url = escape_html(&self.url)
+        )
+    }
+
+    /// The snapshot URL with the `id_` modifier, which serves the page as
+    /// captured instead of wrapped in the archive's toolbar and rewritten links.
+    pub fn raw_url(&self) -> String {
+        let marker = format!("/{}/", self.timestamp);
+        match self.url.find(&marker) {
+            Some(idx) if !self.timestamp.is_empty() => {
+                let split = idx + marker.len() - 1;
+                format!("{}id_{}", &self.url[..split], &self.url[split..])
+            }
+            _ => self.url.clone(),
+        }
+    }
+}
+
+/// Looks up archived copies of URLs that are gone, blocked or timing out.
+#[derive(Debug, Clone)]
+pub struct ArchiveFallback {
+    /// Availability endpoints, tried in order until one has a snapshot.
+    pub endpoints: Vec<String>,
+}
+
+impl ArchiveFallback {
+    /// Build from config; `None` unless `archive_fallback = true`.
+    pub fn from_config(config: Option<&AppConfig>) -> Option<Self> {
+        let config = config?;
+        if config.archive_fallback != Some(true) {
+            return None;
+        }
+        let endpoints = config
+            .archive_endpoints
+            .clone()
+            .filter(|endpoints| !endpoints.is_empty())
+            .unwrap_or_else(|| vec![DEFAULT_AVAILABILITY_ENDPOINT.to_string()]);
+        Some(Self { endpoints })
+    }
+
+    /// Whether a failed fetch is worth retrying from an archive: 403, 404, 410
+    /// or a timeout.
+    pub fn should_try(error: &FetchError) -> bool {
+        match error {
+            FetchError::Request(e) if e.is_timeout() => true,
+            _ => error.status().is_some_and(|status| {
+                matches!(
+                    status,
+                    StatusCode::FORBIDDEN | StatusCode::NOT_FOUND | StatusCode::GONE
+                )
+            }),
+        }
+    }
+
+    /// Find the closest snapshot of `url` and fetch it. Endpoints that fail or
+    /// have no capture fall through to the next one.
+    pub fn recover(
+        &self,
+        fetcher: &Fetcher,
+        url: &str,
+    ) -> Result<(Snapshot, FetchResponse), Box<dyn Error>> {
+        let mut last_error: Option<Box<dyn Error>> = None;
+        for endpoint in &self.endpoints {
+            let snapshot = match lookup(fetcher, endpoint, url) {
+                Ok(Some(snapshot)) => snapshot,
+                Ok(None) => continue,
+                Err(e) => {
+                    eprintln!("Warning: archive lookup via {} failed: {}", endpoint, e);
+                    last_error = Some(e);
+                    continue;
+                }
+            };
+            match fetcher.get(&snapshot.raw_url()) {
+                Ok(response) => return Ok((snapshot, response)),
+                Err(e) => last_error = Some(format!("snapshot fetch failed: {}", e).into()),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| "no archived copy found".into()))
+    }
+}
+
+/// Ask one availability endpoint for the closest capture of `url`.
+fn lookup(
+    fetcher: &Fetcher,
+    endpoint: &str,
+    url: &str,
+) -> Result<Option<Snapshot>, Box<dyn Error>> {
+    let mut query = Url::parse(endpoint)?;
+    query.query_pairs_mut().append_pair("url", url);
+    let response = fetcher.get(query.as_str())?;
+    parse_availability(&response.body)
+}
+
+/// Parse a Wayback availability response (`archived_snapshots.closest`).
+pub fn parse_availability(body: &[u8]) -> Result<Option<Snapshot>, Box<dyn Error>> {
+    let json: serde_json::Value = serde_json::from_slice(body)?;
+    let closest = &json["archived_snapshots"]["closest"];
+    if closest["available"].as_bool() == Some(false) {
+        return Ok(None);
+    }
+    // A capture of an error page is no better than the live error.
+    if let Some(status) = closest["status"].as_str()
+        && !status.starts_with('2')
+    {
+        return Ok(None);
+    }
+    let Some(url) = closest["url"].as_str() else {
+        return Ok(None);
+    };
+    Ok(Some(Snapshot {
+        url: url.to_string(),
+        timestamp: closest["timestamp"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArchiveFallback, Snapshot, parse_availability};
+    use crate::fetcher::{FetchSettings, Fetcher};
+    use crate::test_support::{TestResponse, TestServer};
+
+    #[test]
+    fn parses_closest_snapshot() {
+        let body = br#"{"url": "example.com/story", "archived_snapshots": {"closest":
+            {"status": "200", "available": true, "timestamp": "20240102030405",
+             "url": "http://web.archive.org/web/20240102030405/https://example.com/story"}}}"#;
+        let snapshot = parse_availability(body).expect("json").expect("snapshot");
+        assert_eq!(snapshot.date().as_deref(), Some("2024-01-02"));
+        assert!(
+            snapshot
+                .notice_html()
+                .starts_with("<p class=\"archive-notice\">Archived copy from January 2, 2024;")
+        );
+        assert_eq!(
+            snapshot.raw_url(),
+            "http://web.archive.org/web/20240102030405id_/https://example.com/story"
+        );
+
+        assert_eq!(
+            parse_availability(br#"{"archived_snapshots": {}}"#).expect("json"),
+            None
+        );
+        let error_capture = br#"{"archived_snapshots": {"closest": {"status": "404",
+            "available": true, "timestamp": "2024", "url": "http://a/web/2024/x"}}}"#;
+        assert_eq!(parse_availability(error_capture).expect("json"), None);
+    }
+
+    #[test]
+    fn raw_url_leaves_unknown_layouts_alone() {
+        let snapshot = Snapshot {
+            url: "https://archive.example/abc123".to_string(),
+            timestamp: "20240102030405".to_string(),
+        };
+        assert_eq!(snapshot.raw_url(), "https://archive.example/abc123");
+    }
+
+    #[test]
+    fn recovers_through_the_endpoint_chain() {
+        let server = TestServer::start(|req| {
+            let path = req.path.as_str();
+            let host = req.header("host").unwrap_or_default();
+            if path.starts_with("/empty/available") {
+                TestResponse::ok("application/json", r#"{"archived_snapshots": {}}"#)
+            } else if path == "/wayback/available?url=https%3A%2F%2Fgone.example%2Fstory" {
+                let body = format!(
+                    r#"{{"archived_snapshots": {{"closest": {{"available": true, "status": "200",
+                        "timestamp": "20230405060708",
+                        "url": "http://{host}/web/20230405060708/https://gone.example/story"}}}}}}"#
+                );
+                TestResponse::ok("application/json", body)
+            } else if path == "/web/20230405060708id_/https://gone.example/story" {
+                TestResponse::ok("text/html", "<p>archived body</p>")
+            } else {
+                TestResponse::status(404)
+            }
+        });
+        let fallback = ArchiveFallback {
+            endpoints: vec![
+                server.url("/broken/available"),
+                server.url("/empty/available"),
+                server.url("/wayback/available"),
+            ],
+        };
+        let fetcher = Fetcher::new(FetchSettings::default()).expect("client");
+
+        let (snapshot, response) = fallback
+            .recover(&fetcher, "https://gone.example/story")
+            .expect("recovered");
+        assert_eq!(snapshot.date().as_deref(), Some("2023-04-05"));
+        assert_eq!(response.text(), "<p>archived body</p>");
+
+        let err = fallback
+            .recover(&fetcher, "https://other.example/")
+            .expect_err("no snapshot");
+        assert!(err.to_string().contains("404"));
+    }
+
+    #[test]
+    fn only_gone_blocked_or_timed_out_fetches_fall_back() {
+        use crate::fetcher::FetchError;
+        use reqwest::StatusCode;
+
+        assert!(ArchiveFallback::should_try(&FetchError::Status(
+            StatusCode::GONE
+        )));
+        assert!(ArchiveFallback::should_try(&FetchError::Status(
+            StatusCode::FORBIDDEN
+        )));
+        assert!(!ArchiveFallback::should_try(&FetchError::Status(
+            StatusCode::INTERNAL_SERVER_ERROR
+        )));
+    }
+}
@@ -0,0 +1,211 @@
+use chrono::{DateTime, NaiveDate};
+use dom_smoothie::Article;
+
+use crate::archive::Snapshot;
+use crate::escape_html;
+
+/// Bylines longer than this are usually a sentence readability grabbed by mistake.
+const MAX_BYLINE_CHARS: usize = 100;
+
+/// Citation metadata carried alongside an article's content.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArticleMeta {
+    pub byline: Option<String>,
+    /// Publish time as found in the source (RFC 3339 or a bare date, usually).
+    pub published: Option<String>,
+    pub site_name: Option<String>,
+    pub excerpt: Option<String>,
+    pub lead_image: Option<String>,
+    pub language: Option<String>,
+    /// The snapshot an article was recovered from when the original was unavailable.
+    pub archived: Option<Snapshot>,
+}
+
+impl ArticleMeta {
+    pub fn from_article(article: &Article) -> Self {
+        Self {
+            byline: clean(article.byline.as_deref()),
+            published: clean(article.published_time.as_deref()),
+            site_name: clean(article.site_name.as_deref()),
+            excerpt: clean(article.excerpt.as_deref()),
+            lead_image: clean(article.image.as_deref()),
+            language: clean(article.lang.as_deref()),
+            archived: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Fill in from feed entry metadata. Feed dates are structured, so they win;
+    /// other fields are taken from the feed when the page had none, or when the
+    /// page's byline looks like stray text.
+    pub fn merge_feed(&mut self, feed: &ArticleMeta) {
+        let byline_suspect = self
+            .byline
+            .as_ref()
+            .is_some_and(|b| b.chars().count() > MAX_BYLINE_CHARS);
+        if feed.byline.is_some() && (self.byline.is_none() || byline_suspect) {
+            self.byline = feed.byline.clone();
+        }
+        if feed.published.is_some() {
+            self.published = feed.published.clone();
+        }
+        fill(&mut self.site_name, &feed.site_name);
+        fill(&mut self.excerpt, &feed.excerpt);
+        fill(&mut self.lead_image, &feed.lead_image);
+        fill(&mut self.language, &feed.language);
+    }
+
+    /// The byline block shown under an article's title, followed by the archive
+    /// notice for recovered articles; an empty string when there is neither.
+    pub fn byline_html(&self) -> String {
+        let notice = self
+            .archived
+            .as_ref()
+            .map(Snapshot::notice_html)
+            .unwrap_or_default();
+        format!("{}{}", self.credit_html(), notice)
+    }
+
+    fn credit_html(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(byline) = &self.byline {
+            let byline = if byline.to_ascii_lowercase().starts_with("by ") {
+                byline.clone()
+            } else {
+                format!("By {}", byline)
+            };
+            parts.push(format!(
+                "<span class=\"byline-author\">{}</span>",
+                escape_html(&byline)
+            ));
+        }
+        if let Some(site) = &self.site_name {
+            parts.push(format!(
+                "<span class=\"byline-site\">{}</span>",
+                escape_html(site)
+            ));
+        }
+        if let Some(published) = &self.published {
+            parts.push(format!(
+                "<time class=\"byline-date\">{}</time>",
+                escape_html(&format_date(published))
+            ));
+        }
+        if parts.is_empty() {
+            return String::new();
+        }
+        format!("<p class=\"article-byline\">{}</p>", parts.join(" · "))
+    }
+}
+
+/// `2024-03-01T08:00:00Z` → `March 1, 2024`; unrecognized values are kept as-is.
+pub fn format_date(raw: &str) -> String {
+    let raw = raw.trim();
+    let date = DateTime::parse_from_rfc3339(raw)
+        .map(|dt| dt.date_naive())
+        .or_else(|_| DateTime::parse_from_rfc2822(raw).map(|dt| dt.date_naive()))
+        .ok()
+        .or_else(|| NaiveDate::parse_from_str(raw.get(..10)?, "%Y-%m-%d").ok());
+    match date {
+        Some(date) => date.format("%B %-d, %Y").to_string(),
+        None => raw.to_string(),
+    }
+}
+
+fn clean(value: Option<&str>) -> Option<String> {
+    let value = value?.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!value.is_empty()).then_some(value)
+}
+
+fn fill(target: &mut Option<String>, source: &Option<String>) {
+    if target.is_none() {
+        target.clone_from(source);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArticleMeta, format_date};
+    use crate::archive::Snapshot;
+
+    #[test]
+    fn formats_rfc3339_rfc2822_and_bare_dates() {
+        assert_eq!(format_date("2024-03-01T08:00:00+02:00"), "March 1, 2024");
+        assert_eq!(
+            format_date("Tue, 12 Nov 2024 10:00:00 GMT"),
+            "November 12, 2024"
+        );
+        assert_eq!(format_date("2023-12-24"), "December 24, 2023");
+        assert_eq!(format_date("last Tuesday"), "last Tuesday");
+    }
+
+    #[test]
+    fn byline_block_lists_author_site_and_date() {
+        let meta = ArticleMeta {
+            byline: Some("Ada & Grace".to_string()),
+            site_name: Some("The Paper".to_string()),
+            published: Some("2024-03-01".to_string()),
+            ..ArticleMeta::default()
+        };
+        assert_eq!(
+            meta.byline_html(),
+            "<p class=\"article-byline\"><span class=\"byline-author\">By Ada &amp; Grace</span> · \
+             <span class=\"byline-site\">The Paper</span> · \
+             <time class=\"byline-date\">March 1, 2024</time></p>"
+        );
+        assert_eq!(ArticleMeta::default().byline_html(), "");
+    }
+
+    #[test]
+    fn archived_articles_show_the_snapshot_after_the_byline() {
+        let meta = ArticleMeta {
+            byline: Some("Ada".to_string()),
+            archived: Some(Snapshot {
+                url: "https://web.archive.org/web/20240102030405/https://example.com/a".to_string(),
+                timestamp: "20240102030405".to_string(),
+            }),
+            ..ArticleMeta::default()
+        };
+        let html = meta.byline_html();
+        assert!(html.starts_with(
+            "<p class=\"article-byline\"><span class=\"byline-author\">By Ada</span></p>\
+             <p class=\"archive-notice\">Archived copy from January 2, 2024;"
+        ));
+        assert!(html.contains("<a href=\"https://web.archive.org/web/20240102030405/"));
+
+        let without_byline = ArticleMeta {
+            byline: None,
+            ..meta
+        };
+        assert!(
+            without_byline
+                .byline_html()
+                .starts_with("<p class=\"archive-notice\">")
+        );
+    }
+
+    #[test]
+    fn feed_metadata_fills_gaps_and_replaces_suspect_bylines() {
+        let mut meta = ArticleMeta {
+            byline: Some("word ".repeat(30)),
+            site_name: Some("Page Site".to_string()),
+            published: Some("March 2024".to_string()),
+            ..ArticleMeta::default()
+        };
+        let feed = ArticleMeta {
+            byline: Some("Feed Author".to_string()),
+            site_name: Some("Feed Title".to_string()),
+            published: Some("2024-03-05T00:00:00Z".to_string()),
+            excerpt: Some("Feed summary".to_string()),
+            ..ArticleMeta::default()
+        };
+        meta.merge_feed(&feed);
+        assert_eq!(meta.byline.as_deref(), Some("Feed Author"));
+        assert_eq!(meta.site_name.as_deref(), Some("Page Site"));
+        assert_eq!(meta.published.as_deref(), Some("2024-03-05T00:00:00Z"));
+        assert_eq!(meta.excerpt.as_deref(), Some("Feed summary"));
+    }
+}
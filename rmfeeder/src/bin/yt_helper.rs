@@ -7,6 +7,7 @@ use std::time::Duration;
 
 use chrono::Local;
 use pulldown_cmark::{Options, Parser, html};
+use rmfeeder::article_meta::ArticleMeta;
 use rmfeeder::{
     PageSize, default_config_path, escape_html, expand_tilde_path, load_config_from_path,
     categorize::{CategorizeInput, categorize},
@@ -314,6 +315,8 @@ fn build_flat_articles(collected: &[CollectedArticle]) -> Vec<BundleArticle> {
             section: None,
             title: a.title.clone(),
             content_html: a.body_html.clone(),
+            meta: ArticleMeta::default(),
+            source_pdf: None,
         })
         .collect()
 }
@@ -360,6 +363,8 @@ fn build_categorized_articles(collected: &[CollectedArticle]) -> Vec<BundleArtic
                 section: Some(group.name.clone()),
                 title: collected[idx].title.clone(),
                 content_html: collected[idx].body_html.clone(),
+                meta: ArticleMeta::default(),
+                source_pdf: None,
             });
         }
     }
@@ -378,6 +383,8 @@ fn build_categorized_articles(collected: &[CollectedArticle]) -> Vec<BundleArtic
             section: Some("Other".to_string()),
             title: collected[idx].title.clone(),
             content_html: collected[idx].body_html.clone(),
+            meta: ArticleMeta::default(),
+            source_pdf: None,
         });
     }
 
@@ -393,6 +400,8 @@ fn build_categorized_articles(collected: &[CollectedArticle]) -> Vec<BundleArtic
                 section: Some("Other".to_string()),
                 title: collected[idx].title.clone(),
                 content_html: collected[idx].body_html.clone(),
+                meta: ArticleMeta::default(),
+                source_pdf: None,
             });
         }
     }
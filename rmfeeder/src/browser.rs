@@ -0,0 +1,305 @@
+use std::error::Error;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+use crate::request_profile::{RequestProfile, glob_matches};
+use crate::{AppConfig, expand_tilde_path, fetcher};
+
+/// Browser executables tried, in order, when `browser_path` is not configured.
+const BROWSER_CANDIDATES: &[&str] = &[
+    "chromium",
+    "chromium-browser",
+    "google-chrome",
+    "google-chrome-stable",
+    "microsoft-edge",
+    "/Applications/Chromium.app/Contents/MacOS/Chromium",
+    "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+];
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Articles with less extracted text than this are re-rendered in auto mode.
+pub const DEFAULT_MIN_TEXT_CHARS: usize = 500;
+
+/// Runs a headless Chromium-family browser and returns the DOM after scripts ran.
+#[derive(Debug, Clone)]
+pub struct BrowserRenderer {
+    pub binary: PathBuf,
+    pub timeout: Duration,
+}
+
+impl BrowserRenderer {
+    pub fn new(binary: PathBuf, timeout: Duration) -> Self {
+        Self { binary, timeout }
+    }
+
+    /// Load `url` headlessly and return the serialized DOM (`--dump-dom`). The
+    /// browser and everything it started are killed if it does not finish within
+    /// the timeout.
+    ///
+    /// The matching request `profile` supplies the user agent and proxy. Chromium's
+    /// command line has no way to pass its headers, cookies or credentials, so pages
+    /// that need those are rendered signed out.
+    pub fn render(
+        &self,
+        url: &str,
+        profile: Option<&RequestProfile>,
+    ) -> Result<String, Box<dyn Error>> {
+        // Let scripts run for most of the timeout, leaving time to serialize the DOM.
+        let budget_ms = self.timeout.as_millis().saturating_sub(2000).max(1000);
+        let user_agent = profile
+            .and_then(|p| p.user_agent.as_deref())
+            .unwrap_or(fetcher::USER_AGENT);
+        let mut command = Command::new(&self.binary);
+        command
+            .arg("--headless=new")
+            .arg("--disable-gpu")
+            .arg("--hide-scrollbars")
+            .arg("--mute-audio")
+            .arg(format!("--user-agent={}", user_agent))
+            .arg(format!("--virtual-time-budget={}", budget_ms));
+        if let Some(proxy) = profile.and_then(|p| p.proxy.as_deref()) {
+            command.arg(format!("--proxy-server={}", proxy));
+        }
+        if profile.is_some_and(sends_credentials) {
+            eprintln!(
+                "Warning: rendering {} without the request profile's headers, cookies or credentials",
+                url
+            );
+        }
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let mut child = command
+            .arg("--dump-dom")
+            .arg(url)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("could not start {}: {}", self.binary.display(), e))?;
+
+        let mut stdout = child.stdout.take().ok_or("browser stdout unavailable")?;
+        let reader = thread::spawn(move || {
+            let mut out = Vec::new();
+            stdout.read_to_end(&mut out).map(|_| out)
+        });
+
+        let started = Instant::now();
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if started.elapsed() >= self.timeout {
+                kill_process_group(&mut child);
+                let _ = child.wait();
+                let _ = reader.join();
+                return Err(format!("browser timed out after {}s", self.timeout.as_secs()).into());
+            }
+            thread::sleep(Duration::from_millis(50));
+        };
+
+        let out = reader
+            .join()
+            .map_err(|_| "browser output reader panicked")??;
+        if !status.success() {
+            return Err(format!("browser exited with {}", status).into());
+        }
+        let dom = String::from_utf8_lossy(&out).into_owned();
+        if dom.trim().is_empty() {
+            return Err("browser returned an empty DOM".into());
+        }
+        Ok(dom)
+    }
+}
+
+fn sends_credentials(profile: &RequestProfile) -> bool {
+    profile.headers.as_ref().is_some_and(|h| !h.is_empty())
+        || profile.cookie_file.is_some()
+        || profile.basic_auth_user.is_some()
+        || profile.token_env.is_some()
+}
+
+/// Kill the browser together with the renderer, GPU and zygote processes it
+/// forked, which would otherwise keep the stdout pipe open.
+fn kill_process_group(child: &mut Child) {
+    #[cfg(unix)]
+    if let Ok(pid) = libc::pid_t::try_from(child.id()) {
+        // SAFETY: kill(2) with a negative pid only sends a signal to that process group.
+        unsafe {
+            libc::kill(-pid, libc::SIGKILL);
+        }
+    }
+    let _ = child.kill();
+}
+
+/// When to fall back to the headless browser for HTML articles.
+#[derive(Debug, Clone)]
+pub struct BrowserFallback {
+    pub renderer: BrowserRenderer,
+    /// Re-render when static extraction fails or yields fewer than `min_text_chars`.
+    pub auto: bool,
+    /// Host globs that are always rendered in the browser.
+    pub hosts: Vec<String>,
+    pub min_text_chars: usize,
+}
+
+impl BrowserFallback {
+    /// Build from config. Returns `None` when the fallback is not enabled, or when
+    /// no browser executable can be found (with a warning).
+    pub fn from_config(config: Option<&AppConfig>, force_auto: bool) -> Option<Self> {
+        let auto = force_auto || config.and_then(|c| c.browser_fallback).unwrap_or(false);
+        let hosts = config
+            .and_then(|c| c.browser_hosts.clone())
+            .unwrap_or_default();
+        if !auto && hosts.is_empty() {
+            return None;
+        }
+
+        let configured = config.and_then(|c| c.browser_path.as_deref());
+        let Some(binary) = configured.map(expand_tilde_path).or_else(find_browser) else {
+            eprintln!("Warning: browser fallback enabled but no Chromium/Chrome executable found");
+            return None;
+        };
+        let timeout = config
+            .and_then(|c| c.browser_timeout)
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TIMEOUT);
+        Some(Self {
+            renderer: BrowserRenderer::new(binary, timeout),
+            auto,
+            hosts,
+            min_text_chars: config
+                .and_then(|c| c.browser_min_text)
+                .unwrap_or(DEFAULT_MIN_TEXT_CHARS),
+        })
+    }
+
+    /// Whether `url`'s host is configured to always use the browser.
+    pub fn always_for(&self, url: &str) -> bool {
+        let Some(host) = Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(|h| h.to_ascii_lowercase()))
+        else {
+            return false;
+        };
+        self.hosts
+            .iter()
+            .any(|pattern| glob_matches(&pattern.to_ascii_lowercase(), &host))
+    }
+}
+
+/// The first browser from [`BROWSER_CANDIDATES`] found on `PATH` or at its absolute path.
+pub fn find_browser() -> Option<PathBuf> {
+    let path_dirs: Vec<PathBuf> = std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).collect())
+        .unwrap_or_default();
+    BROWSER_CANDIDATES.iter().find_map(|candidate| {
+        let candidate = Path::new(candidate);
+        if candidate.is_absolute() {
+            return candidate.is_file().then(|| candidate.to_path_buf());
+        }
+        path_dirs
+            .iter()
+            .map(|dir| dir.join(candidate))
+            .find(|path| path.is_file())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BrowserFallback, BrowserRenderer, DEFAULT_MIN_TEXT_CHARS};
+    use crate::request_profile::RequestProfile;
+    use crate::test_support::stand_in_executable as stand_in;
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn returns_the_dumped_dom() {
+        let binary = stand_in(
+            "fake-chromium",
+            r#"for arg in "$@"; do last="$arg"; done
+echo "<html><body><p>rendered $last</p></body></html>""#,
+        );
+        let renderer = BrowserRenderer::new(binary, Duration::from_secs(10));
+        let dom = renderer
+            .render("https://spa.example/post", None)
+            .expect("render");
+        assert!(dom.contains("<p>rendered https://spa.example/post</p>"));
+    }
+
+    #[test]
+    fn kills_browsers_that_exceed_the_timeout() {
+        let binary = stand_in("slow-chromium", "exec sleep 10");
+        let renderer = BrowserRenderer::new(binary, Duration::from_millis(300));
+        let started = Instant::now();
+        let err = renderer
+            .render("https://spa.example/", None)
+            .expect_err("timeout");
+        assert!(err.to_string().contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn passes_the_request_profile_agent_and_proxy() {
+        let binary = stand_in(
+            "profile-chromium",
+            r#"echo "<html><body><pre>$*</pre></body></html>""#,
+        );
+        let renderer = BrowserRenderer::new(binary, Duration::from_secs(10));
+        let profile = RequestProfile {
+            hosts: vec!["spa.example".to_string()],
+            user_agent: Some("Mozilla/5.0 (Profile)".to_string()),
+            proxy: Some("socks5://127.0.0.1:1080".to_string()),
+            ..RequestProfile::default()
+        };
+        let dom = renderer
+            .render("https://spa.example/", Some(&profile))
+            .expect("render");
+        assert!(dom.contains("--user-agent=Mozilla/5.0 (Profile)"));
+        assert!(dom.contains("--proxy-server=socks5://127.0.0.1:1080"));
+
+        let dom = renderer
+            .render("https://spa.example/", None)
+            .expect("render");
+        assert!(dom.contains("--user-agent=rmfeeder/"));
+        assert!(!dom.contains("--proxy-server"));
+    }
+
+    #[test]
+    fn timeouts_kill_the_processes_the_browser_started() {
+        // The forked child keeps stdout open; only killing the whole group lets
+        // the output reader finish.
+        let binary = stand_in("forking-chromium", "sleep 10 &\nwait");
+        let renderer = BrowserRenderer::new(binary, Duration::from_millis(300));
+        let started = Instant::now();
+        let err = renderer
+            .render("https://spa.example/", None)
+            .expect_err("timeout");
+        assert!(err.to_string().contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn reports_failing_browsers() {
+        let binary = stand_in("broken-chromium", "exit 3");
+        let renderer = BrowserRenderer::new(binary, Duration::from_secs(5));
+        assert!(renderer.render("https://spa.example/", None).is_err());
+    }
+
+    #[test]
+    fn host_globs_force_browser_rendering() {
+        let fallback = BrowserFallback {
+            renderer: BrowserRenderer::new(PathBuf::from("chromium"), Duration::from_secs(1)),
+            auto: false,
+            hosts: vec!["*.spa.example".to_string()],
+            min_text_chars: DEFAULT_MIN_TEXT_CHARS,
+        };
+        assert!(fallback.always_for("https://docs.spa.example/guide"));
+        assert!(!fallback.always_for("https://example.com/"));
+    }
+}
@@ -0,0 +1,392 @@
+use url::Url;
+
+use crate::AppConfig;
+use crate::request_profile::glob_matches;
+
+/// Query parameters dropped by default. A trailing `*` matches any suffix.
+pub const DEFAULT_STRIPPED_PARAMS: &[&str] = &[
+    "utm_*",
+    "fbclid",
+    "gclid",
+    "dclid",
+    "msclkid",
+    "yclid",
+    "igshid",
+    "mc_cid",
+    "mc_eid",
+    "_hsenc",
+    "_hsmi",
+    "mkt_tok",
+    "ref",
+    "ref_src",
+    "ref_url",
+    "cmpid",
+    "smid",
+    "amp",
+    "outputType",
+];
+
+/// Normalizes article URLs so the same story reached via tracking links, AMP
+/// pages or trailing-slash variants dedupes to one key.
+#[derive(Debug, Clone)]
+pub struct Canonicalizer {
+    stripped: Vec<String>,
+    /// Host globs whose `/amp` paths are always AMP pages. Elsewhere an `/amp`
+    /// path is only mapped once the fetched page turns out to be AMP.
+    amp_path_hosts: Vec<String>,
+}
+
+impl Default for Canonicalizer {
+    fn default() -> Self {
+        Self::new(&[])
+    }
+}
+
+impl Canonicalizer {
+    /// Strip the default parameters plus `extra` (same `*` suffix syntax).
+    pub fn new(extra: &[String]) -> Self {
+        let stripped = DEFAULT_STRIPPED_PARAMS
+            .iter()
+            .map(|p| p.to_ascii_lowercase())
+            .chain(extra.iter().map(|p| p.to_ascii_lowercase()))
+            .collect();
+        Self {
+            stripped,
+            amp_path_hosts: Vec::new(),
+        }
+    }
+
+    /// From `strip_query_params` and `amp_path_hosts`.
+    pub fn from_config(config: Option<&AppConfig>) -> Self {
+        let mut canonicalizer = Self::new(
+            config
+                .and_then(|c| c.strip_query_params.as_deref())
+                .unwrap_or(&[]),
+        );
+        canonicalizer.amp_path_hosts = config
+            .and_then(|c| c.amp_path_hosts.clone())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|host| host.to_ascii_lowercase())
+            .collect();
+        canonicalizer
+    }
+
+    /// The canonical form of `input`, or `input` unchanged if it is not an http(s) URL.
+    pub fn canonicalize(&self, input: &str) -> String {
+        self.canonicalize_with(input, false)
+    }
+
+    /// `amp_page` says the URL is known to serve an AMP page, so an `/amp` path
+    /// segment or `.amp` suffix is dropped whatever the host.
+    fn canonicalize_with(&self, input: &str, amp_page: bool) -> String {
+        let Ok(mut url) = Url::parse(input.trim()) else {
+            return input.to_string();
+        };
+        if !matches!(url.scheme(), "http" | "https") {
+            return input.to_string();
+        }
+        let mut amp_page = amp_page;
+        if let Some(unwrapped) = unwrap_amp_cache(&url) {
+            url = unwrapped;
+            amp_page = true;
+        }
+
+        url.set_fragment(None);
+
+        let kept: Vec<(String, String)> = url
+            .query_pairs()
+            .filter(|(name, _)| !self.is_stripped(name))
+            .map(|(name, value)| (name.into_owned(), value.into_owned()))
+            .collect();
+        if kept.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(kept);
+        }
+
+        if let Some(host) = url.host_str()
+            && let Some(stripped) = host.strip_prefix("amp.")
+            && stripped.contains('.')
+        {
+            let stripped = stripped.to_string();
+            let _ = url.set_host(Some(&stripped));
+        }
+
+        let amp_host = url.host_str().is_some_and(|host| {
+            self.amp_path_hosts
+                .iter()
+                .any(|pattern| glob_matches(pattern, host))
+        });
+        let path = if amp_page || amp_host {
+            strip_amp_path(url.path())
+        } else {
+            url.path().to_string()
+        };
+        let path = if path.len() > 1 {
+            path.trim_end_matches('/').to_string()
+        } else {
+            path
+        };
+        url.set_path(&path);
+
+        url.into()
+    }
+
+    fn is_stripped(&self, name: &str) -> bool {
+        let name = name.to_ascii_lowercase();
+        self.stripped
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == *pattern,
+            })
+    }
+}
+
+/// Canonical URL for a fetched page: `<link rel="canonical">` when it looks
+/// trustworthy, else the final URL after redirects, without its AMP path when
+/// the page is AMP.
+pub fn canonical_for_page(canonicalizer: &Canonicalizer, html: &str, final_url: &str) -> String {
+    let declared = find_canonical_link(html, final_url).filter(|declared| {
+        // Some sites point every page at their homepage; ignore that.
+        declared.path() != "/" || Url::parse(final_url).is_ok_and(|u| u.path() == "/")
+    });
+    match declared {
+        Some(url) => canonicalizer.canonicalize(url.as_str()),
+        None => canonicalizer.canonicalize_with(final_url, is_amp_page(html)),
+    }
+}
+
+/// Whether the `<html>` tag carries the `amp` or `⚡` attribute.
+fn is_amp_page(html: &str) -> bool {
+    let lower = html.to_ascii_lowercase();
+    let Some(start) = lower.find("<html") else {
+        return false;
+    };
+    let tag = &lower[start + 5..];
+    let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+    tag.split(|c: char| c.is_ascii_whitespace() || c == '/')
+        .map(|attr| attr.split('=').next().unwrap_or(""))
+        .any(|name| name == "amp" || name == "⚡")
+}
+
+/// The absolute `href` of the first `<link rel="canonical">` in `html`.
+pub fn find_canonical_link(html: &str, base: &str) -> Option<Url> {
+    let base = Url::parse(base).ok()?;
+    let lower = html.to_ascii_lowercase();
+    let head_end = lower.find("</head>").unwrap_or(lower.len());
+    let mut rest = &lower[..head_end];
+    let mut offset = 0;
+
+    while let Some(start) = rest.find("<link") {
+        let tag_start = start + 5;
+        let tag_len = rest[tag_start..]
+            .find('>')
+            .unwrap_or(rest.len() - tag_start);
+        let tag = &rest[tag_start..tag_start + tag_len];
+        if attr_value(tag, "rel").is_some_and(|rel| {
+            rel.split_ascii_whitespace()
+                .any(|token| token == "canonical")
+        }) {
+            // Re-read the href from the original text to keep its case.
+            let original = &html[offset + tag_start..offset + tag_start + tag_len];
+            let href = attr_value(original, "href")?;
+            let resolved = base.join(href.trim()).ok()?;
+            return matches!(resolved.scheme(), "http" | "https").then_some(resolved);
+        }
+        offset += tag_start + tag_len;
+        rest = &rest[tag_start + tag_len..];
+    }
+    None
+}
+
+fn attr_value<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut search = 0;
+    while let Some(found) = tag[search..].to_ascii_lowercase().find(name) {
+        let idx = search + found;
+        search = idx + name.len();
+        let preceded_ok = idx == 0
+            || tag[..idx]
+                .chars()
+                .last()
+                .is_some_and(|c| c.is_ascii_whitespace());
+        let Some(after) = tag[search..].trim_start().strip_prefix('=') else {
+            continue;
+        };
+        if !preceded_ok {
+            continue;
+        }
+        let value = after.trim_start();
+        return Some(match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or(""),
+            _ => value
+                .split(|c: char| c.is_ascii_whitespace() || c == '/')
+                .next()
+                .unwrap_or(""),
+        });
+    }
+    None
+}
+
+/// `https://example-com.cdn.ampproject.org/c/s/example.com/story` → `https://example.com/story`.
+fn unwrap_amp_cache(url: &Url) -> Option<Url> {
+    if !url.host_str()?.ends_with(".cdn.ampproject.org") {
+        return None;
+    }
+    let mut segments = url.path_segments()?;
+    let mut scheme = "http";
+    let mut next = segments.next()?;
+    if matches!(next, "c" | "v" | "i") {
+        next = segments.next()?;
+    }
+    if next == "s" {
+        scheme = "https";
+        next = segments.next()?;
+    }
+    let rest: Vec<&str> = segments.collect();
+    Url::parse(&format!("{}://{}/{}", scheme, next, rest.join("/"))).ok()
+}
+
+fn strip_amp_path(path: &str) -> String {
+    let trimmed = path.trim_end_matches('/');
+    if let Some(stripped) = trimmed.strip_suffix("/amp") {
+        return if stripped.is_empty() {
+            "/".to_string()
+        } else {
+            stripped.to_string()
+        };
+    }
+    if let Some(stripped) = trimmed.strip_prefix("/amp/") {
+        return format!("/{}", stripped);
+    }
+    if let Some(stripped) = trimmed.strip_suffix(".amp") {
+        return stripped.to_string();
+    }
+    path.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Canonicalizer, canonical_for_page, find_canonical_link};
+
+    #[test]
+    fn strips_tracking_params_fragments_and_trailing_slashes() {
+        let c = Canonicalizer::default();
+        assert_eq!(
+            c.canonicalize("https://Example.com/story/?utm_source=rss&id=7&fbclid=x#top"),
+            "https://example.com/story?id=7"
+        );
+        assert_eq!(
+            c.canonicalize("https://example.com/story?ref=hn"),
+            "https://example.com/story"
+        );
+        assert_eq!(
+            c.canonicalize("https://example.com/"),
+            "https://example.com/"
+        );
+    }
+
+    #[test]
+    fn extra_params_extend_the_defaults() {
+        let c = Canonicalizer::new(&["sessionid".to_string(), "trk_*".to_string()]);
+        assert_eq!(
+            c.canonicalize("https://example.com/a?sessionid=1&trk_x=2&page=2&utm_medium=x"),
+            "https://example.com/a?page=2"
+        );
+    }
+
+    #[test]
+    fn maps_amp_urls_to_their_canonical_form() {
+        let c = Canonicalizer {
+            amp_path_hosts: vec!["example.com".to_string()],
+            ..Canonicalizer::default()
+        };
+        assert_eq!(
+            c.canonicalize("https://example.com/2024/05/story/amp/"),
+            "https://example.com/2024/05/story"
+        );
+        assert_eq!(
+            c.canonicalize("https://example.com/amp/2024/05/story"),
+            "https://example.com/2024/05/story"
+        );
+        assert_eq!(
+            c.canonicalize("https://amp.example.com/story.amp?amp=1"),
+            "https://example.com/story"
+        );
+
+        let c = Canonicalizer::default();
+        assert_eq!(
+            c.canonicalize("https://example-com.cdn.ampproject.org/c/s/example.com/story/amp"),
+            "https://example.com/story"
+        );
+    }
+
+    #[test]
+    fn keeps_amp_paths_on_pages_that_are_not_amp() {
+        let c = Canonicalizer::default();
+        assert_eq!(
+            c.canonicalize("https://blog.example/tags/amp"),
+            "https://blog.example/tags/amp"
+        );
+        assert_eq!(
+            c.canonicalize("https://amp.dev/amp/getting-started"),
+            "https://amp.dev/amp/getting-started"
+        );
+        assert_eq!(
+            canonical_for_page(
+                &c,
+                "<html lang=\"en\"><body>Tag page</body></html>",
+                "https://blog.example/tags/amp/"
+            ),
+            "https://blog.example/tags/amp"
+        );
+
+        for page in ["<html amp lang=\"en\">", "<HTML \u{26a1}>"] {
+            assert_eq!(
+                canonical_for_page(&c, page, "https://news.example/2024/story/amp"),
+                "https://news.example/2024/story"
+            );
+        }
+        let declared = r#"<html amp><head><link rel="canonical" href="/2024/story"></head>"#;
+        assert_eq!(
+            canonical_for_page(&c, declared, "https://news.example/amp/2024/story"),
+            "https://news.example/2024/story"
+        );
+    }
+
+    #[test]
+    fn leaves_non_http_inputs_alone() {
+        let c = Canonicalizer::default();
+        assert_eq!(c.canonicalize("yt::abc"), "yt::abc");
+        assert_eq!(c.canonicalize("not a url"), "not a url");
+    }
+
+    #[test]
+    fn finds_relative_canonical_links_in_head() {
+        let html = r#"<html><head><LINK href="/Story-1?utm_source=x" REL="canonical"></head>
+            <body><link rel="canonical" href="/ignored"></body></html>"#;
+        let url = find_canonical_link(html, "https://example.com/amp/story-1").expect("link");
+        assert_eq!(url.as_str(), "https://example.com/Story-1?utm_source=x");
+    }
+
+    #[test]
+    fn page_canonical_prefers_declared_link_but_ignores_homepage() {
+        let c = Canonicalizer::default();
+        let declared = r#"<head><link rel="canonical" href="https://example.com/story"></head>"#;
+        assert_eq!(
+            canonical_for_page(&c, declared, "https://example.com/story?utm_source=x"),
+            "https://example.com/story"
+        );
+
+        let homepage = r#"<head><link rel="canonical" href="https://example.com/"></head>"#;
+        assert_eq!(
+            canonical_for_page(&c, homepage, "https://example.com/news/1/"),
+            "https://example.com/news/1"
+        );
+        assert_eq!(
+            canonical_for_page(&c, "<p>none</p>", "https://example.com/final?ref=x"),
+            "https://example.com/final"
+        );
+    }
+}
@@ -66,10 +66,9 @@ fn extract_json(text: &str) -> &str {
     if let Some(inner) = trimmed
         .strip_prefix("```json")
         .or_else(|| trimmed.strip_prefix("```"))
+        && let Some(end) = inner.rfind("```")
     {
-        if let Some(end) = inner.rfind("```") {
-            return inner[..end].trim();
-        }
+        return inner[..end].trim();
     }
     trimmed
 }
@@ -0,0 +1,242 @@
+use std::borrow::Cow;
+
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8};
+
+/// How many leading bytes are scanned for `<meta charset>` or an XML declaration.
+const SNIFF_LIMIT: usize = 4096;
+
+/// Decode an HTML body to UTF-8: BOM, then the `Content-Type` charset, then
+/// `<meta>` sniffing, then a statistical guess.
+pub fn decode_html(body: &[u8], content_type: Option<&str>) -> String {
+    let encoding = detect_html_encoding(body, content_type);
+    decode_with(body, encoding)
+}
+
+/// Decode an XML (feed) body to UTF-8 and rewrite its declaration to match, so
+/// downstream parsers do not re-decode it with the original charset.
+pub fn decode_xml(body: &[u8], content_type: Option<&str>) -> String {
+    let encoding = detect_xml_encoding(body, content_type);
+    let text = decode_with(body, encoding);
+    rewrite_xml_declaration(&text)
+}
+
+pub fn detect_html_encoding(body: &[u8], content_type: Option<&str>) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(body) {
+        return encoding;
+    }
+    if let Some(encoding) = content_type.and_then(charset_from_content_type) {
+        return encoding;
+    }
+    if let Some(encoding) = sniff_meta_charset(body) {
+        return encoding;
+    }
+    guess_encoding(body)
+}
+
+pub fn detect_xml_encoding(body: &[u8], content_type: Option<&str>) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(body) {
+        return encoding;
+    }
+    if let Some(encoding) = content_type.and_then(charset_from_content_type) {
+        return encoding;
+    }
+    if let Some(encoding) = sniff_xml_declaration(body) {
+        return encoding;
+    }
+    guess_encoding(body)
+}
+
+fn decode_with(body: &[u8], encoding: &'static Encoding) -> String {
+    // `decode` strips a matching BOM and falls back to the BOM's encoding if it disagrees.
+    let (text, _, _) = encoding.decode(body);
+    match text {
+        Cow::Borrowed(s) => s.to_string(),
+        Cow::Owned(s) => s,
+    }
+}
+
+fn charset_from_content_type(content_type: &str) -> Option<&'static Encoding> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("charset") {
+            return None;
+        }
+        label_to_encoding(value.trim().trim_matches(|c| c == '"' || c == '\''))
+    })
+}
+
+fn label_to_encoding(label: &str) -> Option<&'static Encoding> {
+    let encoding = Encoding::for_label(label.trim().as_bytes())?;
+    // Per the HTML spec, a UTF-16 declaration in ASCII-compatible bytes means UTF-8.
+    if encoding == encoding_rs::UTF_16LE || encoding == encoding_rs::UTF_16BE {
+        return Some(UTF_8);
+    }
+    Some(encoding)
+}
+
+fn sniff_meta_charset(body: &[u8]) -> Option<&'static Encoding> {
+    let head = ascii_lowercase_prefix(body);
+    let mut rest = head.as_str();
+    while let Some(start) = rest.find("<meta") {
+        let tag_start = &rest[start + 5..];
+        let tag_end = tag_start.find('>').unwrap_or(tag_start.len());
+        let tag = &tag_start[..tag_end];
+        if let Some(idx) = tag.find("charset") {
+            let value = tag[idx + 7..]
+                .trim_start()
+                .strip_prefix('=')
+                .map(str::trim_start)
+                .unwrap_or("");
+            if let Some(encoding) = label_to_encoding(take_attr_value(value)) {
+                return Some(encoding);
+            }
+        }
+        rest = &tag_start[tag_end..];
+    }
+    None
+}
+
+fn sniff_xml_declaration(body: &[u8]) -> Option<&'static Encoding> {
+    let head = ascii_lowercase_prefix(body);
+    let decl = head.strip_prefix("<?xml")?;
+    let decl = &decl[..decl.find("?>")?];
+    let idx = decl.find("encoding")?;
+    let value = decl[idx + 8..].trim_start().strip_prefix('=')?.trim_start();
+    label_to_encoding(take_attr_value(value))
+}
+
+fn take_attr_value(value: &str) -> &str {
+    if let Some(quoted) = value.strip_prefix('"') {
+        return quoted.split('"').next().unwrap_or("");
+    }
+    if let Some(quoted) = value.strip_prefix('\'') {
+        return quoted.split('\'').next().unwrap_or("");
+    }
+    value
+        .split(|c: char| c.is_whitespace() || c == ';' || c == '/' || c == '>')
+        .next()
+        .unwrap_or("")
+}
+
+fn ascii_lowercase_prefix(body: &[u8]) -> String {
+    let prefix = &body[..body.len().min(SNIFF_LIMIT)];
+    prefix
+        .iter()
+        .map(|&b| {
+            if b.is_ascii() {
+                b.to_ascii_lowercase() as char
+            } else {
+                ' '
+            }
+        })
+        .collect()
+}
+
+fn guess_encoding(body: &[u8]) -> &'static Encoding {
+    let mut detector = EncodingDetector::new();
+    detector.feed(body, true);
+    detector.guess(None, true)
+}
+
+fn rewrite_xml_declaration(text: &str) -> String {
+    let trimmed = text.trim_start_matches('\u{feff}');
+    if let Some(rest) = trimmed.strip_prefix("<?xml")
+        && let Some(end) = rest.find("?>")
+    {
+        let decl = &rest[..end];
+        let version = if decl.contains("1.1") { "1.1" } else { "1.0" };
+        return format!(
+            "<?xml version=\"{}\" encoding=\"utf-8\"?>{}",
+            version,
+            &rest[end + 2..]
+        );
+    }
+    trimmed.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_html, decode_xml, detect_html_encoding};
+
+    const SHIFT_JIS_META: &[u8] = include_bytes!("../../testdata/charset/shift_jis-meta.html");
+    const WINDOWS_1251_HTTP_EQUIV: &[u8] =
+        include_bytes!("../../testdata/charset/windows-1251-http-equiv.html");
+    const ISO_8859_2_HEADER: &[u8] =
+        include_bytes!("../../testdata/charset/iso-8859-2-header.html");
+    const UTF_8_BOM: &[u8] = include_bytes!("../../testdata/charset/utf-8-bom.html");
+    const WINDOWS_1252_UNDECLARED: &[u8] =
+        include_bytes!("../../testdata/charset/windows-1252-undeclared.html");
+    const ISO_8859_1_FEED: &[u8] = include_bytes!("../../testdata/charset/iso-8859-1-feed.xml");
+    const WINDOWS_1251_FEED: &[u8] =
+        include_bytes!("../../testdata/charset/windows-1251-feed-header.xml");
+
+    #[test]
+    fn decodes_meta_charset_when_header_has_none() {
+        let html = decode_html(SHIFT_JIS_META, Some("text/html"));
+        assert!(html.contains("吾輩は猫である。名前はまだ無い。"));
+    }
+
+    #[test]
+    fn decodes_http_equiv_content_type() {
+        let html = decode_html(WINDOWS_1251_HTTP_EQUIV, None);
+        assert!(html.contains("Съешь же ещё этих мягких французских булок."));
+    }
+
+    #[test]
+    fn header_charset_wins_over_guessing() {
+        let html = decode_html(ISO_8859_2_HEADER, Some("text/html; charset=ISO-8859-2"));
+        assert!(html.contains("Příliš žluťoučký kůň úpěl ďábelské ódy."));
+    }
+
+    #[test]
+    fn bom_wins_over_header_and_meta() {
+        let html = decode_html(UTF_8_BOM, Some("text/html; charset=windows-1252"));
+        assert!(html.starts_with("<html>"));
+        assert!(html.contains("Crème brûlée – naïve façade."));
+    }
+
+    #[test]
+    fn guesses_undeclared_legacy_encodings() {
+        assert_eq!(
+            detect_html_encoding(WINDOWS_1252_UNDECLARED, Some("text/html")),
+            encoding_rs::WINDOWS_1252
+        );
+        let html = decode_html(WINDOWS_1252_UNDECLARED, None);
+        assert!(html.contains("Déjà vu: les élèves français"));
+    }
+
+    #[test]
+    fn quoted_header_charset_and_utf16_labels_are_handled() {
+        assert_eq!(
+            detect_html_encoding(b"<p>x</p>", Some("text/html; charset=\"Shift_JIS\"")),
+            encoding_rs::SHIFT_JIS
+        );
+        assert_eq!(
+            detect_html_encoding(b"<meta charset=utf-16><p>x</p>", None),
+            encoding_rs::UTF_8
+        );
+    }
+
+    #[test]
+    fn decodes_xml_declaration_and_rewrites_it() {
+        let xml = decode_xml(ISO_8859_1_FEED, Some("application/rss+xml"));
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>"));
+        assert!(xml.contains("Grüße aus Köln"));
+
+        let feed = feed_rs::parser::parse(xml.as_bytes()).expect("parse decoded feed");
+        assert_eq!(
+            feed.entries[0].title.as_ref().map(|t| t.content.as_str()),
+            Some("Grüße aus Köln")
+        );
+    }
+
+    #[test]
+    fn decodes_feed_charset_from_header() {
+        let xml = decode_xml(
+            WINDOWS_1251_FEED,
+            Some("application/rss+xml; charset=windows-1251"),
+        );
+        assert!(xml.contains("Привет, мир"));
+    }
+}
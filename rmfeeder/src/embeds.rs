@@ -0,0 +1,358 @@
+use dom_query::{Document, Selection};
+use qrcode::QrCode;
+use qrcode::render::svg;
+use url::Url;
+
+use crate::escape_html;
+
+/// Elements that embed another document or a media player.
+pub const EMBED_ELEMENTS: &str = "iframe, embed, object, video, audio";
+
+/// Longest title kept for a placeholder; tweets quoted in full would crowd the box.
+const MAX_TITLE_CHARS: usize = 200;
+
+/// Kinds that are embedded as a `<blockquote>` plus a script rather than a frame.
+const QUOTED_POSTS: &[&str] = &[
+    "Post on X",
+    "Instagram post",
+    "TikTok video",
+    "Threads post",
+    "Bluesky post",
+];
+
+/// Something embedded in an article that cannot be shown on paper.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Embed {
+    /// Human-readable type, e.g. "YouTube video".
+    pub kind: &'static str,
+    pub title: Option<String>,
+    /// Where to open it: the canonical page rather than the player URL.
+    pub url: String,
+}
+
+impl Embed {
+    /// Recognize an embed from its source URL. `None` for non-web URLs.
+    pub fn from_src(src: &str, fallback_kind: &'static str, title: Option<&str>) -> Option<Self> {
+        let parsed = Url::parse(src.trim()).ok()?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return None;
+        }
+        let (kind, url) = canonical_target(&parsed).unwrap_or((fallback_kind, parsed.to_string()));
+        let title = title
+            .map(|t| t.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|t| !t.is_empty())
+            .map(|t| truncate(&t, MAX_TITLE_CHARS));
+        Some(Self { kind, title, url })
+    }
+
+    /// Boxed placeholder with the type, title, link and a QR code for the link.
+    pub fn placeholder_html(&self) -> String {
+        let qr = qr_svg(&self.url)
+            .map(|svg| format!("<div class=\"embed-qr\">{}</div>", svg))
+            .unwrap_or_default();
+        let title = self
+            .title
+            .as_deref()
+            .map(|t| format!("<span class=\"embed-title\">{}</span>", escape_html(t)))
+            .unwrap_or_default();
+        format!(
+            "<figure class=\"embed-placeholder\">{qr}<figcaption>\
+             <strong class=\"embed-kind\">{kind}</strong>{title}\
+             <a class=\"embed-url\" href=\"{url}\">{url}</a></figcaption></figure>",
+            qr = qr,
+            kind = escape_html(self.kind),
+            title = title,
+            url = escape_html(&self.url)
+        )
+    }
+}
+
+/// Map a player or widget URL to its type and the page a reader would open.
+fn canonical_target(url: &Url) -> Option<(&'static str, String)> {
+    let host = url.host_str()?.to_ascii_lowercase();
+    let host = host
+        .strip_prefix("www.")
+        .or_else(|| host.strip_prefix("m."))
+        .unwrap_or(&host);
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(|s| s.filter(|seg| !seg.is_empty()).collect())
+        .unwrap_or_default();
+    let query = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+
+    let target = match (host, segments.as_slice()) {
+        ("youtube.com" | "youtube-nocookie.com", ["embed", id, ..]) => (
+            "YouTube video",
+            format!("https://www.youtube.com/watch?v={}", id),
+        ),
+        ("youtube.com" | "youtu.be", _) => ("YouTube video", url.to_string()),
+        ("player.vimeo.com", ["video", id, ..]) => {
+            ("Vimeo video", format!("https://vimeo.com/{}", id))
+        }
+        ("vimeo.com", _) => ("Vimeo video", url.to_string()),
+        ("platform.twitter.com", _) => (
+            "Post on X",
+            format!("https://twitter.com/i/status/{}", query("id")?),
+        ),
+        ("twitter.com" | "x.com", [_, "status", ..]) => ("Post on X", url.to_string()),
+        ("open.spotify.com", ["embed", kind, id, ..]) => (
+            "Spotify",
+            format!("https://open.spotify.com/{}/{}", kind, id),
+        ),
+        ("w.soundcloud.com", _) => ("SoundCloud audio", query("url")?),
+        ("soundcloud.com", _) => ("SoundCloud audio", url.to_string()),
+        ("embed.podcasts.apple.com", _) => {
+            let mut page = url.clone();
+            page.set_host(Some("podcasts.apple.com")).ok()?;
+            ("Apple Podcasts", page.to_string())
+        }
+        ("codepen.io", [user, "embed", rest @ ..]) => {
+            let id = rest.iter().rev().find(|seg| **seg != "preview")?;
+            ("CodePen", format!("https://codepen.io/{}/pen/{}", user, id))
+        }
+        ("instagram.com", [kind @ ("p" | "reel"), id, ..]) => (
+            "Instagram post",
+            format!("https://www.instagram.com/{}/{}/", kind, id),
+        ),
+        ("tiktok.com", _) => ("TikTok video", url.to_string()),
+        ("threads.net", _) => ("Threads post", url.to_string()),
+        ("bsky.app", [_, _, "post", ..]) => ("Bluesky post", url.to_string()),
+        ("google.com", ["maps", ..]) | ("maps.google.com", _) => ("Map", url.to_string()),
+        ("docs.google.com", _) => ("Google document", url.to_string()),
+        _ => return None,
+    };
+    Some(target)
+}
+
+fn qr_svg(url: &str) -> Option<String> {
+    let code = QrCode::new(url.as_bytes()).ok()?;
+    let image = code
+        .render::<svg::Color>()
+        .min_dimensions(120, 120)
+        .quiet_zone(false)
+        .build();
+    // Inline SVG must not carry an XML declaration.
+    let start = image.find("<svg")?;
+    Some(image[start..].to_string())
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => format!("{}…", text[..idx].trim_end()),
+        None => text.to_string(),
+    }
+}
+
+fn media_src(element: &Selection) -> Option<String> {
+    element
+        .attr("src")
+        .or_else(|| element.attr("data"))
+        .or_else(|| element.attr("data-src"))
+        .or_else(|| element.select("source[src]").attr("src"))
+        .map(|src| src.to_string())
+}
+
+fn fallback_kind(element: &Selection) -> &'static str {
+    if element.is("video") {
+        "Video"
+    } else if element.is("audio") {
+        "Audio"
+    } else {
+        "Embedded content"
+    }
+}
+
+/// Turn embeds into `<p data-embed>` holders before extraction, since
+/// readability drops frames that are not from a handful of video sites.
+/// Returns `None` when the page has no embeds.
+pub fn mark_embeds(html: &str) -> Option<String> {
+    if !["<iframe", "<embed", "<object", "<video", "<audio"]
+        .iter()
+        .any(|tag| html.contains(tag))
+    {
+        return None;
+    }
+    let doc = Document::from(html);
+    let mut marked = false;
+    for element in doc.select(EMBED_ELEMENTS).iter() {
+        let Some(src) = media_src(&element) else {
+            continue;
+        };
+        let title = element
+            .attr("title")
+            .or_else(|| element.attr("aria-label"))
+            .map(|t| t.to_string())
+            .unwrap_or_default();
+        let label = if title.trim().is_empty() {
+            &src
+        } else {
+            &title
+        };
+        element.replace_with_html(format!(
+            "<p data-embed=\"{src}\" data-embed-kind=\"{kind}\" data-embed-title=\"{title}\">\
+             <a href=\"{src}\">{label}</a></p>",
+            src = escape_html(&src),
+            kind = escape_html(fallback_kind(&element)),
+            title = escape_html(&title),
+            label = escape_html(label)
+        ));
+        marked = true;
+    }
+    marked.then(|| doc.html().to_string())
+}
+
+/// Replace embeds in cleaned article HTML with printable placeholders: holders
+/// from [`mark_embeds`], frames and media elements that survived extraction,
+/// and social posts quoted as `<blockquote>`s. URLs should already be absolute.
+pub fn replace_embeds(doc: &Document) {
+    for holder in doc.select("[data-embed]").iter() {
+        let kind = match holder.attr("data-embed-kind").as_deref() {
+            Some("Video") => "Video",
+            Some("Audio") => "Audio",
+            _ => "Embedded content",
+        };
+        let title = holder.attr("data-embed-title");
+        let src = holder.attr("data-embed").unwrap_or_default();
+        match Embed::from_src(&src, kind, title.as_deref()) {
+            Some(embed) => holder.replace_with_html(embed.placeholder_html()),
+            None => holder.remove(),
+        }
+    }
+
+    for element in doc.select(EMBED_ELEMENTS).iter() {
+        let title = element.attr("title");
+        let embed = media_src(&element)
+            .and_then(|src| Embed::from_src(&src, fallback_kind(&element), title.as_deref()));
+        match embed {
+            Some(embed) => element.replace_with_html(embed.placeholder_html()),
+            None => element.remove(),
+        }
+    }
+
+    for quote in doc.select("blockquote").iter() {
+        let cite = quote.attr("cite").map(|c| c.to_string());
+        let links: Vec<String> = quote
+            .select("a[href]")
+            .iter()
+            .filter_map(|a| a.attr("href").map(|h| h.to_string()))
+            .collect();
+        // Embedded posts end with the permalink; `cite` wins when present.
+        let embed = cite
+            .iter()
+            .chain(links.iter().rev())
+            .filter_map(|href| Embed::from_src(href, "Embedded content", None))
+            .find(|embed| QUOTED_POSTS.contains(&embed.kind));
+        if let Some(mut embed) = embed {
+            let text = quote.text();
+            embed.title = Some(truncate(
+                &text.split_whitespace().collect::<Vec<_>>().join(" "),
+                MAX_TITLE_CHARS,
+            ))
+            .filter(|t| !t.is_empty());
+            quote.replace_with_html(embed.placeholder_html());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Embed, mark_embeds, replace_embeds};
+    use dom_query::Document;
+
+    fn target(src: &str) -> (&'static str, String) {
+        let embed = Embed::from_src(src, "Embedded content", None).expect("embed");
+        (embed.kind, embed.url)
+    }
+
+    #[test]
+    fn maps_players_to_their_pages() {
+        assert_eq!(
+            target("https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ?start=5"),
+            (
+                "YouTube video",
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_string()
+            )
+        );
+        assert_eq!(
+            target("https://player.vimeo.com/video/76979871"),
+            ("Vimeo video", "https://vimeo.com/76979871".to_string())
+        );
+        assert_eq!(
+            target("https://open.spotify.com/embed/episode/abc123?utm_source=x"),
+            (
+                "Spotify",
+                "https://open.spotify.com/episode/abc123".to_string()
+            )
+        );
+        assert_eq!(
+            target("https://codepen.io/chris/embed/preview/XYZ?height=300"),
+            ("CodePen", "https://codepen.io/chris/pen/XYZ".to_string())
+        );
+        assert_eq!(
+            target("https://w.soundcloud.com/player/?url=https%3A%2F%2Fsoundcloud.com%2Fa%2Fb"),
+            ("SoundCloud audio", "https://soundcloud.com/a/b".to_string())
+        );
+        assert_eq!(
+            target("https://example.com/widget"),
+            ("Embedded content", "https://example.com/widget".to_string())
+        );
+        assert!(Embed::from_src("about:blank", "Video", None).is_none());
+    }
+
+    #[test]
+    fn placeholder_has_kind_title_link_and_qr_code() {
+        let embed = Embed::from_src(
+            "https://www.youtube.com/embed/abc",
+            "Video",
+            Some("  A <great>   talk "),
+        )
+        .expect("embed");
+        let html = embed.placeholder_html();
+        assert!(
+            html.starts_with("<figure class=\"embed-placeholder\"><div class=\"embed-qr\"><svg")
+        );
+        assert!(html.contains("<strong class=\"embed-kind\">YouTube video</strong>"));
+        assert!(html.contains("<span class=\"embed-title\">A &lt;great&gt; talk</span>"));
+        assert!(html.contains("href=\"https://www.youtube.com/watch?v=abc\""));
+        assert!(!html.contains("<?xml"));
+    }
+
+    #[test]
+    fn marks_frames_before_extraction() {
+        let page = r#"<p>Intro</p><iframe src="https://codepen.io/a/embed/B" title="Demo"></iframe>
+            <video><source src="https://cdn.example/clip.mp4"></video>"#;
+        let marked = mark_embeds(page).expect("marked");
+        assert!(marked.contains(
+            "<p data-embed=\"https://codepen.io/a/embed/B\" data-embed-kind=\"Embedded content\" \
+             data-embed-title=\"Demo\"><a href=\"https://codepen.io/a/embed/B\">Demo</a></p>"
+        ));
+        assert!(marked.contains("data-embed-kind=\"Video\""));
+        assert!(mark_embeds("<p>No embeds</p>").is_none());
+    }
+
+    #[test]
+    fn replaces_holders_frames_and_quoted_posts() {
+        let doc = Document::fragment(
+            r#"<p data-embed="https://codepen.io/a/embed/B" data-embed-kind="Embedded content"
+                  data-embed-title="Demo"><a href="https://codepen.io/a/embed/B">Demo</a></p>
+               <iframe src="https://player.vimeo.com/video/1"></iframe>
+               <iframe></iframe>
+               <blockquote><p>Big news today</p>&mdash; Someone
+                 <a href="https://twitter.com/someone/status/42">May 1</a></blockquote>
+               <blockquote><p>An ordinary quote</p><a href="https://example.com/">source</a></blockquote>"#,
+        );
+        replace_embeds(&doc);
+        let html = doc.select("html").inner_html().to_string();
+        assert_eq!(html.matches("class=\"embed-placeholder\"").count(), 3);
+        assert!(html.contains("https://codepen.io/a/pen/B"));
+        assert!(html.contains("https://vimeo.com/1"));
+        assert!(html.contains("<strong class=\"embed-kind\">Post on X</strong>"));
+        assert!(html.contains("Big news today"));
+        assert!(html.contains("<p>An ordinary quote</p>"));
+        assert!(!html.contains("<iframe"));
+    }
+}
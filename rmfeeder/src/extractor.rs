@@ -1,7 +1,451 @@
+use std::fmt;
+
+use dom_query::{Document, Selection};
 use dom_smoothie::{Article, Config, Readability};
+use serde_json::Value;
+
+use crate::{embeds, escape_html, highlight, images, math};
+
+/// Extracted text shorter than this is treated as a stub and the next method is tried.
+pub const STUB_TEXT_CHARS: usize = 200;
+
+/// JSON-LD `@type`s that carry an `articleBody`.
+const ARTICLE_TYPES: &[&str] = &[
+    "Article",
+    "NewsArticle",
+    "BlogPosting",
+    "ReportageNewsArticle",
+    "AnalysisNewsArticle",
+    "OpinionNewsArticle",
+    "TechArticle",
+    "ScholarlyArticle",
+    "SocialMediaPosting",
+];
+
+/// Which step of the extraction chain produced an article.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtractionMethod {
+    SiteRule,
+    Readability,
+    JsonLd,
+    Microdata,
+    OpenGraph,
+}
+
+impl ExtractionMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::SiteRule => "site-rule",
+            Self::Readability => "readability",
+            Self::JsonLd => "json-ld",
+            Self::Microdata => "microdata",
+            Self::OpenGraph => "opengraph",
+        }
+    }
+}
+
+impl fmt::Display for ExtractionMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Extraction {
+    pub article: Article,
+    pub method: ExtractionMethod,
+}
+
+/// Rewrite the page so extraction keeps what it would otherwise drop: code
+/// block languages, math, embeds and lazy-loaded images. Every extraction path
+/// (the chain below and site rules) runs this once before selecting content.
+/// Returns `None` when nothing changed.
+pub fn prepare_page(html: &str) -> Option<String> {
+    let steps: [fn(&str) -> Option<String>; 4] = [
+        highlight::tag_code_languages,
+        math::prepare_page,
+        embeds::mark_embeds,
+        images::resolve_lazy_images,
+    ];
+    steps.iter().fold(None, |prepared, step| {
+        step(prepared.as_deref().unwrap_or(html)).or(prepared)
+    })
+}
 
+/// Readability alone, with the default config, over a page already passed
+/// through [`prepare_page`].
 pub fn extract_article(html: &str, url: Option<&str>) -> Option<Article> {
     let cfg = Config::default();
     let mut rdr = Readability::new(html, url, Some(cfg)).ok()?;
     rdr.parse().ok()
-}
\ No newline at end of file
+}
+
+/// Readability, then JSON-LD `articleBody`, then microdata, then the OpenGraph
+/// teaser. The first result that is not a stub wins; if all are stubs, the
+/// longest one is returned.
+pub fn extract(html: &str, url: Option<&str>) -> Option<Extraction> {
+    let prepared = prepare_page(html);
+    let html = prepared.as_deref().unwrap_or(html);
+    let readability = extract_article(html, url).map(|article| Extraction {
+        article,
+        method: ExtractionMethod::Readability,
+    });
+    if readability
+        .as_ref()
+        .is_some_and(|e| e.article.length >= STUB_TEXT_CHARS)
+    {
+        return readability;
+    }
+
+    let doc = Document::from(html);
+    let mut best = readability;
+    let fallbacks: [(ExtractionMethod, StructuredSource); 3] = [
+        (ExtractionMethod::JsonLd, from_json_ld),
+        (ExtractionMethod::Microdata, from_microdata),
+        (ExtractionMethod::OpenGraph, from_open_graph),
+    ];
+    for (method, extract) in fallbacks {
+        let Some(structured) = extract(&doc) else {
+            continue;
+        };
+        let candidate = Extraction {
+            article: structured.into_article(&doc, url),
+            method,
+        };
+        if candidate.article.length >= STUB_TEXT_CHARS {
+            return Some(candidate);
+        }
+        if best
+            .as_ref()
+            .is_none_or(|b| candidate.article.length > b.article.length)
+        {
+            best = Some(candidate);
+        }
+    }
+    best
+}
+
+type StructuredSource = fn(&Document) -> Option<Structured>;
+
+/// Article fields found in structured data.
+#[derive(Debug, Default)]
+struct Structured {
+    headline: Option<String>,
+    author: Option<String>,
+    published: Option<String>,
+    site_name: Option<String>,
+    content_html: String,
+    text: String,
+}
+
+impl Structured {
+    fn from_text(body: &str) -> Self {
+        // Some publishers put markup in articleBody; keep only its text.
+        let text = if body.contains('<') {
+            Document::fragment(body).select("html").text().to_string()
+        } else {
+            body.to_string()
+        };
+        let paragraphs: Vec<String> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| format!("<p>{}</p>", escape_html(line)))
+            .collect();
+        Self {
+            content_html: paragraphs.join("\n"),
+            text: collapse_whitespace(&text),
+            ..Self::default()
+        }
+    }
+
+    fn into_article(self, doc: &Document, url: Option<&str>) -> Article {
+        let title = self
+            .headline
+            .or_else(|| meta_content(doc, "meta[property=\"og:title\"]"))
+            .unwrap_or_else(|| collapse_whitespace(&doc.select("head title").text()));
+        Article {
+            title,
+            byline: self.author,
+            length: self.text.chars().count(),
+            content: self.content_html.into(),
+            text_content: self.text.into(),
+            excerpt: None,
+            site_name: self
+                .site_name
+                .or_else(|| meta_content(doc, "meta[property=\"og:site_name\"]")),
+            dir: None,
+            lang: None,
+            published_time: self.published,
+            modified_time: None,
+            image: None,
+            favicon: None,
+            url: url.map(str::to_string),
+        }
+    }
+}
+
+fn from_json_ld(doc: &Document) -> Option<Structured> {
+    doc.select("script[type=\"application/ld+json\"]")
+        .iter()
+        .filter_map(|script| serde_json::from_str::<Value>(script.text().trim()).ok())
+        .flat_map(|value| json_ld_nodes(&value))
+        .filter(|node| is_article_type(&node["@type"]))
+        .find_map(|node| {
+            let body = node["articleBody"].as_str()?.trim();
+            if body.is_empty() {
+                return None;
+            }
+            let mut structured = Structured::from_text(body);
+            structured.headline = json_string(&node["headline"]).or(json_string(&node["name"]));
+            structured.author = json_author(&node["author"]);
+            structured.published = json_string(&node["datePublished"]);
+            structured.site_name = json_string(&node["publisher"]["name"]);
+            Some(structured)
+        })
+}
+
+/// Every object in a JSON-LD document, looking into top-level arrays and `@graph`.
+fn json_ld_nodes(value: &Value) -> Vec<Value> {
+    match value {
+        Value::Array(items) => items.iter().flat_map(json_ld_nodes).collect(),
+        Value::Object(map) => {
+            let mut nodes = vec![value.clone()];
+            if let Some(graph) = map.get("@graph") {
+                nodes.extend(json_ld_nodes(graph));
+            }
+            nodes
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn is_article_type(kind: &Value) -> bool {
+    match kind {
+        Value::String(kind) => ARTICLE_TYPES.contains(&kind.as_str()),
+        Value::Array(kinds) => kinds.iter().any(is_article_type),
+        _ => false,
+    }
+}
+
+fn json_string(value: &Value) -> Option<String> {
+    let text = collapse_whitespace(value.as_str()?);
+    (!text.is_empty()).then_some(text)
+}
+
+fn json_author(value: &Value) -> Option<String> {
+    match value {
+        Value::String(_) => json_string(value),
+        Value::Object(_) => json_string(&value["name"]),
+        Value::Array(authors) => {
+            let names: Vec<String> = authors.iter().filter_map(json_author).collect();
+            (!names.is_empty()).then(|| names.join(", "))
+        }
+        _ => None,
+    }
+}
+
+fn from_microdata(doc: &Document) -> Option<Structured> {
+    let body = doc.select("[itemprop~=\"articleBody\"]");
+    let body = body.iter().next()?;
+    let text = collapse_whitespace(&body.text());
+    if text.is_empty() {
+        return None;
+    }
+    let scope = body
+        .ancestors(None)
+        .iter()
+        .find(|ancestor| ancestor.is("[itemscope]"));
+    let within = |selector: &str| -> Option<Selection> {
+        let found = match &scope {
+            Some(scope) => scope.select(selector),
+            None => doc.select(selector),
+        };
+        found.iter().next()
+    };
+    let author = within("[itemprop~=\"author\"]").and_then(|author| {
+        let name = author.select("[itemprop~=\"name\"]");
+        if name.exists() {
+            microdata_value(&name)
+        } else {
+            microdata_value(&author)
+        }
+    });
+    Some(Structured {
+        headline: within("[itemprop~=\"headline\"]").and_then(|s| microdata_value(&s)),
+        author,
+        published: within("[itemprop~=\"datePublished\"]").and_then(|s| microdata_value(&s)),
+        site_name: None,
+        content_html: body.inner_html().to_string(),
+        text,
+    })
+}
+
+/// An itemprop's value: `content`/`datetime` attributes, else its text.
+fn microdata_value(selection: &Selection) -> Option<String> {
+    let raw = selection
+        .attr("content")
+        .or_else(|| selection.attr("datetime"))
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| selection.text().to_string());
+    let value = collapse_whitespace(&raw);
+    (!value.is_empty()).then_some(value)
+}
+
+fn from_open_graph(doc: &Document) -> Option<Structured> {
+    let teaser = meta_content(doc, "meta[property=\"og:description\"]")
+        .or_else(|| meta_content(doc, "meta[name=\"description\"]"))?;
+    let mut structured = Structured::from_text(&teaser);
+    structured.author = meta_content(doc, "meta[name=\"author\"]");
+    structured.published = meta_content(doc, "meta[property=\"article:published_time\"]");
+    Some(structured)
+}
+
+fn meta_content(doc: &Document, selector: &str) -> Option<String> {
+    let content = doc.select(selector).attr("content")?;
+    let content = collapse_whitespace(&content);
+    (!content.is_empty()).then_some(content)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExtractionMethod, extract};
+
+    fn long_text(word: &str) -> String {
+        format!("{word} ").repeat(60)
+    }
+
+    #[test]
+    fn readability_wins_for_normal_articles() {
+        let html = format!(
+            "<html><head><title>Post</title></head><body><article><h1>Post</h1>\
+             <p>{}</p><p>{}</p></article></body></html>",
+            long_text("alpha"),
+            long_text("beta")
+        );
+        let extraction = extract(&html, Some("https://example.com/post")).expect("article");
+        assert_eq!(extraction.method, ExtractionMethod::Readability);
+    }
+
+    #[test]
+    fn falls_back_to_json_ld_article_body() {
+        let html = format!(
+            r#"<html><head><title>Shell</title>
+            <script type="application/ld+json">{{"@context": "https://schema.org",
+              "@graph": [{{"@type": "WebPage", "name": "Shell"}},
+                {{"@type": ["NewsArticle"], "headline": "Real Headline",
+                  "author": [{{"@type": "Person", "name": "Ada Lovelace"}}, "Charles Babbage"],
+                  "datePublished": "2024-03-01T08:00:00Z",
+                  "articleBody": "First paragraph {body}\nSecond paragraph & more"}}]}}
+            </script></head><body><div id="app"></div></body></html>"#,
+            body = long_text("word")
+        );
+        let extraction = extract(&html, None).expect("article");
+        assert_eq!(extraction.method, ExtractionMethod::JsonLd);
+        let article = extraction.article;
+        assert_eq!(article.title, "Real Headline");
+        assert_eq!(
+            article.byline.as_deref(),
+            Some("Ada Lovelace, Charles Babbage")
+        );
+        assert_eq!(
+            article.published_time.as_deref(),
+            Some("2024-03-01T08:00:00Z")
+        );
+        assert!(
+            article
+                .content
+                .contains("<p>Second paragraph &amp; more</p>")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_microdata() {
+        // Readability skips hidden nodes, so only the microdata body has the text.
+        let html = format!(
+            r#"<html><body><div itemscope itemtype="https://schema.org/BlogPosting">
+            <h2 itemprop="headline">Micro Post</h2>
+            <span itemprop="author" itemscope><span itemprop="name">Grace Hopper</span></span>
+            <time itemprop="datePublished" datetime="2023-12-24">Christmas Eve</time>
+            <nav><a href="/a">a</a> <a href="/b">b</a></nav>
+            <div itemprop="articleBody" hidden><p>{}</p></div></div></body></html>"#,
+            long_text("gamma")
+        );
+        let extraction = extract(&html, None).expect("article");
+        assert_eq!(extraction.method, ExtractionMethod::Microdata);
+        assert_eq!(extraction.article.title, "Micro Post");
+        assert_eq!(extraction.article.byline.as_deref(), Some("Grace Hopper"));
+        assert_eq!(
+            extraction.article.published_time.as_deref(),
+            Some("2023-12-24")
+        );
+    }
+
+    #[test]
+    fn open_graph_teaser_is_the_last_resort() {
+        let html = r#"<html><head><meta property="og:title" content="Teaser Title">
+            <meta property="og:description" content="A short teaser for the story.">
+            </head><body><div id="root"></div></body></html>"#;
+        let extraction = extract(html, None).expect("teaser");
+        assert_eq!(extraction.method, ExtractionMethod::OpenGraph);
+        assert_eq!(extraction.article.title, "Teaser Title");
+        assert_eq!(
+            extraction.article.content.as_ref(),
+            "<p>A short teaser for the story.</p>"
+        );
+
+        assert!(extract("<html><body></body></html>", None).is_none());
+    }
+
+    #[test]
+    fn code_block_languages_survive_readability() {
+        let html = format!(
+            "<html><head><title>Post</title></head><body><article><h1>Post</h1>\
+             <p>{}</p><pre><code class=\"language-rust\">fn main() {{}}</code></pre>\
+             <p>{}</p></article></body></html>",
+            long_text("alpha"),
+            long_text("beta")
+        );
+        let extraction = extract(&html, None).expect("article");
+        assert!(extraction.article.content.contains("data-lang=\"rust\""));
+    }
+
+    #[test]
+    fn script_math_survives_readability() {
+        let html = format!(
+            "<html><head><title>Post</title></head><body><article><h1>Post</h1>\
+             <p>{} <script type=\"math/tex\">x^2</script> {}</p></article></body></html>",
+            long_text("alpha"),
+            long_text("beta")
+        );
+        let extraction = extract(&html, None).expect("article");
+        assert!(
+            extraction
+                .article
+                .content
+                .contains("<span data-tex=\"inline\">x^2</span>")
+        );
+    }
+
+    #[test]
+    fn embedded_frames_survive_readability() {
+        let html = format!(
+            "<html><head><title>Post</title></head><body><article><h1>Post</h1>\
+             <p>{}</p><iframe src=\"https://open.spotify.com/embed/episode/abc\"></iframe>\
+             <p>{}</p></article></body></html>",
+            long_text("alpha"),
+            long_text("beta")
+        );
+        let extraction = extract(&html, None).expect("article");
+        assert!(
+            extraction
+                .article
+                .content
+                .contains("data-embed=\"https://open.spotify.com/embed/episode/abc\"")
+        );
+    }
+}
@@ -2,9 +2,19 @@ use std::cmp::Reverse;
 use std::collections::HashSet;
 
 use feed_rs::parser;
-use reqwest::blocking::Client;
 use roxmltree::{Document, Node};
 
+use crate::article_meta::ArticleMeta;
+use crate::charset;
+use crate::fetcher::Fetcher;
+
+/// A feed item's link plus the citation metadata the feed provides for it.
+#[derive(Debug, Clone)]
+pub struct FeedEntry {
+    pub url: String,
+    pub meta: ArticleMeta,
+}
+
 #[derive(Debug, Clone)]
 pub struct FeedSource {
     pub feed_url: String,
@@ -72,27 +82,97 @@ fn collect_sources(
 }
 
 pub fn fetch_feed_links(
-    client: &Client,
+    fetcher: &Fetcher,
     feed_url: &str,
     limit: usize,
 ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    let res = client.get(feed_url).send()?.error_for_status()?;
-    let bytes = res.bytes()?;
-    let feed = parser::parse(bytes.as_ref())?;
+    Ok(fetch_feed_entries(fetcher, feed_url, limit)?
+        .into_iter()
+        .map(|entry| entry.url)
+        .collect())
+}
+
+/// The newest `limit` entries of a feed, with author, date and feed title.
+pub fn fetch_feed_entries(
+    fetcher: &Fetcher,
+    feed_url: &str,
+    limit: usize,
+) -> Result<Vec<FeedEntry>, Box<dyn std::error::Error>> {
+    let res = fetcher.get(feed_url)?;
+    let xml = charset::decode_xml(&res.body, res.content_type.as_deref());
+    parse_feed_entries(xml.as_bytes(), limit)
+}
+
+fn parse_feed_entries(
+    xml: &[u8],
+    limit: usize,
+) -> Result<Vec<FeedEntry>, Box<dyn std::error::Error>> {
+    let feed = parser::parse(xml)?;
+    let feed_title = feed.title.as_ref().map(|t| t.content.trim().to_string());
+    let feed_language = feed.language.clone();
 
     let mut entries = feed.entries;
     entries.sort_by_key(|entry| Reverse(entry_timestamp(entry)));
 
     let mut out = Vec::new();
     for entry in entries.into_iter().take(limit) {
-        if let Some(link) = pick_entry_link(&entry) {
-            out.push(link);
+        if let Some(url) = pick_entry_link(&entry) {
+            let mut meta = entry_meta(&entry);
+            if meta.site_name.is_none() {
+                meta.site_name = feed_title.clone().filter(|t| !t.is_empty());
+            }
+            if meta.language.is_none() {
+                meta.language = feed_language.clone();
+            }
+            out.push(FeedEntry { url, meta });
         }
     }
 
     Ok(out)
 }
 
+fn entry_meta(entry: &feed_rs::model::Entry) -> ArticleMeta {
+    let authors: Vec<&str> = entry
+        .authors
+        .iter()
+        .map(|person| person.name.trim())
+        .filter(|name| !name.is_empty() && !name.eq_ignore_ascii_case("unknown"))
+        .collect();
+    let excerpt = entry
+        .summary
+        .as_ref()
+        .map(|summary| summary_text(&summary.content))
+        .filter(|text| !text.is_empty());
+    let lead_image = entry
+        .media
+        .iter()
+        .flat_map(|media| media.thumbnails.iter())
+        .map(|thumbnail| thumbnail.image.uri.clone())
+        .next();
+    ArticleMeta {
+        byline: (!authors.is_empty()).then(|| authors.join(", ")),
+        published: entry.published.or(entry.updated).map(|d| d.to_rfc3339()),
+        site_name: None,
+        excerpt,
+        lead_image,
+        language: entry.language.clone(),
+        archived: None,
+    }
+}
+
+/// Feed summaries are often HTML; keep their text.
+fn summary_text(summary: &str) -> String {
+    let text = if summary.contains('<') {
+        dom_query::Document::fragment(summary)
+            .select("html")
+            .text()
+            .to_string()
+    } else {
+        summary.to_string()
+    };
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
 fn entry_timestamp(entry: &feed_rs::model::Entry) -> i64 {
     entry
         .published
@@ -114,7 +194,7 @@ fn pick_entry_link(entry: &feed_rs::model::Entry) -> Option<String> {
 
 #[cfg(test)]
 mod tests {
-    use super::load_opml_feed_sources;
+    use super::{load_opml_feed_sources, parse_feed_entries};
     use std::path::PathBuf;
     use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -158,4 +238,43 @@ mod tests {
         assert_eq!(sources[2].feed_url, "https://example.com/c.xml");
         assert_eq!(sources[2].section, None);
     }
+
+    #[test]
+    fn feed_entries_carry_author_date_and_feed_title() {
+        let rss = r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:dc="http://purl.org/dc/elements/1.1/">
+  <channel>
+    <title>Example Weekly</title>
+    <language>en-gb</language>
+    <item>
+      <title>Older</title>
+      <link>https://example.com/older</link>
+      <pubDate>Mon, 04 Mar 2024 09:00:00 GMT</pubDate>
+    </item>
+    <item>
+      <title>Newer</title>
+      <link>https://example.com/newer</link>
+      <dc:creator>Ada Lovelace</dc:creator>
+      <pubDate>Tue, 05 Mar 2024 09:00:00 GMT</pubDate>
+      <description>&lt;p&gt;A &lt;b&gt;short&lt;/b&gt; teaser.&lt;/p&gt;</description>
+    </item>
+  </channel>
+</rss>"#;
+
+        let entries = parse_feed_entries(rss.as_bytes(), 5).expect("parse feed");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].url, "https://example.com/newer");
+        let meta = &entries[0].meta;
+        assert_eq!(meta.byline.as_deref(), Some("Ada Lovelace"));
+        assert_eq!(meta.site_name.as_deref(), Some("Example Weekly"));
+        assert_eq!(meta.excerpt.as_deref(), Some("A short teaser."));
+        assert_eq!(meta.language.as_deref(), Some("en-gb"));
+        assert!(
+            meta.published
+                .as_deref()
+                .unwrap_or("")
+                .starts_with("2024-03-05T09:00:00")
+        );
+        assert_eq!(entries[1].meta.byline, None);
+    }
 }
@@ -1,25 +1,706 @@
-use reqwest::blocking::Client;
-use url::Url;
 use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use reqwest::Proxy;
+use reqwest::StatusCode;
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{
+    CACHE_CONTROL, CONTENT_TYPE, COOKIE, ETAG, HeaderMap, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+    LAST_MODIFIED, LOCATION, RETRY_AFTER,
+};
+use reqwest::redirect::Policy;
+use url::Url;
+
+use crate::http_cache::{self, CachedResponse, HttpCache};
+use crate::politeness::HostScheduler;
+use crate::request_profile::{self, CookieJar, RequestProfile};
+use crate::robots::RobotsCache;
+use crate::warc::{ArchivedResponse, WarcArchive, WarcWriter};
+use crate::{AppConfig, charset, expand_tilde_path, state};
+
+pub const USER_AGENT: &str = "rmfeeder/0.1 (+https://example.com)";
+
+/// Same as reqwest's default redirect limit.
+const MAX_REDIRECTS: usize = 10;
+
+static SHARED: OnceLock<Fetcher> = OnceLock::new();
+
+/// Timeout, retry, cache and politeness knobs for the shared HTTP client.
+#[derive(Debug, Clone)]
+pub struct FetchSettings {
+    pub connect_timeout: Duration,
+    pub timeout: Duration,
+    pub max_retries: u32,
+    pub backoff_base: Duration,
+    pub backoff_max: Duration,
+    pub cache_path: Option<PathBuf>,
+    pub cache_max_bytes: u64,
+    /// Minimum spacing between requests to the same host.
+    pub host_delay: Duration,
+    pub respect_robots: bool,
+    /// Per-host overrides; the first profile whose host glob matches is used.
+    pub profiles: Vec<RequestProfile>,
+    /// Write every response to this WARC file.
+    pub record_path: Option<PathBuf>,
+    /// Serve every request from this WARC file without touching the network.
+    pub replay_path: Option<PathBuf>,
+}
+
+impl Default for FetchSettings {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(10),
+            timeout: Duration::from_secs(30),
+            max_retries: 2,
+            backoff_base: Duration::from_millis(500),
+            backoff_max: Duration::from_secs(30),
+            cache_path: None,
+            cache_max_bytes: http_cache::DEFAULT_MAX_BYTES,
+            host_delay: Duration::ZERO,
+            respect_robots: false,
+            profiles: Vec::new(),
+            record_path: None,
+            replay_path: None,
+        }
+    }
+}
+
+impl FetchSettings {
+    pub fn from_config(config: Option<&AppConfig>) -> Self {
+        let mut settings = Self::default();
+        if config.and_then(|c| c.http_cache).unwrap_or(true) {
+            settings.cache_path = default_cache_path(config);
+        }
+        let Some(config) = config else {
+            return settings;
+        };
+        if let Some(value) = config.http_connect_timeout {
+            settings.connect_timeout = Duration::from_secs(value);
+        }
+        if let Some(value) = config.http_timeout {
+            settings.timeout = Duration::from_secs(value);
+        }
+        if let Some(value) = config.http_retries {
+            settings.max_retries = value;
+        }
+        if let Some(value) = config.http_retry_backoff_ms {
+            settings.backoff_base = Duration::from_millis(value);
+        }
+        if let Some(value) = config.http_retry_max_backoff_ms {
+            settings.backoff_max = Duration::from_millis(value);
+        }
+        if let Some(value) = config.http_cache_max_mb {
+            settings.cache_max_bytes = value.saturating_mul(1024 * 1024);
+        }
+        if let Some(value) = config.delay {
+            settings.host_delay = Duration::from_secs(value);
+        }
+        if let Some(value) = config.respect_robots_txt {
+            settings.respect_robots = value;
+        }
+        if let Some(profiles) = &config.request_profile {
+            settings.profiles = profiles.clone();
+        }
+        settings
+    }
+
+    fn backoff_for(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt);
+        self.backoff_base
+            .checked_mul(factor)
+            .unwrap_or(self.backoff_max)
+            .min(self.backoff_max)
+    }
+}
+
+#[derive(Debug)]
+pub enum FetchError {
+    Status(StatusCode),
+    Request(reqwest::Error),
+    /// Replay mode and the URL was not recorded.
+    NotArchived(String),
+}
+
+impl FetchError {
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::Status(status) => Some(*status),
+            Self::Request(e) => e.status(),
+            Self::NotArchived(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Status(status) => write!(f, "HTTP {}", status),
+            Self::Request(e) => write!(f, "{}", e),
+            Self::NotArchived(url) => write!(f, "{} is not in the replay archive", url),
+        }
+    }
+}
+
+impl Error for FetchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Status(_) | Self::NotArchived(_) => None,
+            Self::Request(e) => Some(e),
+        }
+    }
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Request(e)
+    }
+}
+
+/// A successful response body along with the URL it was served from after redirects.
+#[derive(Debug, Clone)]
+pub struct FetchResponse {
+    pub url: String,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl FetchResponse {
+    /// The body decoded as HTML, honouring BOM, header and `<meta>` charsets.
+    pub fn text(&self) -> String {
+        charset::decode_html(&self.body, self.content_type.as_deref())
+    }
+}
+
+/// A client configured from one request profile.
+struct ProfileClient {
+    profile: RequestProfile,
+    client: Client,
+    cookies: Option<CookieJar>,
+}
+
+/// Pooled HTTP client shared by article and feed fetching.
+pub struct Fetcher {
+    client: Client,
+    profiles: Vec<ProfileClient>,
+    settings: FetchSettings,
+    cache: Option<Mutex<HttpCache>>,
+    scheduler: HostScheduler,
+    robots: Option<RobotsCache>,
+    recorder: Option<Mutex<WarcWriter>>,
+    replay: Option<WarcArchive>,
+    retries: AtomicUsize,
+    cache_hits: AtomicUsize,
+}
+
+impl Fetcher {
+    pub fn new(settings: FetchSettings) -> Result<Self, Box<dyn Error>> {
+        let client = build_client(&settings, USER_AGENT, HeaderMap::new(), None)?;
+        let profiles = settings
+            .profiles
+            .iter()
+            .map(|profile| {
+                // Sending a private feed's requests without its credentials
+                // would only produce confusing 401s further down.
+                let headers = profile
+                    .resolve_headers(|var| std::env::var(var).ok())
+                    .map_err(|e| {
+                        format!("request profile for {}: {}", profile.hosts.join(", "), e)
+                    })?;
+                let user_agent = profile.user_agent.as_deref().unwrap_or(USER_AGENT);
+                Ok(ProfileClient {
+                    client: build_client(&settings, user_agent, headers, profile.proxy.as_deref())?,
+                    cookies: request_profile::load_cookie_jar(profile),
+                    profile: profile.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+        let replay =
+            match &settings.replay_path {
+                Some(path) => Some(WarcArchive::load(path).map_err(|e| {
+                    format!("failed to read replay archive {}: {}", path.display(), e)
+                })?),
+                None => None,
+            };
+        let recorder = match &settings.record_path {
+            Some(path) => Some(Mutex::new(WarcWriter::create(path).map_err(|e| {
+                format!("failed to create record archive {}: {}", path.display(), e)
+            })?)),
+            None => None,
+        };
+        // Replay must not depend on (or update) the live HTTP cache.
+        let cache_path = settings.cache_path.as_deref().filter(|_| replay.is_none());
+        let cache =
+            cache_path.and_then(
+                |path| match HttpCache::open(path, settings.cache_max_bytes) {
+                    Ok(cache) => Some(Mutex::new(cache)),
+                    Err(e) => {
+                        eprintln!(
+                            "Warning: HTTP cache disabled ({}): {}",
+                            path.to_string_lossy(),
+                            e
+                        );
+                        None
+                    }
+                },
+            );
+        Ok(Self {
+            client,
+            profiles,
+            scheduler: HostScheduler::new(settings.host_delay),
+            robots: settings.respect_robots.then(RobotsCache::new),
+            settings,
+            cache,
+            recorder,
+            replay,
+            retries: AtomicUsize::new(0),
+            cache_hits: AtomicUsize::new(0),
+        })
+    }
+
+    /// Whether responses come from a WARC archive instead of the network.
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// Archive the headless-browser DOM used for `url` when recording.
+    pub fn record_rendered(&self, url: &str, html: &str) {
+        let Some(recorder) = &self.recorder else {
+            return;
+        };
+        let Ok(mut writer) = recorder.lock() else {
+            return;
+        };
+        if let Err(e) = writer.record_rendered(url, html) {
+            eprintln!("Warning: failed to record rendered page {}: {}", url, e);
+        }
+    }
+
+    /// The browser-rendered DOM recorded for `url`, when replaying.
+    pub fn replayed_render(&self, url: &str) -> Option<String> {
+        self.replay
+            .as_ref()
+            .and_then(|archive| archive.rendered(url))
+            .map(ToString::to_string)
+    }
+
+    /// GET `url`, retrying 5xx/429 responses and connection errors with backoff.
+    /// Cached responses are revalidated with `If-None-Match`/`If-Modified-Since`.
+    /// In replay mode the response comes from the archive instead of the network.
+    pub fn get(&self, url: &str) -> Result<FetchResponse, FetchError> {
+        if let Some(archive) = &self.replay {
+            return replay_response(archive, url);
+        }
+        let result = self.fetch_live(url);
+        if let Some(recorder) = &self.recorder {
+            self.record(recorder, url, &result);
+        }
+        result
+    }
+
+    fn fetch_live(&self, url: &str) -> Result<FetchResponse, FetchError> {
+        self.fetch_live_hop(url, 0)
+    }
+
+    /// `hops` counts the cross-host redirects followed so far; see [`same_host_redirects`].
+    fn fetch_live_hop(&self, url: &str, hops: usize) -> Result<FetchResponse, FetchError> {
+        let cached = self.cache_lookup(url);
+        let (client, cookies) = self.client_for(url);
+        let mut attempt = 0u32;
+        loop {
+            let mut request = client.get(url);
+            if let Some(cookie) = &cookies {
+                request = request.header(COOKIE, cookie);
+            }
+            if let Some(entry) = &cached {
+                if let Some(etag) = &entry.etag {
+                    request = request.header(IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = &entry.last_modified {
+                    request = request.header(IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+
+            self.scheduler.wait_turn(url);
+            let outcome = request.send().and_then(|res| {
+                if res.status().is_success() {
+                    read_response(res).map(Ok)
+                } else {
+                    Ok(Err(res))
+                }
+            });
+
+            let (error, wait) = match outcome {
+                Ok(Ok((response, validators))) => {
+                    self.cache_store(url, &response, validators);
+                    return Ok(response);
+                }
+                Ok(Err(res)) if res.status() == StatusCode::NOT_MODIFIED && cached.is_some() => {
+                    let entry = cached.expect("checked above");
+                    self.cache_hits.fetch_add(1, Ordering::Relaxed);
+                    self.cache_touch(url);
+                    return Ok(FetchResponse {
+                        url: entry.final_url,
+                        content_type: entry.content_type,
+                        body: entry.body,
+                    });
+                }
+                Ok(Err(res)) if res.status().is_redirection() => {
+                    // A profile client stopped before leaving the host; the next
+                    // hop goes out with whatever the new host's profile allows.
+                    return match redirect_target(&res) {
+                        Some(next) if hops < MAX_REDIRECTS => self.fetch_live_hop(&next, hops + 1),
+                        _ => Err(FetchError::Status(res.status())),
+                    };
+                }
+                Ok(Err(res)) => {
+                    let status = res.status();
+                    if !is_retryable_status(status) {
+                        return Err(FetchError::Status(status));
+                    }
+                    let wait = retry_after(&res).map(|d| d.min(self.settings.backoff_max));
+                    (FetchError::Status(status), wait)
+                }
+                Err(e) => {
+                    if !is_retryable_error(&e) {
+                        return Err(FetchError::Request(e));
+                    }
+                    (FetchError::Request(e), None)
+                }
+            };
+
+            if attempt >= self.settings.max_retries {
+                return Err(error);
+            }
+            let wait = wait.unwrap_or_else(|| self.settings.backoff_for(attempt));
+            attempt += 1;
+            self.retries.fetch_add(1, Ordering::Relaxed);
+            eprintln!(
+                "Retrying {} in {}ms (attempt {}/{}): {}",
+                url,
+                wait.as_millis(),
+                attempt,
+                self.settings.max_retries,
+                error
+            );
+            thread::sleep(wait);
+        }
+    }
+
+    pub fn fetch_html(&self, url: &str) -> Result<String, FetchError> {
+        Ok(self.get(url)?.text())
+    }
+
+    /// Whether robots.txt permits fetching `url`. Always true when checking is disabled;
+    /// a missing or unreachable robots.txt allows everything.
+    pub fn robots_allowed(&self, url: &str) -> bool {
+        let Some(robots) = &self.robots else {
+            return true;
+        };
+        robots.is_allowed(url, |robots_url| match self.get(robots_url) {
+            Ok(res) => Some(res.text()),
+            Err(FetchError::NotArchived(_)) => None,
+            Err(e) if e.status().is_some_and(|s| s.is_client_error()) => None,
+            Err(e) => {
+                eprintln!(
+                    "Warning: could not fetch {}: {}; assuming allowed",
+                    robots_url, e
+                );
+                None
+            }
+        })
+    }
+
+    /// Number of retry attempts made since this fetcher was created.
+    pub fn retry_count(&self) -> usize {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    /// Number of responses served from the cache after a `304 Not Modified`.
+    pub fn cache_hit_count(&self) -> usize {
+        self.cache_hits.load(Ordering::Relaxed)
+    }
+
+    fn record(
+        &self,
+        recorder: &Mutex<WarcWriter>,
+        url: &str,
+        result: &Result<FetchResponse, FetchError>,
+    ) {
+        let (final_url, archived) = match result {
+            Ok(response) => (
+                response.url.as_str(),
+                ArchivedResponse {
+                    status: 200,
+                    content_type: response.content_type.clone(),
+                    location: None,
+                    body: response.body.clone(),
+                },
+            ),
+            Err(FetchError::Status(status)) => (
+                url,
+                ArchivedResponse {
+                    status: status.as_u16(),
+                    content_type: None,
+                    location: None,
+                    body: Vec::new(),
+                },
+            ),
+            // Transport errors have no response to archive.
+            Err(_) => return,
+        };
+        let Ok(mut writer) = recorder.lock() else {
+            return;
+        };
+        if let Err(e) = writer.record(url, final_url, &archived) {
+            eprintln!("Warning: failed to record {}: {}", url, e);
+        }
+    }
+
+    /// The request profile whose host glob first matches `url`'s host.
+    pub fn profile_for(&self, url: &str) -> Option<&RequestProfile> {
+        self.profile_client_for(url).map(|p| &p.profile)
+    }
+
+    fn profile_client_for(&self, url: &str) -> Option<&ProfileClient> {
+        let host = Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(ToString::to_string))?;
+        self.profiles.iter().find(|p| p.profile.matches_host(&host))
+    }
+
+    /// The client and `Cookie` header for the first profile matching `url`'s host.
+    fn client_for(&self, url: &str) -> (&Client, Option<String>) {
+        match self.profile_client_for(url) {
+            Some(profile) => {
+                let now = chrono::Utc::now().timestamp();
+                let cookie = profile
+                    .cookies
+                    .as_ref()
+                    .and_then(|jar| jar.header_for(url, now));
+                (&profile.client, cookie)
+            }
+            None => (&self.client, None),
+        }
+    }
+
+    fn cache_lookup(&self, url: &str) -> Option<CachedResponse> {
+        let cache = self.cache.as_ref()?.lock().ok()?;
+        match cache.lookup(url) {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("Warning: HTTP cache lookup failed for {}: {}", url, e);
+                None
+            }
+        }
+    }
+
+    fn cache_touch(&self, url: &str) {
+        if let Some(cache) = self.cache.as_ref().and_then(|c| c.lock().ok())
+            && let Err(e) = cache.touch(url)
+        {
+            eprintln!("Warning: HTTP cache update failed for {}: {}", url, e);
+        }
+    }
+
+    fn cache_store(&self, url: &str, response: &FetchResponse, validators: Validators) {
+        if validators.no_store || (validators.etag.is_none() && validators.last_modified.is_none())
+        {
+            return;
+        }
+        let Some(cache) = self.cache.as_ref().and_then(|c| c.lock().ok()) else {
+            return;
+        };
+        let entry = CachedResponse {
+            final_url: response.url.clone(),
+            content_type: response.content_type.clone(),
+            etag: validators.etag,
+            last_modified: validators.last_modified,
+            body: response.body.clone(),
+        };
+        if let Err(e) = cache.store(url, &entry) {
+            eprintln!("Warning: HTTP cache write failed for {}: {}", url, e);
+        }
+    }
+}
+
+/// Install the process-wide fetcher. Later calls keep the first instance.
+pub fn install(settings: FetchSettings) -> Result<&'static Fetcher, Box<dyn Error>> {
+    if let Some(existing) = SHARED.get() {
+        return Ok(existing);
+    }
+    let fetcher = Fetcher::new(settings)?;
+    Ok(SHARED.get_or_init(|| fetcher))
+}
+
+/// The process-wide fetcher, built with default settings if none was installed.
+pub fn shared() -> &'static Fetcher {
+    SHARED.get_or_init(|| {
+        Fetcher::new(FetchSettings::default()).expect("failed to build default HTTP client")
+    })
+}
 
 pub fn normalize_url(input: &str) -> Result<String, Box<dyn Error>> {
     let parsed = Url::parse(input)?;
     Ok(parsed.into())
 }
 
-pub fn fetch_html(url: &str) -> Result<String, reqwest::Error> {
-    let client = Client::builder()
-        .user_agent("rmfeeder/0.1 (+https://example.com)")
-        .build()?;
+pub fn fetch_html(url: &str) -> Result<String, FetchError> {
+    shared().fetch_html(url)
+}
+
+fn replay_response(archive: &WarcArchive, url: &str) -> Result<FetchResponse, FetchError> {
+    let (final_url, response) = archive
+        .lookup(url)
+        .ok_or_else(|| FetchError::NotArchived(url.to_string()))?;
+    let status = StatusCode::from_u16(response.status).unwrap_or(StatusCode::BAD_GATEWAY);
+    if !status.is_success() {
+        return Err(FetchError::Status(status));
+    }
+    Ok(FetchResponse {
+        url: final_url,
+        content_type: response.content_type.clone(),
+        body: response.body.clone(),
+    })
+}
+
+/// Clients carrying request profile `headers` only follow redirects within the
+/// host, so custom credential headers such as `X-Api-Key` never reach another
+/// site (reqwest itself only strips `Authorization` and `Cookie`).
+fn build_client(
+    settings: &FetchSettings,
+    user_agent: &str,
+    headers: HeaderMap,
+    proxy: Option<&str>,
+) -> Result<Client, reqwest::Error> {
+    let redirect = if headers.is_empty() {
+        Policy::limited(MAX_REDIRECTS)
+    } else {
+        same_host_redirects()
+    };
+    let mut builder = Client::builder()
+        .user_agent(user_agent)
+        .default_headers(headers)
+        .redirect(redirect)
+        .connect_timeout(settings.connect_timeout)
+        .timeout(settings.timeout);
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+    builder.build()
+}
+
+/// Follow redirects while the host stays the same; stop (returning the 3xx
+/// response) when it changes.
+fn same_host_redirects() -> Policy {
+    Policy::custom(|attempt| {
+        let origin = attempt.previous().first().and_then(|u| u.host_str());
+        if attempt.previous().len() > MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else if attempt.url().host_str() != origin {
+            attempt.stop()
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// The absolute `Location` of a redirect response.
+fn redirect_target(res: &Response) -> Option<String> {
+    let location = res.headers().get(LOCATION)?.to_str().ok()?;
+    res.url().join(location).ok().map(String::from)
+}
+
+fn default_cache_path(config: Option<&AppConfig>) -> Option<PathBuf> {
+    if let Some(path) = config.and_then(|c| c.http_cache_path.as_deref()) {
+        return Some(expand_tilde_path(path));
+    }
+    let state_path = match config.and_then(|c| c.state_db_path.as_deref()) {
+        Some(path) => expand_tilde_path(path),
+        None => state::default_state_path().ok()?,
+    };
+    Some(http_cache::cache_path_for_state(&state_path))
+}
+
+/// Cache-relevant response headers.
+struct Validators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    no_store: bool,
+}
+
+fn read_response(res: Response) -> Result<(FetchResponse, Validators), reqwest::Error> {
+    let url = res.url().to_string();
+    let header = |name| {
+        res.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(ToString::to_string)
+    };
+    let content_type = header(CONTENT_TYPE);
+    let validators = Validators {
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+        no_store: header(CACHE_CONTROL)
+            .is_some_and(|v| v.to_ascii_lowercase().contains("no-store")),
+    };
+    let body = res.bytes()?.to_vec();
+    Ok((
+        FetchResponse {
+            url,
+            content_type,
+            body,
+        },
+        validators,
+    ))
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Only transient transport failures; invalid URLs and redirect-policy
+/// errors fail the same way on every attempt.
+fn is_retryable_error(e: &reqwest::Error) -> bool {
+    e.is_connect() || e.is_timeout() || e.is_body()
+}
 
-    let res = client.get(url).send()?.error_for_status()?;
-    let body = res.text()?;
-    Ok(body)
+fn retry_after(res: &Response) -> Option<Duration> {
+    let value = res.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    parse_retry_after(value, chrono::Utc::now())
+}
+
+fn parse_retry_after(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delta = at.with_timezone(&chrono::Utc) - now;
+    Some(delta.to_std().unwrap_or(Duration::ZERO))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::normalize_url;
+    use super::{FetchError, FetchSettings, Fetcher, USER_AGENT, normalize_url, parse_retry_after};
+    use crate::request_profile::RequestProfile;
+    use crate::test_support::{TestResponse, TestServer};
+    use reqwest::StatusCode;
+    use std::collections::BTreeMap;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    fn fast_settings(max_retries: u32) -> FetchSettings {
+        FetchSettings {
+            max_retries,
+            backoff_base: Duration::from_millis(1),
+            backoff_max: Duration::from_millis(5),
+            ..FetchSettings::default()
+        }
+    }
 
     #[test]
     fn normalize_url_accepts_valid_http_urls() {
@@ -31,4 +712,320 @@ mod tests {
     fn normalize_url_rejects_invalid_urls() {
         assert!(normalize_url("not a url").is_err());
     }
+
+    #[test]
+    fn retries_server_errors_until_success() {
+        let server = TestServer::sequence(vec![
+            TestResponse::status(502),
+            TestResponse::status(503).with_header("Retry-After", "0"),
+            TestResponse::ok("text/html", "<p>ok</p>"),
+        ]);
+        let fetcher = Fetcher::new(fast_settings(3)).expect("client");
+
+        let body = fetcher
+            .fetch_html(&server.url("/a"))
+            .expect("fetch succeeds");
+        assert_eq!(body, "<p>ok</p>");
+        assert_eq!(fetcher.retry_count(), 2);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests.iter().all(|r| r.path == "/a"));
+        assert_eq!(requests[0].header("user-agent"), Some(USER_AGENT));
+    }
+
+    #[test]
+    fn gives_up_after_configured_retries() {
+        let server = TestServer::sequence(vec![TestResponse::status(429)]);
+        let fetcher = Fetcher::new(fast_settings(1)).expect("client");
+
+        let err = fetcher
+            .fetch_html(&server.url("/a"))
+            .expect_err("fetch fails");
+        assert_eq!(err.status(), Some(StatusCode::TOO_MANY_REQUESTS));
+        assert_eq!(fetcher.retry_count(), 1);
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn client_errors_are_not_retried() {
+        let server = TestServer::sequence(vec![TestResponse::status(404)]);
+        let fetcher = Fetcher::new(fast_settings(3)).expect("client");
+
+        let err = fetcher
+            .fetch_html(&server.url("/a"))
+            .expect_err("fetch fails");
+        assert_eq!(err.status(), Some(StatusCode::NOT_FOUND));
+        assert_eq!(fetcher.retry_count(), 0);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn redirect_loops_are_not_retried() {
+        let server = TestServer::start(|_| TestResponse::status(302).with_header("Location", "/a"));
+        let fetcher = Fetcher::new(fast_settings(3)).expect("client");
+
+        fetcher
+            .fetch_html(&server.url("/a"))
+            .expect_err("fetch fails");
+        assert_eq!(fetcher.retry_count(), 0);
+    }
+
+    #[test]
+    fn revalidates_cached_responses_with_conditional_get() {
+        let server = TestServer::start(|req| {
+            if req.header("if-none-match") == Some("\"v1\"") {
+                TestResponse::status(304)
+            } else {
+                TestResponse::ok("text/html", "<p>cached</p>").with_header("ETag", "\"v1\"")
+            }
+        });
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let cache_path = std::env::temp_dir().join(format!("rmfeeder-fetch-cache-{nanos}.sqlite"));
+        let settings = FetchSettings {
+            cache_path: Some(cache_path.clone()),
+            ..fast_settings(0)
+        };
+
+        let first = Fetcher::new(settings.clone()).expect("client");
+        assert_eq!(
+            first.fetch_html(&server.url("/a")).expect("fetch"),
+            "<p>cached</p>"
+        );
+        assert_eq!(first.cache_hit_count(), 0);
+        drop(first);
+
+        let second = Fetcher::new(settings).expect("client");
+        assert_eq!(
+            second.fetch_html(&server.url("/a")).expect("fetch"),
+            "<p>cached</p>"
+        );
+        assert_eq!(second.cache_hit_count(), 1);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].header("if-none-match"), None);
+        assert_eq!(requests[1].header("if-none-match"), Some("\"v1\""));
+
+        std::fs::remove_file(cache_path).ok();
+    }
+
+    #[test]
+    fn fetch_html_decodes_meta_declared_charsets() {
+        let body = include_bytes!("../../testdata/charset/shift_jis-meta.html").to_vec();
+        let server = TestServer::sequence(vec![TestResponse::ok("text/html", body)]);
+        let fetcher = Fetcher::new(fast_settings(0)).expect("client");
+
+        let html = fetcher.fetch_html(&server.url("/ja")).expect("fetch");
+        assert!(html.contains("<title>日本語の記事</title>"));
+    }
+
+    #[test]
+    fn matching_request_profile_sets_agent_headers_and_cookies() {
+        let server = TestServer::sequence(vec![TestResponse::ok("text/html", "<p>ok</p>")]);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let cookie_path = std::env::temp_dir().join(format!("rmfeeder-cookies-{nanos}.txt"));
+        std::fs::write(
+            &cookie_path,
+            "127.0.0.1\tFALSE\t/\tFALSE\t0\tsubscriber\t42\n",
+        )
+        .expect("write cookie file");
+
+        let settings = FetchSettings {
+            profiles: vec![
+                RequestProfile {
+                    hosts: vec!["unrelated.example".to_string()],
+                    user_agent: Some("wrong".to_string()),
+                    ..RequestProfile::default()
+                },
+                RequestProfile {
+                    hosts: vec!["127.0.0.*".to_string()],
+                    user_agent: Some("Mozilla/5.0 test".to_string()),
+                    headers: Some(BTreeMap::from([(
+                        "X-Feed-Key".to_string(),
+                        "abc".to_string(),
+                    )])),
+                    cookie_file: Some(cookie_path.to_string_lossy().to_string()),
+                    ..RequestProfile::default()
+                },
+            ],
+            ..fast_settings(0)
+        };
+        let fetcher = Fetcher::new(settings).expect("client");
+        fetcher.fetch_html(&server.url("/a")).expect("fetch");
+
+        let requests = server.requests();
+        assert_eq!(requests[0].header("user-agent"), Some("Mozilla/5.0 test"));
+        assert_eq!(requests[0].header("x-feed-key"), Some("abc"));
+        assert_eq!(requests[0].header("cookie"), Some("subscriber=42"));
+
+        std::fs::remove_file(cookie_path).ok();
+    }
+
+    #[test]
+    fn profile_headers_stay_on_the_profile_host_across_redirects() {
+        let other = TestServer::sequence(vec![TestResponse::ok("text/html", "<p>moved</p>")]);
+        // `localhost` is a different host from the profile's `127.0.0.*`.
+        let target = other.url("/landing").replace("127.0.0.1", "localhost");
+        let origin = TestServer::start(move |req| match req.path.as_str() {
+            "/old" => TestResponse::status(301).with_header("Location", "/new"),
+            _ => TestResponse::status(302).with_header("Location", &target),
+        });
+        let settings = FetchSettings {
+            profiles: vec![RequestProfile {
+                hosts: vec!["127.0.0.*".to_string()],
+                headers: Some(BTreeMap::from([(
+                    "X-Api-Key".to_string(),
+                    "secret".to_string(),
+                )])),
+                ..RequestProfile::default()
+            }],
+            ..fast_settings(0)
+        };
+        let fetcher = Fetcher::new(settings).expect("client");
+        let response = fetcher.get(&origin.url("/old")).expect("fetch");
+        assert_eq!(response.text(), "<p>moved</p>");
+        assert!(response.url.starts_with("http://localhost:"));
+
+        let same_host = origin.requests();
+        assert_eq!(same_host.len(), 2);
+        assert!(
+            same_host
+                .iter()
+                .all(|req| req.header("x-api-key") == Some("secret"))
+        );
+        let requests = other.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].header("x-api-key"), None);
+        assert_eq!(requests[0].header("user-agent"), Some(USER_AGENT));
+    }
+
+    #[test]
+    fn missing_profile_credentials_fail_client_setup() {
+        let settings = FetchSettings {
+            profiles: vec![RequestProfile {
+                hosts: vec!["private.example".to_string()],
+                headers: Some(BTreeMap::from([(
+                    "X-Feed-Key".to_string(),
+                    "abc".to_string(),
+                )])),
+                token_env: Some("RMFEEDER_TEST_UNSET_TOKEN".to_string()),
+                ..RequestProfile::default()
+            }],
+            ..fast_settings(0)
+        };
+        let err = Fetcher::new(settings)
+            .err()
+            .expect("setup fails")
+            .to_string();
+        assert!(err.contains("private.example"));
+        assert!(err.contains("RMFEEDER_TEST_UNSET_TOKEN is not set"));
+    }
+
+    #[test]
+    fn replays_recorded_responses_without_the_network() {
+        let server = TestServer::start(|req| match req.path.as_str() {
+            "/article" => TestResponse::ok("text/html; charset=utf-8", "<p>recorded</p>"),
+            _ => TestResponse::status(404),
+        });
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let archive = std::env::temp_dir().join(format!("rmfeeder-record-{nanos}.warc"));
+
+        let recorder = Fetcher::new(FetchSettings {
+            record_path: Some(archive.clone()),
+            ..fast_settings(0)
+        })
+        .expect("client");
+        recorder.fetch_html(&server.url("/article")).expect("fetch");
+        recorder
+            .fetch_html(&server.url("/gone"))
+            .expect_err("404 is recorded as a failure");
+        drop(recorder);
+        let live_requests = server.requests().len();
+
+        let replayer = Fetcher::new(FetchSettings {
+            replay_path: Some(archive.clone()),
+            ..fast_settings(0)
+        })
+        .expect("client");
+        assert_eq!(
+            replayer
+                .fetch_html(&server.url("/article"))
+                .expect("replay"),
+            "<p>recorded</p>"
+        );
+        let err = replayer
+            .fetch_html(&server.url("/gone"))
+            .expect_err("replayed 404");
+        assert_eq!(err.status(), Some(StatusCode::NOT_FOUND));
+        assert!(matches!(
+            replayer.fetch_html(&server.url("/never")),
+            Err(FetchError::NotArchived(_))
+        ));
+        assert_eq!(server.requests().len(), live_requests);
+
+        std::fs::remove_file(archive).ok();
+    }
+
+    #[test]
+    fn robots_txt_is_checked_when_enabled() {
+        let server = TestServer::start(|req| match req.path.as_str() {
+            "/robots.txt" => TestResponse::ok("text/plain", "User-agent: *\nDisallow: /members/"),
+            _ => TestResponse::ok("text/html", "<p>ok</p>"),
+        });
+        let settings = FetchSettings {
+            respect_robots: true,
+            ..fast_settings(0)
+        };
+        let fetcher = Fetcher::new(settings).expect("client");
+
+        assert!(fetcher.robots_allowed(&server.url("/posts/1")));
+        assert!(!fetcher.robots_allowed(&server.url("/members/2")));
+        assert_eq!(server.requests().len(), 1);
+
+        let lenient = Fetcher::new(fast_settings(0)).expect("client");
+        assert!(lenient.robots_allowed(&server.url("/members/2")));
+    }
+
+    #[test]
+    fn parses_retry_after_seconds_and_http_dates() {
+        let now = chrono::DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT")
+            .expect("date")
+            .with_timezone(&chrono::Utc);
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_and_is_capped() {
+        let settings = FetchSettings {
+            backoff_base: Duration::from_millis(100),
+            backoff_max: Duration::from_millis(350),
+            ..FetchSettings::default()
+        };
+        assert_eq!(settings.backoff_for(0), Duration::from_millis(100));
+        assert_eq!(settings.backoff_for(1), Duration::from_millis(200));
+        assert_eq!(settings.backoff_for(2), Duration::from_millis(350));
+        assert_eq!(settings.backoff_for(40), Duration::from_millis(350));
+    }
 }
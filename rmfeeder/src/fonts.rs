@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+
+use crate::AppConfig;
+use crate::language::primary;
+
+const SERIF: &str = r#""Charter", "Noto Serif", "DejaVu Serif", serif"#;
+const SANS: &str = r#""Avenir Next", -apple-system, "Helvetica Neue", "Noto Sans", "DejaVu Sans", Helvetica, Arial, sans-serif"#;
+const MONO: &str = r#""PT Mono", Menlo, "DejaVu Sans Mono", Consolas, monospace"#;
+
+/// Monochrome emoji first so e-ink output doesn't depend on a colour font.
+const EMOJI: &str = r#""Noto Emoji", "Noto Color Emoji", "Apple Color Emoji", "Segoe UI Emoji""#;
+
+/// Per-language fonts, tried before the Latin stack. CJK needs them per
+/// language because Han characters share code points but not glyph shapes.
+const SCRIPTS: &[(&str, &str)] = &[
+    (
+        "ar",
+        r#""Noto Naskh Arabic", "Noto Sans Arabic", "Geeza Pro", "Amiri""#,
+    ),
+    (
+        "fa",
+        r#""Noto Naskh Arabic", "Noto Sans Arabic", "Geeza Pro", "Vazirmatn""#,
+    ),
+    (
+        "he",
+        r#""Noto Serif Hebrew", "Noto Sans Hebrew", "Arial Hebrew""#,
+    ),
+    (
+        "hi",
+        r#""Noto Serif Devanagari", "Noto Sans Devanagari", "Kohinoor Devanagari""#,
+    ),
+    (
+        "ja",
+        r#""Noto Serif CJK JP", "Noto Sans CJK JP", "Hiragino Mincho ProN", "Source Han Serif JP""#,
+    ),
+    (
+        "ko",
+        r#""Noto Serif CJK KR", "Noto Sans CJK KR", "AppleMyungjo", "Source Han Serif KR""#,
+    ),
+    ("th", r#""Noto Serif Thai", "Noto Sans Thai", "Thonburi""#),
+    (
+        "ur",
+        r#""Noto Nastaliq Urdu", "Noto Naskh Arabic", "Geeza Pro""#,
+    ),
+    (
+        "yi",
+        r#""Noto Serif Hebrew", "Noto Sans Hebrew", "Arial Hebrew""#,
+    ),
+    (
+        "zh",
+        r#""Noto Serif CJK SC", "Noto Sans CJK SC", "Songti SC", "Source Han Serif SC""#,
+    ),
+];
+
+/// Font families for body text, headings and code, plus per-language
+/// fallbacks. Values are CSS `font-family` lists.
+#[derive(Debug, Clone)]
+pub struct FontStacks {
+    pub serif: String,
+    pub sans: String,
+    pub mono: String,
+    /// Keyed by primary language subtag (`ja`, `ar`, ...).
+    pub scripts: BTreeMap<String, String>,
+}
+
+impl Default for FontStacks {
+    fn default() -> Self {
+        Self {
+            serif: SERIF.to_string(),
+            sans: SANS.to_string(),
+            mono: MONO.to_string(),
+            scripts: SCRIPTS
+                .iter()
+                .map(|(lang, fonts)| (lang.to_string(), fonts.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl FontStacks {
+    /// Defaults overridden by `font_serif`, `font_sans`, `font_mono` and
+    /// `font_scripts` from the config.
+    pub fn from_config(config: Option<&AppConfig>) -> Self {
+        let mut stacks = Self::default();
+        let Some(config) = config else {
+            return stacks;
+        };
+        for (value, field) in [
+            (&config.font_serif, &mut stacks.serif),
+            (&config.font_sans, &mut stacks.sans),
+            (&config.font_mono, &mut stacks.mono),
+        ] {
+            if let Some(value) = value.as_deref().and_then(clean_family) {
+                *field = value;
+            }
+        }
+        for (lang, fonts) in config.font_scripts.iter().flatten() {
+            let lang = primary(&lang.trim().to_ascii_lowercase()).to_string();
+            match clean_family(fonts) {
+                Some(fonts) => stacks.scripts.insert(lang, fonts),
+                None => stacks.scripts.remove(&lang),
+            };
+        }
+        stacks
+    }
+
+    /// Stylesheet setting the families, appended after the base CSS.
+    pub fn css(&self) -> String {
+        let mut css = format!(
+            "body {{ font-family: {serif}; }}\n\
+             h1, h2, h3, h4, h5, h6, .toc-list li.toc-section {{ font-family: {sans}; }}\n\
+             code, pre {{ font-family: {mono}; }}\n",
+            serif = with_emoji(&self.serif),
+            sans = with_emoji(&self.sans),
+            mono = self.mono
+        );
+        for (lang, fonts) in &self.scripts {
+            let scope = format!("[lang|=\"{}\"]", lang);
+            let headings = ["h1", "h2", "h3", "h4", "h5", "h6"]
+                .iter()
+                .map(|h| format!("{} {}", scope, h))
+                .collect::<Vec<_>>()
+                .join(", ");
+            css.push_str(&format!(
+                "{scope} {{ font-family: {serif}; }}\n\
+                 {headings} {{ font-family: {sans}; }}\n",
+                scope = scope,
+                headings = headings,
+                serif = with_emoji(&format!("{}, {}", fonts, self.serif)),
+                sans = with_emoji(&format!("{}, {}", fonts, self.sans))
+            ));
+        }
+        css
+    }
+}
+
+/// Insert the emoji families before a trailing generic family. A generic
+/// keyword always matches, so anything listed after it is never used.
+fn with_emoji(stack: &str) -> String {
+    let (named, generic) = match stack.rsplit_once(',') {
+        Some((named, last)) if is_generic(last) => (named.trim_end(), Some(last.trim())),
+        _ if is_generic(stack) => ("", Some(stack.trim())),
+        _ => (stack.trim_end(), None),
+    };
+    let mut families: Vec<&str> = Vec::new();
+    if !named.is_empty() {
+        families.push(named);
+    }
+    families.push(EMOJI);
+    families.extend(generic);
+    families.join(", ")
+}
+
+fn is_generic(family: &str) -> bool {
+    matches!(
+        family.trim().to_ascii_lowercase().as_str(),
+        "serif"
+            | "sans-serif"
+            | "monospace"
+            | "cursive"
+            | "fantasy"
+            | "system-ui"
+            | "ui-serif"
+            | "ui-sans-serif"
+            | "ui-monospace"
+            | "ui-rounded"
+            | "emoji"
+            | "math"
+            | "fangsong"
+    )
+}
+
+/// A config font list, or `None` when empty. Characters that could end the
+/// declaration or the `<style>` element are dropped.
+fn clean_family(value: &str) -> Option<String> {
+    let cleaned: String = value
+        .chars()
+        .filter(|c| !matches!(c, ';' | '{' | '}' | '<' | '>'))
+        .collect();
+    let cleaned = cleaned.trim();
+    (!cleaned.is_empty()).then(|| cleaned.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EMOJI, FontStacks, with_emoji};
+    use crate::AppConfig;
+
+    #[test]
+    fn default_stacks_cover_cjk_and_rtl_scripts() {
+        let css = FontStacks::default().css();
+        assert!(css.contains("body { font-family: \"Charter\""));
+        assert!(css.contains("[lang|=\"ja\"] { font-family: \"Noto Serif CJK JP\""));
+        assert!(css.contains("[lang|=\"ar\"] { font-family: \"Noto Naskh Arabic\""));
+        assert!(css.contains(&format!("\"DejaVu Serif\", {}, serif; }}", EMOJI)));
+        assert!(css.contains(&format!(
+            "\"DejaVu Sans\", Helvetica, Arial, {}, sans-serif; }}",
+            EMOJI
+        )));
+    }
+
+    #[test]
+    fn emoji_families_come_before_the_generic_family() {
+        assert_eq!(
+            with_emoji("\"A\", serif"),
+            format!("\"A\", {}, serif", EMOJI)
+        );
+        assert_eq!(with_emoji("sans-serif"), format!("{}, sans-serif", EMOJI));
+        assert_eq!(
+            with_emoji("\"A\", \"B\""),
+            format!("\"A\", \"B\", {}", EMOJI)
+        );
+    }
+
+    #[test]
+    fn config_overrides_families() {
+        let config: AppConfig = toml::from_str(
+            r#"
+font_serif = "\"Source Serif 4\", serif; }"
+font_scripts = { ja = "\"IPAexMincho\"", he = "" }
+"#,
+        )
+        .expect("config");
+        let stacks = FontStacks::from_config(Some(&config));
+        assert_eq!(stacks.serif, "\"Source Serif 4\", serif");
+        assert_eq!(stacks.scripts["ja"], "\"IPAexMincho\"");
+        assert!(!stacks.scripts.contains_key("he"));
+        assert!(stacks.scripts.contains_key("zh"));
+    }
+}
@@ -0,0 +1,202 @@
+use std::sync::OnceLock;
+
+use dom_query::{Document, Selection};
+use syntect::html::{ClassStyle, ClassedHTMLGenerator};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+use crate::{PageSize, escape_html};
+
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+
+/// Code blocks whose language can be read from markup.
+const CODE_BLOCKS: &str = "pre";
+
+/// Grayscale theme for e-ink: emphasis instead of colour.
+const GRAYSCALE_CSS: &str = r#"
+pre.highlight .hl-comment { font-style: italic; color: #555; }
+pre.highlight .hl-keyword, pre.highlight .hl-storage { font-weight: bold; }
+pre.highlight .hl-string { text-decoration: underline dotted #888; }
+pre.highlight .hl-entity.hl-name { font-weight: bold; font-style: italic; }
+pre.highlight .hl-constant.hl-numeric, pre.highlight .hl-constant.hl-language { font-weight: bold; color: #333; }
+pre.highlight .hl-invalid { text-decoration: line-through; }
+"#;
+
+/// Colour theme for colour targets.
+const COLOR_CSS: &str = r#"
+pre.highlight .hl-comment { font-style: italic; color: #6a737d; }
+pre.highlight .hl-keyword, pre.highlight .hl-storage { color: #d73a49; }
+pre.highlight .hl-string { color: #032f62; }
+pre.highlight .hl-entity.hl-name { color: #6f42c1; }
+pre.highlight .hl-constant, pre.highlight .hl-support { color: #005cc5; }
+pre.highlight .hl-variable.hl-parameter { color: #e36209; }
+pre.highlight .hl-invalid { color: #b31d28; }
+"#;
+
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+/// Stylesheet for highlighted code on `page_size`.
+pub fn theme_css(page_size: PageSize) -> &'static str {
+    if page_size.is_color() {
+        COLOR_CSS
+    } else {
+        GRAYSCALE_CSS
+    }
+}
+
+/// Highlight `code` as class-based HTML. `None` when the language is unknown.
+pub fn highlight_code(code: &str, lang: Option<&str>) -> Option<String> {
+    let syntaxes = syntax_set();
+    let syntax = find_syntax(syntaxes, lang, code)?;
+    let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, syntaxes, CLASS_STYLE);
+    for line in LinesWithEndings::from(code) {
+        generator
+            .parse_html_for_line_which_includes_newline(line)
+            .ok()?;
+    }
+    Some(generator.finalize())
+}
+
+fn find_syntax<'a>(
+    syntaxes: &'a SyntaxSet,
+    lang: Option<&str>,
+    code: &str,
+) -> Option<&'a SyntaxReference> {
+    let syntax = match lang {
+        Some(lang) => syntaxes.find_syntax_by_token(lang),
+        // Shebangs and `<?php` openers are safe to go on; anything else stays plain.
+        None => syntaxes.find_syntax_by_first_line(code),
+    }?;
+    (syntax.name != "Plain Text").then_some(syntax)
+}
+
+/// Language named by a code block's markup: `language-x`/`lang-x` classes
+/// (on the `<pre>` or its `<code>`), GitHub's `highlight-source-x`, or a
+/// `data-lang`/`data-language` attribute.
+fn block_language(pre: &Selection) -> Option<String> {
+    let code = pre.select("code");
+    for element in [pre, &code] {
+        for attr in ["data-lang", "data-language"] {
+            if let Some(value) = element.attr(attr).filter(|v| !v.trim().is_empty()) {
+                return Some(value.trim().to_ascii_lowercase());
+            }
+        }
+        let classes = element.attr("class").unwrap_or_default();
+        for class in classes.split_whitespace() {
+            let lang = class
+                .strip_prefix("language-")
+                .or_else(|| class.strip_prefix("lang-"))
+                .or_else(|| class.strip_prefix("highlight-source-"));
+            if let Some(lang) = lang.filter(|l| !l.is_empty()) {
+                return Some(lang.to_ascii_lowercase());
+            }
+        }
+    }
+    // GitHub puts the class on a wrapping div.
+    pre.parent().attr("class").and_then(|classes| {
+        classes
+            .split_whitespace()
+            .find_map(|class| class.strip_prefix("highlight-source-"))
+            .map(str::to_ascii_lowercase)
+    })
+}
+
+/// Copy code block languages to `data-lang` on each `<pre>`. Readability drops
+/// class attributes, so this runs on the page before extraction. Returns `None`
+/// when the page has no tagged code blocks.
+pub fn tag_code_languages(html: &str) -> Option<String> {
+    if !["language-", "lang-", "highlight-source-"]
+        .iter()
+        .any(|marker| html.contains(marker))
+    {
+        return None;
+    }
+    let doc = Document::from(html);
+    let mut tagged = false;
+    for pre in doc.select(CODE_BLOCKS).iter() {
+        if pre.has_attr("data-lang") {
+            continue;
+        }
+        if let Some(lang) = block_language(&pre) {
+            pre.set_attr("data-lang", &lang);
+            tagged = true;
+        }
+    }
+    tagged.then(|| doc.html().to_string())
+}
+
+/// Highlight every `<pre>` block in `html` whose language is known, replacing
+/// its contents with `<code>` holding the highlighted spans.
+pub fn highlight_html(html: &str) -> String {
+    if !html.contains("<pre") {
+        return html.to_string();
+    }
+    let doc = Document::fragment(html);
+    let mut changed = false;
+    for pre in doc.select(CODE_BLOCKS).iter() {
+        let lang = block_language(&pre);
+        let code = pre.text();
+        let Some(highlighted) = highlight_code(&code, lang.as_deref()) else {
+            continue;
+        };
+        let lang_class = lang
+            .map(|lang| format!(" class=\"language-{}\"", escape_html(&lang)))
+            .unwrap_or_default();
+        pre.set_html(format!("<code{}>{}</code>", lang_class, highlighted));
+        pre.add_class("highlight");
+        changed = true;
+    }
+    if !changed {
+        return html.to_string();
+    }
+    doc.select("html").inner_html().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{highlight_code, highlight_html, tag_code_languages, theme_css};
+    use crate::PageSize;
+
+    #[test]
+    fn highlights_known_languages_with_classes() {
+        let html = highlight_code("fn main() {}\n", Some("rust")).expect("rust");
+        assert!(html.contains("<span class=\"hl-source hl-rust\">"));
+        assert!(html.contains("hl-storage hl-type hl-function"));
+        assert!(highlight_code("plain words\n", Some("no-such-language")).is_none());
+        assert!(highlight_code("plain words\n", None).is_none());
+        assert!(highlight_code("#!/bin/sh\necho hi\n", None).is_some());
+    }
+
+    #[test]
+    fn highlights_fenced_and_web_code_blocks() {
+        let markdown = crate::markdown::markdown_to_html("```python\nx = \"a\" # note\n```\n");
+        let out = highlight_html(&markdown);
+        assert!(out.starts_with("<pre class=\"highlight\"><code class=\"language-python\">"));
+        assert!(out.contains("hl-comment"));
+
+        let web = "<pre data-lang=\"js\">let a = 1;</pre><pre>no language</pre>";
+        let out = highlight_html(web);
+        assert!(out.contains("<pre data-lang=\"js\" class=\"highlight\">"));
+        assert!(out.contains("hl-storage"));
+        assert!(out.contains("<pre>no language</pre>"));
+    }
+
+    #[test]
+    fn languages_survive_extraction_as_data_attributes() {
+        let page = r#"<div class="highlight highlight-source-go"><pre>package main</pre></div>
+            <pre><code class="language-toml">a = 1</code></pre>"#;
+        let tagged = tag_code_languages(page).expect("tagged");
+        assert!(tagged.contains("<pre data-lang=\"go\">"));
+        assert!(tagged.contains("<pre data-lang=\"toml\">"));
+        assert!(tag_code_languages("<pre>x</pre>").is_none());
+    }
+
+    #[test]
+    fn colour_targets_get_the_colour_theme() {
+        assert!(theme_css(PageSize::Rm2).contains("font-weight: bold"));
+        assert!(theme_css(PageSize::Ipad11).contains("#d73a49"));
+    }
+}
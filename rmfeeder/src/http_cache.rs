@@ -0,0 +1,244 @@
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OptionalExtension, params};
+
+pub const DEFAULT_MAX_BYTES: u64 = 100 * 1024 * 1024;
+
+const CACHE_FILE_NAME: &str = "rmfeeder_http_cache.sqlite";
+
+/// A stored response plus the validators needed to revalidate it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedResponse {
+    pub final_url: String,
+    pub content_type: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// Persistent response cache keyed by request URL, capped at `max_bytes` of body data.
+pub struct HttpCache {
+    conn: Connection,
+    max_bytes: u64,
+}
+
+impl HttpCache {
+    pub fn open(path: &Path, max_bytes: u64) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS http_cache (
+                url TEXT PRIMARY KEY,
+                final_url TEXT NOT NULL,
+                content_type TEXT,
+                etag TEXT,
+                last_modified TEXT,
+                body BLOB NOT NULL,
+                size INTEGER NOT NULL,
+                last_used INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        Ok(Self { conn, max_bytes })
+    }
+
+    pub fn lookup(&self, url: &str) -> rusqlite::Result<Option<CachedResponse>> {
+        self.conn
+            .query_row(
+                "SELECT final_url, content_type, etag, last_modified, body
+                 FROM http_cache WHERE url = ?1",
+                [url],
+                |row| {
+                    Ok(CachedResponse {
+                        final_url: row.get(0)?,
+                        content_type: row.get(1)?,
+                        etag: row.get(2)?,
+                        last_modified: row.get(3)?,
+                        body: row.get(4)?,
+                    })
+                },
+            )
+            .optional()
+    }
+
+    /// Mark an entry as recently used so eviction keeps it.
+    pub fn touch(&self, url: &str) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "UPDATE http_cache SET last_used = ?2 WHERE url = ?1",
+            params![url, now_nanos()],
+        )?;
+        Ok(())
+    }
+
+    pub fn store(&self, url: &str, response: &CachedResponse) -> rusqlite::Result<()> {
+        if response.body.len() as u64 > self.max_bytes {
+            return Ok(());
+        }
+        self.conn.execute(
+            "INSERT OR REPLACE INTO http_cache
+                (url, final_url, content_type, etag, last_modified, body, size, last_used)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                url,
+                response.final_url,
+                response.content_type,
+                response.etag,
+                response.last_modified,
+                response.body,
+                response.body.len() as i64,
+                now_nanos()
+            ],
+        )?;
+        self.evict()
+    }
+
+    pub fn total_bytes(&self) -> rusqlite::Result<u64> {
+        let total: i64 =
+            self.conn
+                .query_row("SELECT COALESCE(SUM(size), 0) FROM http_cache", [], |row| {
+                    row.get(0)
+                })?;
+        Ok(total as u64)
+    }
+
+    /// Drop least-recently-used entries until the cache fits under `max_bytes`.
+    fn evict(&self) -> rusqlite::Result<()> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT url, size FROM http_cache ORDER BY last_used DESC")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?;
+
+        let mut kept = 0u64;
+        let mut doomed = Vec::new();
+        for row in rows {
+            let (url, size) = row?;
+            kept += size as u64;
+            if kept > self.max_bytes {
+                doomed.push(url);
+            }
+        }
+
+        for url in doomed {
+            self.conn
+                .execute("DELETE FROM http_cache WHERE url = ?1", [url])?;
+        }
+        Ok(())
+    }
+}
+
+/// The cache lives next to the state DB so both share one data directory.
+pub fn cache_path_for_state(state_path: &Path) -> PathBuf {
+    match state_path.parent() {
+        Some(parent) => parent.join(CACHE_FILE_NAME),
+        None => PathBuf::from(CACHE_FILE_NAME),
+    }
+}
+
+fn now_nanos() -> i64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CachedResponse, HttpCache, cache_path_for_state};
+    use std::path::{Path, PathBuf};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_cache_path(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        std::env::temp_dir().join(format!("rmfeeder-http-cache-{name}-{nanos}.sqlite"))
+    }
+
+    fn entry(body: &str) -> CachedResponse {
+        CachedResponse {
+            final_url: "https://example.com/final".to_string(),
+            content_type: Some("text/html".to_string()),
+            etag: Some("\"v1\"".to_string()),
+            last_modified: None,
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn stores_and_reloads_entries() {
+        let path = temp_cache_path("roundtrip");
+        let cache = HttpCache::open(&path, 1024).expect("open cache");
+        cache
+            .store("https://example.com/a", &entry("hello"))
+            .expect("store");
+        drop(cache);
+
+        let reopened = HttpCache::open(&path, 1024).expect("reopen cache");
+        let cached = reopened
+            .lookup("https://example.com/a")
+            .expect("lookup")
+            .expect("entry present");
+        assert_eq!(cached, entry("hello"));
+        assert!(
+            reopened
+                .lookup("https://example.com/b")
+                .expect("lookup")
+                .is_none()
+        );
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn evicts_least_recently_used_entries_over_the_cap() {
+        let path = temp_cache_path("evict");
+        let cache = HttpCache::open(&path, 10).expect("open cache");
+        cache
+            .store("https://example.com/a", &entry("aaaa"))
+            .expect("store a");
+        cache
+            .store("https://example.com/b", &entry("bbbb"))
+            .expect("store b");
+        cache.touch("https://example.com/a").expect("touch a");
+        cache
+            .store("https://example.com/c", &entry("cccc"))
+            .expect("store c");
+
+        assert!(
+            cache
+                .lookup("https://example.com/a")
+                .expect("lookup")
+                .is_some()
+        );
+        assert!(
+            cache
+                .lookup("https://example.com/b")
+                .expect("lookup")
+                .is_none()
+        );
+        assert!(
+            cache
+                .lookup("https://example.com/c")
+                .expect("lookup")
+                .is_some()
+        );
+        assert_eq!(cache.total_bytes().expect("size"), 8);
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn cache_path_sits_next_to_state_db() {
+        assert_eq!(
+            cache_path_for_state(Path::new("/data/rmfeeder/rmfeeder_state.sqlite")),
+            PathBuf::from("/data/rmfeeder/rmfeeder_http_cache.sqlite")
+        );
+    }
+}
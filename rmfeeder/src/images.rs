@@ -0,0 +1,487 @@
+use std::io::Cursor;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use dom_query::{Document, NodeRef, Selection};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::{self, ColorMap, FilterType};
+use image::{DynamicImage, GrayImage, ImageFormat, Luma, RgbImage};
+
+use crate::{AppConfig, PageSize, escape_html, fetcher};
+
+/// Default per-bundle limit for embedded image data.
+pub const DEFAULT_BUDGET_MB: u64 = 25;
+
+/// Gray levels the dithering targets; e-ink panels show 16.
+const GRAY_LEVELS: u8 = 16;
+
+const JPEG_QUALITY: u8 = 82;
+
+/// Attributes lazy loaders keep the real image URL in, most specific first.
+const LAZY_SRC_ATTRS: &[&str] = &[
+    "data-src",
+    "data-lazy-src",
+    "data-original",
+    "data-lazyload",
+    "data-url",
+];
+
+const LAZY_SRCSET_ATTRS: &[&str] = &["data-srcset", "data-lazy-srcset"];
+
+/// Image stage settings, from `images`, `image_dither` and `image_budget_mb`.
+#[derive(Debug, Clone)]
+pub struct ImageOptions {
+    /// Download and convert images at build time instead of inlining the originals.
+    pub enabled: bool,
+    /// Floyd–Steinberg dither to 16 gray levels on monochrome targets.
+    pub dither: bool,
+    pub budget_bytes: u64,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dither: false,
+            budget_bytes: DEFAULT_BUDGET_MB * 1024 * 1024,
+        }
+    }
+}
+
+impl ImageOptions {
+    pub fn from_config(config: Option<&AppConfig>) -> Self {
+        let defaults = Self::default();
+        Self {
+            enabled: config.and_then(|c| c.images).unwrap_or(defaults.enabled),
+            dither: config
+                .and_then(|c| c.image_dither)
+                .unwrap_or(defaults.dither),
+            budget_bytes: config
+                .and_then(|c| c.image_budget_mb)
+                .map(|mb| mb * 1024 * 1024)
+                .unwrap_or(defaults.budget_bytes),
+        }
+    }
+}
+
+/// Image bytes left for one bundle, and how many images did not fit.
+#[derive(Debug)]
+pub struct ImageBudget {
+    remaining: u64,
+    pub omitted: usize,
+}
+
+impl ImageBudget {
+    pub fn new(options: &ImageOptions) -> Self {
+        Self {
+            remaining: options.budget_bytes,
+            omitted: 0,
+        }
+    }
+
+    /// Reserve `bytes`; counts the image as omitted when it doesn't fit.
+    pub fn take(&mut self, bytes: usize) -> bool {
+        let bytes = bytes as u64;
+        if bytes > self.remaining {
+            self.omitted += 1;
+            return false;
+        }
+        self.remaining -= bytes;
+        true
+    }
+}
+
+/// Warn when images were left out to stay within the budget.
+pub fn report_budget(budget: &ImageBudget, options: &ImageOptions) {
+    if budget.omitted > 0 {
+        eprintln!(
+            "Warning: image budget of {} MB reached; left out {} image(s)",
+            options.budget_bytes / (1024 * 1024),
+            budget.omitted
+        );
+    }
+}
+
+/// 16 evenly spaced gray levels.
+struct GrayLevels;
+
+impl ColorMap for GrayLevels {
+    type Color = Luma<u8>;
+
+    fn index_of(&self, color: &Luma<u8>) -> usize {
+        let step = 255.0 / f32::from(GRAY_LEVELS - 1);
+        (f32::from(color.0[0]) / step).round() as usize
+    }
+
+    fn map_color(&self, color: &mut Luma<u8>) {
+        let step = 255.0 / f32::from(GRAY_LEVELS - 1);
+        color.0[0] = (self.index_of(color) as f32 * step).round() as u8;
+    }
+}
+
+/// Copy lazy-load URLs (`data-src`, `data-srcset`, ...) into `src`/`srcset`
+/// and swap placeholder images for their `<noscript>` fallback. Runs on the
+/// page before extraction so readability sees the real images. Returns `None`
+/// when nothing changed.
+pub fn resolve_lazy_images(html: &str) -> Option<String> {
+    if ![
+        "data-src",
+        "data-lazy",
+        "data-original",
+        "data-url",
+        "<noscript",
+    ]
+    .iter()
+    .any(|marker| html.contains(marker))
+    {
+        return None;
+    }
+    let doc = Document::from(html);
+    let mut changed = false;
+
+    for noscript in doc.select("noscript").iter() {
+        let Some(fallback) = noscript_image(&noscript) else {
+            continue;
+        };
+        if let Some(placeholder) = noscript.nodes().first().and_then(previous_image) {
+            placeholder.remove_from_parent();
+        }
+        noscript.replace_with_html(fallback);
+        changed = true;
+    }
+
+    for img in doc.select("img").iter() {
+        let lazy_src = LAZY_SRC_ATTRS
+            .iter()
+            .filter_map(|attr| img.attr(attr))
+            .map(|value| value.trim().to_string())
+            .find(|value| !value.is_empty() && !value.starts_with("data:"));
+        if let Some(lazy_src) = lazy_src
+            && is_placeholder(img.attr("src").as_deref())
+        {
+            img.set_attr("src", &lazy_src);
+            changed = true;
+        }
+        let lazy_srcset = LAZY_SRCSET_ATTRS
+            .iter()
+            .filter_map(|attr| img.attr(attr))
+            .map(|value| value.trim().to_string())
+            .find(|value| !value.is_empty());
+        if let Some(lazy_srcset) = lazy_srcset
+            && img.attr("srcset").is_none_or(|v| v.trim().is_empty())
+        {
+            img.set_attr("srcset", &lazy_srcset);
+            changed = true;
+        }
+    }
+    changed.then(|| doc.html().to_string())
+}
+
+/// The `<img>` right before `node`, skipping whitespace between the tags.
+fn previous_image<'a>(node: &NodeRef<'a>) -> Option<NodeRef<'a>> {
+    let mut previous = node.prev_sibling()?;
+    while previous.is_text() && previous.text().trim().is_empty() {
+        previous = previous.prev_sibling()?;
+    }
+    previous
+        .node_name()
+        .is_some_and(|name| name.eq_ignore_ascii_case("img"))
+        .then_some(previous)
+}
+
+/// The `<img>` inside a `<noscript>`, which parses as raw text when the
+/// parser has scripting enabled.
+fn noscript_image(noscript: &Selection) -> Option<String> {
+    let img = noscript.select("img");
+    if img.exists() {
+        return Some(img.first().html().to_string());
+    }
+    let text = noscript.text();
+    if !text.contains("<img") {
+        return None;
+    }
+    let fragment = Document::fragment(text.to_string());
+    let img = fragment.select("img");
+    img.exists().then(|| img.first().html().to_string())
+}
+
+fn is_placeholder(src: Option<&str>) -> bool {
+    let Some(src) = src.map(str::trim).filter(|s| !s.is_empty()) else {
+        return true;
+    };
+    let lower = src.to_ascii_lowercase();
+    lower.starts_with("data:")
+        || [
+            "placeholder",
+            "blank.",
+            "spacer.",
+            "lazy",
+            "1x1",
+            "pixel.gif",
+        ]
+        .iter()
+        .any(|marker| lower.contains(marker))
+}
+
+/// The candidate to download: the smallest `srcset` width that still covers
+/// `width_px`, else the widest, else `src`.
+fn best_source(img: &Selection, width_px: u32) -> Option<String> {
+    let mut candidates: Vec<(u32, String)> = img
+        .attr("srcset")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|candidate| {
+            let mut parts = candidate.split_whitespace();
+            let url = parts.next()?.to_string();
+            let width = parts
+                .next()
+                .and_then(|d| d.strip_suffix('w'))
+                .and_then(|w| w.parse().ok())?;
+            Some((width, url))
+        })
+        .collect();
+    candidates.sort_by_key(|(width, _)| *width);
+    let from_srcset = candidates
+        .iter()
+        .find(|(width, _)| *width >= width_px)
+        .or(candidates.last())
+        .map(|(_, url)| url.clone());
+    from_srcset
+        .or_else(|| img.attr("src").map(|src| src.trim().to_string()))
+        .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+}
+
+/// Downscale to fit the target screen and convert: grayscale (optionally
+/// dithered) on monochrome targets, colour kept on colour ones. Returns the
+/// MIME type and encoded bytes, or `None` for formats that can't be decoded
+/// (SVG, AVIF, ...).
+pub fn prepare_image(
+    body: &[u8],
+    page_size: PageSize,
+    dither: bool,
+) -> Option<(&'static str, Vec<u8>)> {
+    let image = image::load_from_memory(body).ok()?;
+    let (max_width, max_height) = (page_size.width_px(), page_size.height_px());
+    let image = if image.width() > max_width || image.height() > max_height {
+        image.resize(max_width, max_height, FilterType::Lanczos3)
+    } else {
+        image
+    };
+
+    let mut out = Cursor::new(Vec::new());
+    if page_size.is_color() {
+        if image.color().has_alpha() {
+            image.write_to(&mut out, ImageFormat::Png).ok()?;
+            return Some(("image/png", out.into_inner()));
+        }
+        JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY)
+            .encode_image(&image.to_rgb8())
+            .ok()?;
+        return Some(("image/jpeg", out.into_inner()));
+    }
+
+    let mut gray: GrayImage = DynamicImage::ImageRgb8(flatten(&image)).to_luma8();
+    if dither {
+        imageops::dither(&mut gray, &GrayLevels);
+        DynamicImage::ImageLuma8(gray)
+            .write_to(&mut out, ImageFormat::Png)
+            .ok()?;
+        return Some(("image/png", out.into_inner()));
+    }
+    JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY)
+        .encode_image(&gray)
+        .ok()?;
+    Some(("image/jpeg", out.into_inner()))
+}
+
+/// Composite transparent pixels onto white paper.
+fn flatten(image: &DynamicImage) -> RgbImage {
+    let rgba = image.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |c: u8| {
+            let alpha = u16::from(a);
+            ((u16::from(c) * alpha + 255 * (255 - alpha)) / 255) as u8
+        };
+        image::Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+/// Download every remote `<img>` in `html`, convert it for `page_size` and
+/// embed it as a data URI. Images that don't fit the budget are replaced by
+/// their alt text (see [`replace_with_alt`]); ones that fail to download or
+/// decode keep their remote `src` for [`crate::offline::inline_remote_assets`].
+pub fn embed_images(
+    html: &str,
+    page_size: PageSize,
+    options: &ImageOptions,
+    budget: &mut ImageBudget,
+) -> String {
+    if !options.enabled || !html.contains("<img") {
+        return html.to_string();
+    }
+    let doc = Document::fragment(html);
+    let mut changed = false;
+    for img in doc.select("img").iter() {
+        let Some(url) = best_source(&img, page_size.width_px()) else {
+            continue;
+        };
+        let Ok(response) = fetcher::shared().get(&url) else {
+            continue;
+        };
+        let Some((mime, bytes)) = prepare_image(&response.body, page_size, options.dither) else {
+            continue;
+        };
+        changed = true;
+        // A <picture> would otherwise pick a remote <source> over the embedded copy.
+        img.parent().filter("picture").select("source").remove();
+        if !budget.take(bytes.len()) {
+            replace_with_alt(&img);
+            continue;
+        }
+        img.set_attr(
+            "src",
+            &format!("data:{};base64,{}", mime, BASE64.encode(&bytes)),
+        );
+        img.remove_attrs(&["srcset", "sizes", "loading"]);
+    }
+    if !changed {
+        return html.to_string();
+    }
+    doc.select("html").inner_html().to_string()
+}
+
+/// Stand in for an image that is left out of the output: its alt text in
+/// brackets, or nothing when it has none.
+pub fn replace_with_alt(img: &Selection) {
+    let alt = img.attr("alt").unwrap_or_default().trim().to_string();
+    if alt.is_empty() {
+        img.remove();
+    } else {
+        img.replace_with_html(format!(
+            "<span class=\"missing-image\">[{}]</span>",
+            escape_html(&alt)
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{DynamicImage, ImageFormat, RgbImage, Rgba, RgbaImage};
+
+    use super::{ImageBudget, ImageOptions, embed_images, prepare_image, resolve_lazy_images};
+    use crate::PageSize;
+    use crate::test_support::{TestResponse, TestServer};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbImage::from_fn(width, height, |x, _| image::Rgb([(x % 256) as u8, 40, 200]));
+        let mut out = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut out, ImageFormat::Png)
+            .expect("encode");
+        out.into_inner()
+    }
+
+    #[test]
+    fn resolves_lazy_attributes_and_noscript_fallbacks() {
+        let page = r#"<p><img src="data:image/gif;base64,R0lGOD" data-src="/a.jpg"
+            data-srcset="/a-800.jpg 800w, /a-1600.jpg 1600w"></p>
+            <p><img class="lazy" src="/blank.gif"><noscript><img src="/b.jpg" alt="B"></noscript></p>
+            <p><img src="/c.jpg" data-src="/c-large.jpg"></p>"#;
+        let out = resolve_lazy_images(page).expect("changed");
+        assert!(out.contains("src=\"/a.jpg\""));
+        assert!(out.contains("srcset=\"/a-800.jpg 800w, /a-1600.jpg 1600w\""));
+        assert!(out.contains("<img src=\"/b.jpg\" alt=\"B\">"));
+        assert!(!out.contains("blank.gif"));
+        assert!(out.contains("<img src=\"/c.jpg\""));
+        assert!(resolve_lazy_images("<p><img src=\"/d.jpg\"></p>").is_none());
+    }
+
+    #[test]
+    fn removes_placeholders_separated_from_noscript_by_whitespace() {
+        let page = "<p><img src=\"/blank.gif\">\n  <noscript><img src=\"/b.jpg\"></noscript></p>\
+                    <p><img src=\"/kept.jpg\"> Photo: <noscript><img src=\"/c.jpg\"></noscript></p>";
+        let out = resolve_lazy_images(page).expect("changed");
+        assert!(!out.contains("blank.gif"));
+        assert_eq!(out.matches("/b.jpg").count(), 1);
+        assert!(out.contains("<img src=\"/kept.jpg\"> Photo: <img src=\"/c.jpg\">"));
+    }
+
+    #[test]
+    fn downscales_and_converts_for_the_target() {
+        let body = png(1600, 400);
+
+        let (mime, bytes) = prepare_image(&body, PageSize::Rm2, false).expect("gray");
+        assert_eq!(mime, "image/jpeg");
+        let gray = image::load_from_memory(&bytes).expect("decode");
+        assert_eq!(gray.width(), PageSize::Rm2.width_px());
+        assert_eq!(gray.color(), image::ColorType::L8);
+
+        let (mime, bytes) = prepare_image(&body, PageSize::Rm2, true).expect("dithered");
+        assert_eq!(mime, "image/png");
+        let levels = image::load_from_memory(&bytes).expect("decode").to_luma8();
+        assert!(levels.pixels().all(|p| p.0[0] % 17 == 0));
+
+        let (_, bytes) = prepare_image(&body, PageSize::Ipad11, false).expect("colour");
+        let colour = image::load_from_memory(&bytes).expect("decode");
+        assert_eq!(colour.color(), image::ColorType::Rgb8);
+
+        assert!(prepare_image(b"<svg/>", PageSize::Rm2, false).is_none());
+    }
+
+    #[test]
+    fn embeds_remote_images_until_the_budget_runs_out() {
+        let body = png(300, 200);
+        let (mime, converted) = prepare_image(&body, PageSize::Rm2, false).expect("convert");
+        assert_eq!(mime, "image/jpeg");
+        let server = TestServer::start(move |req| match req.path.as_str() {
+            "/missing.png" => TestResponse::status(404),
+            _ => TestResponse::ok("image/png", body.clone()),
+        });
+        let html = format!(
+            r#"<picture><source srcset="{0}"><img src="{1}" alt="First"></picture>
+            <p><img src="{1}" alt="Second"><img src="{1}"><img src="{2}" alt="Gone"></p>"#,
+            server.url("/wide.png"),
+            server.url("/a.png"),
+            server.url("/missing.png")
+        );
+        let options = ImageOptions {
+            budget_bytes: converted.len() as u64,
+            ..ImageOptions::default()
+        };
+        let mut budget = ImageBudget::new(&options);
+        let out = embed_images(&html, PageSize::Rm2, &options, &mut budget);
+
+        assert!(out.contains("<img src=\"data:image/jpeg;base64,"));
+        assert!(out.contains("alt=\"First\""));
+        assert!(!out.contains("<source"));
+        assert!(out.contains("<span class=\"missing-image\">[Second]</span>"));
+        assert_eq!(out.matches("<img").count(), 2);
+        assert_eq!(budget.omitted, 2);
+        // Failed downloads keep their remote source for the offline pass.
+        assert!(out.contains(&server.url("/missing.png")));
+
+        let disabled = ImageOptions {
+            enabled: false,
+            ..ImageOptions::default()
+        };
+        assert_eq!(
+            embed_images(&html, PageSize::Rm2, &disabled, &mut budget),
+            html
+        );
+    }
+
+    #[test]
+    fn transparent_pixels_become_white_paper() {
+        let image = RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 0]));
+        let mut out = Cursor::new(Vec::new());
+        DynamicImage::ImageRgba8(image)
+            .write_to(&mut out, ImageFormat::Png)
+            .expect("encode");
+        let (_, bytes) = prepare_image(&out.into_inner(), PageSize::Rm2, true).expect("gray");
+        let gray = image::load_from_memory(&bytes).expect("decode").to_luma8();
+        assert!(gray.pixels().all(|p| p.0[0] == 255));
+    }
+}
@@ -0,0 +1,624 @@
+use std::collections::HashSet;
+use std::error::Error;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use url::Url;
+
+use crate::article_meta::ArticleMeta;
+use crate::browser::BrowserFallback;
+use crate::extractor::{Extraction, ExtractionMethod};
+use crate::fetcher::{FetchResponse, Fetcher};
+use crate::pagination::{self, ParagraphDeduper};
+use crate::pdf_merge;
+use crate::site_rules::SiteRules;
+use crate::{AppConfig, escape_html};
+
+/// What a fetched response is, decided from its Content-Type with URL and body fallbacks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentKind {
+    Html,
+    Text,
+    Image(String),
+    Pdf,
+    Unsupported(String),
+}
+
+impl ContentKind {
+    /// Whether the content is text that fabric can summarize.
+    pub fn is_summarizable(&self) -> bool {
+        matches!(self, Self::Html | Self::Text)
+    }
+}
+
+/// Rendering and pagination knobs for [`ingest`].
+#[derive(Debug, Clone)]
+pub struct IngestOptions {
+    pub reflow_text: bool,
+    /// Pages fetched per article including the first; 1 disables pagination.
+    pub max_pages: usize,
+    /// Host globs for which pagination is never followed.
+    pub paginate_exclude: Vec<String>,
+    /// Headless-browser rendering for script-built pages; `None` disables it.
+    pub browser: Option<BrowserFallback>,
+    /// Per-host extraction rules tried before readability.
+    pub site_rules: SiteRules,
+}
+
+impl Default for IngestOptions {
+    fn default() -> Self {
+        Self {
+            reflow_text: false,
+            max_pages: pagination::DEFAULT_MAX_PAGES,
+            paginate_exclude: Vec::new(),
+            browser: None,
+            site_rules: SiteRules::default(),
+        }
+    }
+}
+
+impl IngestOptions {
+    pub fn from_config(config: Option<&AppConfig>) -> Self {
+        let mut options = Self::default();
+        let Some(config) = config else {
+            return options;
+        };
+        if let Some(value) = config.reflow_text {
+            options.reflow_text = value;
+        }
+        if let Some(value) = config.max_pages {
+            options.max_pages = value.max(1);
+        }
+        if config.paginate == Some(false) {
+            options.max_pages = 1;
+        }
+        if let Some(hosts) = &config.paginate_exclude_hosts {
+            options.paginate_exclude = hosts.clone();
+        }
+        options.browser = BrowserFallback::from_config(Some(config), false);
+        options.site_rules = SiteRules::from_config(Some(config));
+        options
+    }
+}
+
+/// A bundle-ready item built from one response.
+#[derive(Debug, Clone)]
+pub struct IngestedItem {
+    pub kind: ContentKind,
+    pub title: String,
+    pub content_html: String,
+    /// Which extraction step produced an HTML article; `None` for other kinds.
+    pub method: Option<ExtractionMethod>,
+    pub meta: ArticleMeta,
+    /// The fetched PDF for [`ContentKind::Pdf`]; its pages are merged into the
+    /// output after the placeholder page.
+    pub source_pdf: Option<Vec<u8>>,
+}
+
+pub fn classify(content_type: Option<&str>, url: &str, body: &[u8]) -> ContentKind {
+    if body.starts_with(b"%PDF-") {
+        return ContentKind::Pdf;
+    }
+    let essence = content_type
+        .and_then(|ct| ct.split(';').next())
+        .map(|ct| ct.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    match essence.as_str() {
+        "text/html" | "application/xhtml+xml" => ContentKind::Html,
+        "text/plain" => ContentKind::Text,
+        "application/pdf" | "application/x-pdf" => ContentKind::Pdf,
+        image if image.starts_with("image/") => ContentKind::Image(essence.clone()),
+        "" | "application/octet-stream" | "binary/octet-stream" => classify_by_extension(url),
+        other => ContentKind::Unsupported(other.to_string()),
+    }
+}
+
+fn classify_by_extension(url: &str) -> ContentKind {
+    let path = Url::parse(url)
+        .map(|u| u.path().to_ascii_lowercase())
+        .unwrap_or_default();
+    let ext = path.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("");
+    match ext {
+        "pdf" => ContentKind::Pdf,
+        "txt" | "text" => ContentKind::Text,
+        "png" => ContentKind::Image("image/png".to_string()),
+        "jpg" | "jpeg" => ContentKind::Image("image/jpeg".to_string()),
+        "gif" => ContentKind::Image("image/gif".to_string()),
+        "webp" => ContentKind::Image("image/webp".to_string()),
+        "svg" => ContentKind::Image("image/svg+xml".to_string()),
+        _ => ContentKind::Html,
+    }
+}
+
+/// Turn a response into a title and bundle HTML according to its content kind.
+/// HTML articles split across pages are followed via `fetcher` and stitched together.
+/// A PDF body is moved out of `response` into the item.
+pub fn ingest(
+    response: &mut FetchResponse,
+    fetcher: &Fetcher,
+    options: &IngestOptions,
+) -> Result<IngestedItem, Box<dyn Error>> {
+    let kind = classify(
+        response.content_type.as_deref(),
+        &response.url,
+        &response.body,
+    );
+    let mut method = None;
+    let mut meta = ArticleMeta::default();
+    let mut source_pdf = None;
+    let (title, content_html) = match &kind {
+        ContentKind::Html => {
+            let page = extract_paginated(response, fetcher, options)?;
+            method = Some(page.method);
+            meta = page.meta;
+            (page.title, page.content_html)
+        }
+        ContentKind::Text => (
+            title_from_url(&response.url),
+            text_to_html(&response.text(), options.reflow_text),
+        ),
+        ContentKind::Image(mime) => {
+            let title = title_from_url(&response.url);
+            let html = image_to_html(&response.body, mime, &title);
+            (title, html)
+        }
+        ContentKind::Pdf => {
+            let (title, html) = pdf_merge::placeholder(&response.body)?;
+            source_pdf = Some(std::mem::take(&mut response.body));
+            (title.unwrap_or_else(|| title_from_url(&response.url)), html)
+        }
+        ContentKind::Unsupported(mime) => {
+            return Err(format!("unsupported content type {}", mime).into());
+        }
+    };
+    Ok(IngestedItem {
+        kind,
+        title,
+        content_html,
+        method,
+        meta,
+        source_pdf,
+    })
+}
+
+/// Extract the first page, rendering it in the headless browser when its host
+/// is configured for that or when static extraction comes back (nearly) empty.
+/// Rendered DOMs are recorded with `--record` and served again on `--replay`.
+fn extract_first_page(
+    response: &FetchResponse,
+    fetcher: &Fetcher,
+    options: &IngestOptions,
+) -> (String, Option<Extraction>) {
+    if fetcher.is_replaying() {
+        let html = fetcher
+            .replayed_render(&response.url)
+            .unwrap_or_else(|| response.text());
+        let article = options.site_rules.extract(&html, Some(&response.url));
+        return (html, article);
+    }
+    let static_html = response.text();
+    let Some(browser) = &options.browser else {
+        let article = options
+            .site_rules
+            .extract(&static_html, Some(&response.url));
+        return (static_html, article);
+    };
+
+    if browser.always_for(&response.url) {
+        match render_and_extract(browser, fetcher, &response.url, &options.site_rules) {
+            Ok(rendered) => {
+                fetcher.record_rendered(&response.url, &rendered.0);
+                return rendered;
+            }
+            Err(e) => eprintln!(
+                "Warning: browser rendering failed for {}: {}",
+                response.url, e
+            ),
+        }
+        let article = options
+            .site_rules
+            .extract(&static_html, Some(&response.url));
+        return (static_html, article);
+    }
+
+    let article = options
+        .site_rules
+        .extract(&static_html, Some(&response.url));
+    let static_len = article.as_ref().map_or(0, |e| e.article.length);
+    if !browser.auto || static_len >= browser.min_text_chars {
+        return (static_html, article);
+    }
+    eprintln!(
+        "Rendering {} in headless browser ({} chars extracted statically)",
+        response.url, static_len
+    );
+    match render_and_extract(browser, fetcher, &response.url, &options.site_rules) {
+        Ok((html, Some(rendered))) if rendered.article.length > static_len => {
+            fetcher.record_rendered(&response.url, &html);
+            (html, Some(rendered))
+        }
+        Ok(_) => (static_html, article),
+        Err(e) => {
+            eprintln!(
+                "Warning: browser rendering failed for {}: {}",
+                response.url, e
+            );
+            (static_html, article)
+        }
+    }
+}
+
+fn render_and_extract(
+    browser: &BrowserFallback,
+    fetcher: &Fetcher,
+    url: &str,
+    site_rules: &SiteRules,
+) -> Result<(String, Option<Extraction>), Box<dyn Error>> {
+    let html = browser.renderer.render(url, fetcher.profile_for(url))?;
+    let article = site_rules.extract(&html, Some(url));
+    Ok((html, article))
+}
+
+/// Extract the article, then follow `rel="next"`-style links up to `max_pages`,
+/// appending each page's content minus paragraphs already seen. Method and
+/// metadata come from the first page.
+fn extract_paginated(
+    response: &FetchResponse,
+    fetcher: &Fetcher,
+    options: &IngestOptions,
+) -> Result<ExtractedPage, Box<dyn Error>> {
+    let (mut page_html, extraction) = extract_first_page(response, fetcher, options);
+    let Extraction { article, method } = extraction.ok_or("extraction failed")?;
+    let meta = ArticleMeta::from_article(&article);
+    let title = article.title;
+    if options.max_pages <= 1
+        || !pagination::is_enabled_for(&response.url, &options.paginate_exclude)
+    {
+        return Ok(ExtractedPage {
+            title,
+            content_html: article.content.to_string(),
+            method,
+            meta,
+        });
+    }
+
+    let mut deduper = ParagraphDeduper::default();
+    let mut content = deduper.filter(&article.content);
+    let mut page_url = response.url.clone();
+    let mut visited = HashSet::from([page_url.clone()]);
+    let mut pages = 1;
+
+    while pages < options.max_pages {
+        let Some(next_url) = pagination::find_next_page(&page_html, &page_url) else {
+            break;
+        };
+        if !visited.insert(next_url.clone()) {
+            break;
+        }
+        let next = match fetcher.get(&next_url) {
+            Ok(next) => next,
+            Err(e) => {
+                eprintln!("Warning: stopped pagination at {}: {}", next_url, e);
+                break;
+            }
+        };
+        page_html = next.text();
+        let Some(page) = options.site_rules.extract(&page_html, Some(&next.url)) else {
+            eprintln!(
+                "Warning: stopped pagination at {}: extraction failed",
+                next_url
+            );
+            break;
+        };
+        let filtered = deduper.filter(&page.article.content);
+        // A "next" page with nothing new is a listing or a loop, not a continuation.
+        if !has_text(&filtered) {
+            break;
+        }
+        content.push('\n');
+        content.push_str(&filtered);
+        page_url = next.url;
+        visited.insert(page_url.clone());
+        pages += 1;
+    }
+
+    if pages > 1 {
+        eprintln!("Stitched {} pages for {}", pages, response.url);
+    }
+    Ok(ExtractedPage {
+        title,
+        content_html: content,
+        method,
+        meta,
+    })
+}
+
+/// An HTML article after extraction and pagination.
+struct ExtractedPage {
+    title: String,
+    content_html: String,
+    method: ExtractionMethod,
+    meta: ArticleMeta,
+}
+
+fn has_text(html: &str) -> bool {
+    !dom_query::Document::fragment(html).text().trim().is_empty()
+}
+
+/// Plain text as a `<pre>` block, or as paragraphs split on blank lines when reflowing.
+pub fn text_to_html(text: &str, reflow: bool) -> String {
+    let text = text.replace("\r\n", "\n").replace('\u{c}', "\n");
+    if !reflow {
+        return format!(
+            "<pre class=\"plain-text\">{}</pre>",
+            escape_html(text.trim_end())
+        );
+    }
+    text.split("\n\n")
+        .map(|para| {
+            para.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|para| !para.is_empty())
+        .map(|para| format!("<p>{}</p>", escape_html(&para)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn image_to_html(body: &[u8], mime: &str, title: &str) -> String {
+    format!(
+        "<figure class=\"full-page-figure\"><img src=\"{}\" alt=\"{}\"></figure>",
+        data_uri(mime, body),
+        escape_html(title)
+    )
+}
+
+/// The last path segment, percent-decoded, e.g. `rfc9110.txt`.
+pub fn title_from_url(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|u| {
+            let segment = u.path_segments()?.rev().find(|s| !s.is_empty())?;
+            Some(percent_decode(segment))
+        })
+        .unwrap_or_else(|| url.to_string())
+}
+
+pub(crate) fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let hex = bytes
+            .get(idx + 1..idx + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[idx], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                idx += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                idx += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn data_uri(mime: &str, body: &[u8]) -> String {
+    format!("data:{};base64,{}", mime, BASE64.encode(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ContentKind, IngestOptions, classify, image_to_html, ingest, text_to_html, title_from_url,
+    };
+    use crate::browser::{BrowserFallback, BrowserRenderer};
+    use crate::fetcher::{FetchSettings, Fetcher};
+    use crate::test_support::{TestResponse, TestServer, stand_in_executable};
+    use std::time::Duration;
+
+    fn article_page(body: &str, next: Option<&str>) -> String {
+        let next = next
+            .map(|href| format!("<link rel=\"next\" href=\"{href}\">"))
+            .unwrap_or_default();
+        let filler = "This paragraph is long enough for readability to keep it as content. ";
+        format!(
+            "<html><head><title>Long Read</title>{next}</head><body><article>\
+             <h1>Long Read</h1><p>Shared teaser paragraph repeated on every page.</p>\
+             <p>{body} {filler}{filler}{filler}</p></article></body></html>"
+        )
+    }
+
+    #[test]
+    fn classifies_by_content_type_then_extension_then_magic() {
+        let url = "https://example.com/a";
+        assert_eq!(
+            classify(Some("text/html; charset=utf-8"), url, b"<p>"),
+            ContentKind::Html
+        );
+        assert_eq!(classify(Some("text/plain"), url, b"x"), ContentKind::Text);
+        assert_eq!(
+            classify(Some("image/png"), url, b"x"),
+            ContentKind::Image("image/png".to_string())
+        );
+        assert_eq!(
+            classify(
+                Some("application/octet-stream"),
+                "https://arxiv.org/x.pdf",
+                b"x"
+            ),
+            ContentKind::Pdf
+        );
+        assert_eq!(
+            classify(None, "https://www.rfc-editor.org/rfc/rfc9110.txt", b"x"),
+            ContentKind::Text
+        );
+        assert_eq!(
+            classify(Some("text/html"), url, b"%PDF-1.7"),
+            ContentKind::Pdf
+        );
+        assert_eq!(
+            classify(Some("application/zip"), url, b"PK"),
+            ContentKind::Unsupported("application/zip".to_string())
+        );
+        assert_eq!(classify(None, url, b"<p>"), ContentKind::Html);
+    }
+
+    #[test]
+    fn stitches_paginated_articles_without_repeated_paragraphs() {
+        let server = TestServer::start(|req| match req.path.as_str() {
+            "/story?page=2" => TestResponse::ok("text/html", article_page("Page two body.", None)),
+            _ => TestResponse::ok(
+                "text/html",
+                article_page("Page one body.", Some("/story?page=2")),
+            ),
+        });
+        let fetcher = Fetcher::new(FetchSettings::default()).expect("client");
+        let mut first = fetcher.get(&server.url("/story")).expect("fetch");
+
+        let item = ingest(&mut first, &fetcher, &IngestOptions::default()).expect("ingest");
+        assert!(item.content_html.contains("Page one body."));
+        assert!(item.content_html.contains("Page two body."));
+        assert_eq!(item.content_html.matches("Shared teaser").count(), 1);
+
+        let single = IngestOptions {
+            max_pages: 1,
+            ..IngestOptions::default()
+        };
+        let item = ingest(&mut first, &fetcher, &single).expect("ingest");
+        assert!(!item.content_html.contains("Page two body."));
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn script_only_pages_fall_back_to_the_browser_dom() {
+        let server = TestServer::start(|_| {
+            TestResponse::ok(
+                "text/html",
+                "<html><body><div id=\"app\"></div><script src=\"/app.js\"></script></body></html>",
+            )
+        });
+        let rendered = article_page("Rendered by scripts.", None).replace('"', "\\\"");
+        let binary = stand_in_executable("ingest-chromium", &format!("echo \"{rendered}\""));
+        let fetcher = Fetcher::new(FetchSettings::default()).expect("client");
+        let mut response = fetcher.get(&server.url("/app")).expect("fetch");
+
+        let without = IngestOptions::default();
+        let static_only = ingest(&mut response, &fetcher, &without)
+            .map(|item| item.content_html)
+            .unwrap_or_default();
+        assert!(!static_only.contains("Rendered by scripts."));
+
+        let with = IngestOptions {
+            browser: Some(BrowserFallback {
+                renderer: BrowserRenderer::new(binary, Duration::from_secs(10)),
+                auto: true,
+                hosts: Vec::new(),
+                min_text_chars: 100,
+            }),
+            ..IngestOptions::default()
+        };
+        let item = ingest(&mut response, &fetcher, &with).expect("ingest");
+        assert_eq!(item.title, "Long Read");
+        assert!(item.content_html.contains("Rendered by scripts."));
+    }
+
+    #[test]
+    fn rendered_pages_replay_from_the_archive() {
+        let server = TestServer::start(|_| {
+            TestResponse::ok(
+                "text/html",
+                "<html><body><div id=\"app\"></div><script src=\"/app.js\"></script></body></html>",
+            )
+        });
+        let rendered = article_page("Rendered while recording.", None).replace('"', "\\\"");
+        let binary = stand_in_executable("record-chromium", &format!("echo \"{rendered}\""));
+        let archive =
+            std::env::temp_dir().join(format!("rmfeeder-rendered-{}.warc", std::process::id()));
+
+        let recorder = Fetcher::new(FetchSettings {
+            record_path: Some(archive.clone()),
+            ..FetchSettings::default()
+        })
+        .expect("client");
+        let with_browser = IngestOptions {
+            browser: Some(BrowserFallback {
+                renderer: BrowserRenderer::new(binary, Duration::from_secs(10)),
+                auto: true,
+                hosts: Vec::new(),
+                min_text_chars: 100,
+            }),
+            ..IngestOptions::default()
+        };
+        let mut response = recorder.get(&server.url("/app")).expect("fetch");
+        ingest(&mut response, &recorder, &with_browser).expect("ingest");
+        drop(recorder);
+
+        let replayer = Fetcher::new(FetchSettings {
+            replay_path: Some(archive.clone()),
+            ..FetchSettings::default()
+        })
+        .expect("client");
+        let mut response = replayer.get(&server.url("/app")).expect("replay");
+        let item =
+            ingest(&mut response, &replayer, &IngestOptions::default()).expect("ingest replayed");
+        assert!(item.content_html.contains("Rendered while recording."));
+        std::fs::remove_file(archive).ok();
+    }
+
+    #[test]
+    fn pdf_bodies_move_into_the_item() {
+        const FIXTURE: &[u8] = include_bytes!("../../testdata/pdf/two-pages.pdf");
+        let server = TestServer::start(|_| TestResponse::ok("application/pdf", FIXTURE));
+        let fetcher = Fetcher::new(FetchSettings::default()).expect("client");
+        let mut response = fetcher.get(&server.url("/paper.pdf")).expect("fetch");
+
+        let item = ingest(&mut response, &fetcher, &IngestOptions::default()).expect("ingest");
+        assert_eq!(item.kind, ContentKind::Pdf);
+        assert_eq!(item.title, "Two Page Fixture");
+        assert!(item.content_html.contains("PDF document, 2 pages."));
+        assert_eq!(item.source_pdf.as_deref(), Some(FIXTURE));
+        assert!(response.body.is_empty());
+    }
+
+    #[test]
+    fn plain_text_is_preformatted_or_reflowed() {
+        let text = "Title\r\n\r\nline one\nline <two>\n\n\nnext para\n";
+        assert_eq!(
+            text_to_html(text, false),
+            "<pre class=\"plain-text\">Title\n\nline one\nline &lt;two&gt;\n\n\nnext para</pre>"
+        );
+        assert_eq!(
+            text_to_html(text, true),
+            "<p>Title</p>\n<p>line one line &lt;two&gt;</p>\n<p>next para</p>"
+        );
+    }
+
+    #[test]
+    fn images_become_data_uri_figures() {
+        let html = image_to_html(b"GIF89a", "image/gif", "chart.gif");
+        assert_eq!(
+            html,
+            "<figure class=\"full-page-figure\"><img src=\"data:image/gif;base64,R0lGODlh\" alt=\"chart.gif\"></figure>"
+        );
+    }
+
+    #[test]
+    fn titles_fall_back_to_the_last_path_segment() {
+        assert_eq!(
+            title_from_url("https://arxiv.org/pdf/2401.01234v2/"),
+            "2401.01234v2"
+        );
+        assert_eq!(
+            title_from_url("https://example.com/files/My%20Paper.pdf"),
+            "My Paper.pdf"
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use dom_query::Document;
+use whatlang::Lang;
+
+use crate::AppConfig;
+
+/// Enough running text for the trigram statistics; the rest adds little.
+const SAMPLE_CHARS: usize = 4000;
+
+/// Normalize a declared language tag (`en_us`, `DE`, `pt-BR`) to `xx` or
+/// `xx-YY`. Returns `None` for empty, private-use or undetermined tags.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().replace('_', "-");
+    let mut parts = tag.split('-');
+    let primary = parts.next()?.to_ascii_lowercase();
+    if !(2..=3).contains(&primary.len())
+        || !primary.chars().all(|c| c.is_ascii_alphabetic())
+        || matches!(primary.as_str(), "und" | "mul" | "zxx")
+    {
+        return None;
+    }
+    let region = parts.find(|part| {
+        (part.len() == 2 && part.chars().all(|c| c.is_ascii_alphabetic()))
+            || (part.len() == 3 && part.chars().all(|c| c.is_ascii_digit()))
+    });
+    Some(match region {
+        Some(region) => format!("{}-{}", primary, region.to_ascii_uppercase()),
+        None => primary,
+    })
+}
+
+/// The primary subtag of a normalized tag: `de` for `de-AT`.
+pub fn primary(tag: &str) -> &str {
+    tag.split('-').next().unwrap_or(tag)
+}
+
+/// Whether `lang` is written right to left.
+pub fn is_rtl(lang: &str) -> bool {
+    matches!(
+        primary(lang),
+        "ar" | "fa" | "he" | "iw" | "ur" | "yi" | "ps" | "sd" | "ug" | "dv" | "ckb"
+    )
+}
+
+/// Guess the language of `text` from its character statistics. `None` when
+/// the guess isn't reliable.
+pub fn detect(text: &str) -> Option<String> {
+    let sample: String = text.chars().take(SAMPLE_CHARS).collect();
+    let info = whatlang::detect(&sample).filter(|info| info.is_reliable())?;
+    Some(iso_639_1(info.lang()).to_string())
+}
+
+/// Language of an article: the declared tag (page `lang` attribute or feed
+/// language) when there is one, else a guess from the body text.
+pub fn resolve(declared: Option<&str>, content_html: &str) -> Option<String> {
+    declared.and_then(normalize_tag).or_else(|| {
+        let doc = Document::fragment(content_html);
+        detect(&doc.select("html").text())
+    })
+}
+
+fn iso_639_1(lang: Lang) -> &'static str {
+    match lang {
+        Lang::Afr => "af",
+        Lang::Aka => "ak",
+        Lang::Amh => "am",
+        Lang::Ara => "ar",
+        Lang::Aze => "az",
+        Lang::Bel => "be",
+        Lang::Ben => "bn",
+        Lang::Bul => "bg",
+        Lang::Cat => "ca",
+        Lang::Ces => "cs",
+        Lang::Cmn => "zh",
+        Lang::Dan => "da",
+        Lang::Deu => "de",
+        Lang::Ell => "el",
+        Lang::Eng => "en",
+        Lang::Epo => "eo",
+        Lang::Est => "et",
+        Lang::Fin => "fi",
+        Lang::Fra => "fr",
+        Lang::Guj => "gu",
+        Lang::Heb => "he",
+        Lang::Hin => "hi",
+        Lang::Hrv => "hr",
+        Lang::Hun => "hu",
+        Lang::Hye => "hy",
+        Lang::Ind => "id",
+        Lang::Ita => "it",
+        Lang::Jav => "jv",
+        Lang::Jpn => "ja",
+        Lang::Kan => "kn",
+        Lang::Kat => "ka",
+        Lang::Khm => "km",
+        Lang::Kor => "ko",
+        Lang::Lat => "la",
+        Lang::Lav => "lv",
+        Lang::Lit => "lt",
+        Lang::Mal => "ml",
+        Lang::Mar => "mr",
+        Lang::Mkd => "mk",
+        Lang::Mya => "my",
+        Lang::Nep => "ne",
+        Lang::Nld => "nl",
+        Lang::Nob => "nb",
+        Lang::Ori => "or",
+        Lang::Pan => "pa",
+        Lang::Pes => "fa",
+        Lang::Pol => "pl",
+        Lang::Por => "pt",
+        Lang::Ron => "ro",
+        Lang::Rus => "ru",
+        Lang::Sin => "si",
+        Lang::Slk => "sk",
+        Lang::Slv => "sl",
+        Lang::Sna => "sn",
+        Lang::Spa => "es",
+        Lang::Srp => "sr",
+        Lang::Swe => "sv",
+        Lang::Tam => "ta",
+        Lang::Tel => "te",
+        Lang::Tgl => "tl",
+        Lang::Tha => "th",
+        Lang::Tuk => "tk",
+        Lang::Tur => "tr",
+        Lang::Ukr => "uk",
+        Lang::Urd => "ur",
+        Lang::Uzb => "uz",
+        Lang::Vie => "vi",
+        Lang::Yid => "yi",
+        Lang::Zul => "zu",
+    }
+}
+
+/// CSS hyphenation settings, from `hyphenate` and `hyphenate_languages`.
+#[derive(Debug, Clone, Default)]
+pub struct Hyphenation {
+    pub enabled: bool,
+    /// Primary subtags to hyphenate; empty means every known language.
+    pub languages: Vec<String>,
+}
+
+impl Hyphenation {
+    pub fn from_config(config: Option<&AppConfig>) -> Self {
+        Self {
+            enabled: config.and_then(|c| c.hyphenate).unwrap_or(false),
+            languages: config
+                .and_then(|c| c.hyphenate_languages.as_ref())
+                .map(|languages| {
+                    languages
+                        .iter()
+                        .filter_map(|lang| normalize_tag(lang))
+                        .map(|lang| primary(&lang).to_string())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Whether text in `lang` gets hyphenated. Unknown languages never are:
+    /// WeasyPrint needs the language to pick a dictionary.
+    pub fn applies_to(&self, lang: Option<&str>) -> bool {
+        let Some(lang) = lang else {
+            return false;
+        };
+        self.enabled
+            && (self.languages.is_empty() || self.languages.iter().any(|l| l == primary(lang)))
+    }
+}
+
+/// `lang`, `dir` and `data-hyphenate` attributes for an article container,
+/// with a leading space, or an empty string when the language is unknown.
+pub fn container_attrs(lang: Option<&str>, hyphenation: &Hyphenation) -> String {
+    let Some(lang) = lang else {
+        return String::new();
+    };
+    let mut attrs = format!(" lang=\"{}\"", lang);
+    if is_rtl(lang) {
+        attrs.push_str(" dir=\"rtl\"");
+    }
+    if hyphenation.applies_to(Some(lang)) {
+        attrs.push_str(" data-hyphenate=\"auto\"");
+    }
+    attrs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Hyphenation, container_attrs, detect, normalize_tag, resolve};
+
+    #[test]
+    fn normalizes_declared_tags() {
+        assert_eq!(normalize_tag("en_us").as_deref(), Some("en-US"));
+        assert_eq!(normalize_tag(" DE ").as_deref(), Some("de"));
+        assert_eq!(normalize_tag("zh-Hant-TW").as_deref(), Some("zh-TW"));
+        assert_eq!(normalize_tag("es-419").as_deref(), Some("es-419"));
+        assert_eq!(normalize_tag("und"), None);
+        assert_eq!(normalize_tag(""), None);
+    }
+
+    #[test]
+    fn detects_language_from_text_when_none_is_declared() {
+        let german = "<p>Die Bundesregierung hat am Mittwoch beschlossen, die Förderung \
+            für den Ausbau der Windenergie an Land deutlich zu erhöhen. Damit sollen \
+            die Klimaziele schneller erreicht werden.</p>";
+        assert_eq!(resolve(None, german).as_deref(), Some("de"));
+        assert_eq!(resolve(Some("nl-NL"), german).as_deref(), Some("nl-NL"));
+        assert_eq!(detect("ok"), None);
+    }
+
+    #[test]
+    fn hyphenation_follows_the_allow_list() {
+        let off = Hyphenation::default();
+        assert!(!off.applies_to(Some("de")));
+
+        let all = Hyphenation {
+            enabled: true,
+            languages: Vec::new(),
+        };
+        assert!(all.applies_to(Some("nl")));
+        assert!(!all.applies_to(None));
+
+        let listed = Hyphenation {
+            enabled: true,
+            languages: vec!["de".to_string()],
+        };
+        assert!(listed.applies_to(Some("de-AT")));
+        assert!(!listed.applies_to(Some("en")));
+    }
+
+    #[test]
+    fn right_to_left_languages_get_a_direction() {
+        let off = Hyphenation::default();
+        assert_eq!(
+            container_attrs(Some("he"), &off),
+            " lang=\"he\" dir=\"rtl\""
+        );
+        assert_eq!(
+            container_attrs(Some("ar-EG"), &off),
+            " lang=\"ar-EG\" dir=\"rtl\""
+        );
+        assert_eq!(container_attrs(Some("de"), &off), " lang=\"de\"");
+        assert_eq!(container_attrs(None, &off), "");
+        let arabic = "<p>أعلنت الحكومة اليوم عن خطة جديدة لدعم الطاقة المتجددة في جميع أنحاء \
+            البلاد خلال السنوات الخمس القادمة</p>";
+        assert_eq!(resolve(None, arabic).as_deref(), Some("ar"));
+    }
+}
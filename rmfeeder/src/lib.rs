@@ -1,13 +1,40 @@
+pub mod archive;
+pub mod article_meta;
+pub mod browser;
+pub mod canonical;
 pub mod categorize;
+pub mod charset;
+pub mod embeds;
 pub mod epub;
 pub mod extractor;
 pub mod feeds;
 pub mod fetcher;
+pub mod fonts;
+pub mod highlight;
+pub mod http_cache;
+pub mod images;
+pub mod ingest;
+pub mod language;
+pub mod links;
 pub mod markdown;
+pub mod math;
 pub mod multipdf;
+pub mod offline;
+pub mod pagination;
 pub mod pdf;
+pub mod pdf_merge;
+pub mod pipeline;
+pub mod politeness;
+pub mod quality;
+pub mod request_profile;
+pub mod robots;
+pub mod site_rules;
 pub mod state;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod warc;
 pub mod xhtml;
+pub mod xhtml_sanitize;
 pub mod youtube;
 
 use serde::Deserialize;
@@ -31,6 +58,49 @@ pub struct AppConfig {
     pub yt_mark_watched_on_success: Option<bool>,
     pub page_size: Option<String>,
     pub categorize: Option<bool>,
+    pub http_connect_timeout: Option<u64>,
+    pub http_timeout: Option<u64>,
+    pub http_retries: Option<u32>,
+    pub http_retry_backoff_ms: Option<u64>,
+    pub http_retry_max_backoff_ms: Option<u64>,
+    pub http_cache: Option<bool>,
+    pub http_cache_path: Option<String>,
+    pub http_cache_max_mb: Option<u64>,
+    pub respect_robots_txt: Option<bool>,
+    pub fetch_concurrency: Option<usize>,
+    pub summarize_concurrency: Option<usize>,
+    pub request_profile: Option<Vec<request_profile::RequestProfile>>,
+    pub strip_query_params: Option<Vec<String>>,
+    pub amp_path_hosts: Option<Vec<String>>,
+    pub reflow_text: Option<bool>,
+    pub paginate: Option<bool>,
+    pub max_pages: Option<usize>,
+    pub paginate_exclude_hosts: Option<Vec<String>>,
+    pub browser_fallback: Option<bool>,
+    pub browser_hosts: Option<Vec<String>>,
+    pub browser_path: Option<String>,
+    pub browser_timeout: Option<u64>,
+    pub browser_min_text: Option<usize>,
+    pub archive_fallback: Option<bool>,
+    pub archive_endpoints: Option<Vec<String>>,
+    pub site_rule: Option<Vec<site_rules::SiteRule>>,
+    pub site_rules_dir: Option<String>,
+    pub quality_check: Option<bool>,
+    pub quality_min_words: Option<usize>,
+    pub quality_max_link_density: Option<f64>,
+    pub quality_phrases: Option<Vec<String>>,
+    pub quality_selectors: Option<Vec<String>>,
+    pub quality_action: Option<String>,
+    pub link_style: Option<String>,
+    pub hyphenate: Option<bool>,
+    pub hyphenate_languages: Option<Vec<String>>,
+    pub font_serif: Option<String>,
+    pub font_sans: Option<String>,
+    pub font_mono: Option<String>,
+    pub font_scripts: Option<std::collections::BTreeMap<String, String>>,
+    pub images: Option<bool>,
+    pub image_dither: Option<bool>,
+    pub image_budget_mb: Option<u64>,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -66,6 +136,8 @@ pub struct TargetSpec {
     pub width_px: u32,
     pub height_px: u32,
     pub dpi: u16,
+    /// Colour display; selects colour rather than grayscale styling.
+    pub color: bool,
     pub description: &'static str,
 }
 
@@ -122,6 +194,10 @@ impl PageSize {
         self.target_spec().description
     }
 
+    pub fn is_color(self) -> bool {
+        self.target_spec().color
+    }
+
     pub fn all_targets() -> &'static [TargetSpec] {
         &TARGET_SPECS
     }
@@ -163,6 +239,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 2550,
         height_px: 3300,
         dpi: 300,
+        color: false,
         description: "US Letter",
     },
     TargetSpec {
@@ -172,6 +249,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 1404,
         height_px: 1872,
         dpi: 226,
+        color: false,
         description: "reMarkable 1",
     },
     TargetSpec {
@@ -181,6 +259,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 1404,
         height_px: 1872,
         dpi: 226,
+        color: false,
         description: "reMarkable 2",
     },
     TargetSpec {
@@ -190,6 +269,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 1620,
         height_px: 2160,
         dpi: 229,
+        color: true,
         description: "reMarkable Paper Pro",
     },
     TargetSpec {
@@ -205,6 +285,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 1620,
         height_px: 2160,
         dpi: 229,
+        color: true,
         description: "reMarkable Paper Pro Move",
     },
     TargetSpec {
@@ -214,6 +295,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 1860,
         height_px: 2480,
         dpi: 300,
+        color: false,
         description: "Kindle Scribe",
     },
     TargetSpec {
@@ -223,6 +305,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 1980,
         height_px: 2640,
         dpi: 300,
+        color: true,
         description: "Kindle Scribe Colorsoft",
     },
     TargetSpec {
@@ -232,6 +315,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 1920,
         height_px: 2560,
         dpi: 226,
+        color: false,
         description: "Supernote A5X",
     },
     TargetSpec {
@@ -241,6 +325,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 1920,
         height_px: 2560,
         dpi: 226,
+        color: false,
         description: "Supernote A5X2",
     },
     TargetSpec {
@@ -250,6 +335,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 1404,
         height_px: 1872,
         dpi: 226,
+        color: false,
         description: "Supernote A6X",
     },
     TargetSpec {
@@ -259,6 +345,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 1404,
         height_px: 1872,
         dpi: 226,
+        color: false,
         description: "Supernote A6X2",
     },
     TargetSpec {
@@ -268,6 +355,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 1860,
         height_px: 2480,
         dpi: 300,
+        color: false,
         description: "Boox Go 10.3",
     },
     TargetSpec {
@@ -277,6 +365,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 1860,
         height_px: 2480,
         dpi: 300,
+        color: false,
         description: "Boox Note Air",
     },
     TargetSpec {
@@ -286,6 +375,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 1860,
         height_px: 2480,
         dpi: 300,
+        color: true,
         description: "Boox Note Air4 C",
     },
     TargetSpec {
@@ -295,6 +385,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 930,
         height_px: 1240,
         dpi: 150,
+        color: true,
         description: "Boox Note Air4 C Color Layer",
     },
     TargetSpec {
@@ -304,6 +395,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 2400,
         height_px: 3200,
         dpi: 300,
+        color: false,
         description: "Boox Note Max",
     },
     TargetSpec {
@@ -313,6 +405,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 1240,
         height_px: 1748,
         dpi: 300,
+        color: false,
         description: "ISO A6",
     },
     TargetSpec {
@@ -322,6 +415,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 1748,
         height_px: 2480,
         dpi: 300,
+        color: false,
         description: "ISO A5",
     },
     TargetSpec {
@@ -331,6 +425,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 2480,
         height_px: 3508,
         dpi: 300,
+        color: false,
         description: "ISO A4",
     },
     TargetSpec {
@@ -340,6 +435,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 1668,
         height_px: 2420,
         dpi: 264,
+        color: true,
         description: "iPad Pro 11-inch",
     },
     TargetSpec {
@@ -349,6 +445,7 @@ const TARGET_SPECS: [TargetSpec; 21] = [
         width_px: 2064,
         height_px: 2752,
         dpi: 264,
+        color: true,
         description: "iPad Pro 13-inch",
     },
 ];
@@ -421,14 +518,21 @@ pub fn process_url(url: &str) -> String {
         Err(e) => return format!("Fetch error '{}': {}", normalized, e),
     };
 
-    match extractor::extract_article(&html, Some(&normalized)) {
-        Some(article) => article.content.to_string(),
-        None => format!("Readability failed for {}", normalized),
+    match extractor::extract(&html, Some(&normalized)) {
+        Some(extraction) => extraction.article.content.to_string(),
+        None => format!("Extraction failed for {}", normalized),
     }
 }
 
 pub fn process_url_to_pdf(url: &str, output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
-    process_url_to_pdf_with_options(url, output_path, false, "summarize", PageSize::Letter)
+    process_url_to_pdf_with_options(
+        url,
+        output_path,
+        false,
+        "summarize",
+        PageSize::Letter,
+        &multipdf::RenderOptions::default(),
+    )
 }
 
 pub fn process_url_to_pdf_with_options(
@@ -437,20 +541,31 @@ pub fn process_url_to_pdf_with_options(
     summarize: bool,
     pattern: &str,
     page_size: PageSize,
+    options: &multipdf::RenderOptions,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let normalized = fetcher::normalize_url(url)?;
-    let html = fetcher::fetch_html(&normalized)?;
-
-    if let Some(article) = extractor::extract_article(&html, Some(&normalized)) {
-        let body_html = if summarize {
-            summarize_html(article.content.as_ref(), &normalized, pattern)?
-        } else {
-            article.content.to_string()
-        };
-        pdf::generate_pdf(&article.title, &body_html, output_path, page_size)
+    let http = fetcher::shared();
+    let mut response = http.get(&normalized)?;
+    let item = ingest::ingest(&mut response, http, &ingest::IngestOptions::default())?;
+
+    let mut meta = item.meta;
+    let body_html = if summarize && item.kind.is_summarizable() {
+        // The summary may be in another language than the page declared.
+        meta.language = None;
+        summarize_html(&item.content_html, &normalized, pattern)?
     } else {
-        Err("Readability extraction failed".into())
-    }
+        item.content_html
+    };
+    let body_html = xhtml_sanitize::sanitize_content(&body_html, Some(&response.url));
+    pdf::generate_pdf(
+        &item.title,
+        &meta,
+        &body_html,
+        output_path,
+        page_size,
+        options,
+        item.source_pdf.as_deref(),
+    )
 }
 
 pub fn escape_html(input: &str) -> String {
@@ -0,0 +1,131 @@
+use dom_query::Document;
+
+use crate::escape_html;
+
+/// How hyperlinks in article bodies are rendered.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum LinkStyle {
+    /// Leave links as they are: clickable, target not printed.
+    #[default]
+    Inline,
+    /// Number external links and list their targets after the article.
+    Endnotes,
+}
+
+impl LinkStyle {
+    pub const VALUE_LIST: &'static str = "inline, endnotes";
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "inline" => Some(Self::Inline),
+            "endnotes" | "endnote" | "footnotes" => Some(Self::Endnotes),
+            _ => None,
+        }
+    }
+
+    /// Apply the style to an article body. `id_prefix` keeps endnote anchors
+    /// unique when several articles share one document.
+    pub fn apply(self, html: &str, id_prefix: &str) -> String {
+        match self {
+            Self::Inline => html.to_string(),
+            Self::Endnotes => link_endnotes(html, id_prefix),
+        }
+    }
+}
+
+fn is_external(href: &str) -> bool {
+    let lower = href.to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+/// Replace each external link with its text and a superscript reference
+/// number, and append a "Links" list with the targets. Repeated targets share
+/// a number; in-document anchors and embed placeholder links stay as they are.
+pub fn link_endnotes(html: &str, id_prefix: &str) -> String {
+    if !html.contains("<a") {
+        return html.to_string();
+    }
+    let doc = Document::fragment(html);
+    let mut notes: Vec<(String, String)> = Vec::new();
+    for link in doc.select("a[href]").iter() {
+        let href = link.attr("href").unwrap_or_default().trim().to_string();
+        if !is_external(&href) || link.is(".embed-placeholder a") {
+            continue;
+        }
+        let text = link.text().split_whitespace().collect::<Vec<_>>().join(" ");
+        let number = match notes.iter().position(|(url, _)| *url == href) {
+            Some(idx) => idx + 1,
+            None => {
+                notes.push((href, text));
+                notes.len()
+            }
+        };
+        link.replace_with_html(format!(
+            "{}<sup class=\"link-ref\"><a href=\"#{prefix}-{n}\">{n}</a></sup>",
+            link.inner_html(),
+            prefix = id_prefix,
+            n = number
+        ));
+    }
+    if notes.is_empty() {
+        return html.to_string();
+    }
+
+    let mut items = String::new();
+    for (idx, (url, text)) in notes.iter().enumerate() {
+        let safe_url = escape_html(url);
+        let label = if text.is_empty() || text == url {
+            String::new()
+        } else {
+            format!("<span class=\"link-text\">{}</span> ", escape_html(text))
+        };
+        items.push_str(&format!(
+            "<li id=\"{prefix}-{n}\">{label}<a class=\"link-url\" href=\"{url}\">{url}</a></li>\n",
+            prefix = id_prefix,
+            n = idx + 1,
+            label = label,
+            url = safe_url
+        ));
+    }
+    format!(
+        "{}\n<section class=\"link-endnotes\">\n<h2>Links</h2>\n<ol>\n{}</ol>\n</section>",
+        doc.select("html").inner_html(),
+        items
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LinkStyle, link_endnotes};
+
+    #[test]
+    fn numbers_external_links_and_lists_them_at_the_end() {
+        let html = r##"<p>See <a href="https://a.example/x">the docs</a>, <a href="#intro">above</a>
+            and <a href="https://b.example/">https://b.example/</a>, or <a href="https://a.example/x">again</a>.</p>"##;
+        let out = link_endnotes(html, "article-2-link");
+        assert!(
+            out.contains(
+                "the docs<sup class=\"link-ref\"><a href=\"#article-2-link-1\">1</a></sup>"
+            )
+        );
+        assert!(out.contains("again<sup class=\"link-ref\"><a href=\"#article-2-link-1\">1</a>"));
+        assert!(out.contains("<a href=\"#intro\">above</a>"));
+        assert!(out.contains(
+            "<li id=\"article-2-link-1\"><span class=\"link-text\">the docs</span> <a class=\"link-url\" href=\"https://a.example/x\">"
+        ));
+        assert!(out.contains(
+            "<li id=\"article-2-link-2\"><a class=\"link-url\" href=\"https://b.example/\">"
+        ));
+        assert!(!out.contains("article-2-link-3"));
+    }
+
+    #[test]
+    fn leaves_bodies_without_external_links_alone() {
+        let html = "<p><a href=\"#top\">Top</a></p>";
+        assert_eq!(link_endnotes(html, "link"), html);
+        let html = "<p><a href=\"https://a.example/\">a</a></p>";
+        assert_eq!(LinkStyle::Inline.apply(html, "link"), html);
+        assert_eq!(LinkStyle::parse(" Endnotes "), Some(LinkStyle::Endnotes));
+        assert_eq!(LinkStyle::parse("margin"), None);
+    }
+}
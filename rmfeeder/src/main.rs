@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fs::{self, File};
 use std::io::{BufRead, BufReader, Read, Write};
 use std::path::{Path, PathBuf};
@@ -8,13 +8,16 @@ use std::time::Duration;
 use chrono::Local;
 use clap::{CommandFactory, Parser};
 use reqwest::StatusCode;
-use reqwest::blocking::Client;
+use rmfeeder::article_meta::ArticleMeta;
 use rmfeeder::categorize::{CategorizeInput, categorize};
+use rmfeeder::links::LinkStyle;
 use rmfeeder::multipdf;
 use rmfeeder::{
-    PageSize, default_config_path, default_feeds_opml_path, expand_tilde_path, extractor, feeds,
-    fetcher, list_targets_csv, load_config_from_path, markdown, process_url_to_pdf_with_options,
-    state, summarize_content_html, summarize_html, youtube,
+    AppConfig, PageSize, archive, browser, canonical, charset, default_config_path,
+    default_feeds_opml_path, expand_tilde_path, extractor, feeds, fetcher, fonts, images, ingest,
+    language, list_targets_csv, load_config_from_path, markdown, pipeline,
+    process_url_to_pdf_with_options, quality, site_rules, state, summarize_content_html,
+    summarize_html, xhtml_sanitize, youtube,
 };
 
 const HELP_USAGE: &str = "\
@@ -26,7 +29,8 @@ rmfeeder [OPTIONS] <url1> [url2 ...]
        rmfeeder [OPTIONS] --markdown-dir <path>
        rmfeeder [OPTIONS] --stdin
        rmfeeder --clear-state
-       rmfeeder --list-targets";
+       rmfeeder --list-targets
+       rmfeeder --test-rule <file.html> <url>";
 
 const HELP_AFTER: &str = "\
 Examples:
@@ -130,10 +134,63 @@ struct CliArgs {
         long,
         value_name = "seconds",
         help_heading = "Output & Rendering",
-        help = "Delay between fetches"
+        help = "Minimum delay between requests to the same host"
     )]
     delay: Option<u64>,
 
+    #[arg(
+        long,
+        help_heading = "Output & Rendering",
+        help = "Skip article URLs disallowed by the site's robots.txt"
+    )]
+    respect_robots: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help_heading = "Output & Rendering",
+        help = "Number of articles fetched and extracted in parallel (default: 4)"
+    )]
+    jobs: Option<usize>,
+
+    #[arg(
+        long,
+        help_heading = "Output & Rendering",
+        help = "Bypass the on-disk HTTP cache for this run"
+    )]
+    no_cache: bool,
+
+    #[arg(
+        long,
+        value_name = "path",
+        conflicts_with = "replay",
+        help_heading = "Output & Rendering",
+        help = "Record every HTTP response of this run into a WARC file"
+    )]
+    record: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "path",
+        help_heading = "Output & Rendering",
+        help = "Serve every HTTP request from a recorded WARC file (no network)"
+    )]
+    replay: Option<String>,
+
+    #[arg(
+        long,
+        help_heading = "Output & Rendering",
+        help = "Render pages in a headless browser when static extraction finds too little text"
+    )]
+    browser_fallback: bool,
+
+    #[arg(
+        long,
+        help_heading = "Output & Rendering",
+        help = "Report the extraction method and detected language of each article"
+    )]
+    verbose: bool,
+
     #[arg(
         long,
         value_name = "name",
@@ -142,6 +199,14 @@ struct CliArgs {
     )]
     page_size: Option<String>,
 
+    #[arg(
+        long,
+        value_name = "style",
+        help_heading = "Output & Rendering",
+        help = "How to render links: inline (default) or endnotes"
+    )]
+    link_style: Option<String>,
+
     #[arg(
         long,
         help_heading = "Summarization",
@@ -157,6 +222,14 @@ struct CliArgs {
     )]
     pattern: Option<String>,
 
+    #[arg(
+        long,
+        value_name = "N",
+        help_heading = "Summarization",
+        help = "Number of fabric summaries run in parallel (default: 2)"
+    )]
+    summarize_jobs: Option<usize>,
+
     #[arg(
         long,
         value_name = "N",
@@ -230,6 +303,66 @@ struct CliArgs {
         help = "Print page-size target table as CSV and exit"
     )]
     list_targets: bool,
+
+    #[arg(
+        long,
+        value_name = "file.html",
+        help_heading = "Maintenance",
+        help = "Extract a saved page with the site rule for <url>, print the result and exit"
+    )]
+    test_rule: Option<String>,
+}
+
+#[derive(Default)]
+struct RunCounts {
+    attempted: usize,
+    included: usize,
+    /// Included items that came from an archived copy.
+    recovered: usize,
+    skipped: BTreeMap<&'static str, usize>,
+    failed: usize,
+    /// Included HTML articles per extraction method.
+    extracted_by: BTreeMap<&'static str, usize>,
+}
+
+impl RunCounts {
+    fn skip(&mut self, reason: &'static str) {
+        *self.skipped.entry(reason).or_default() += 1;
+    }
+
+    fn extraction_line(&self) -> String {
+        let parts: Vec<String> = self
+            .extracted_by
+            .iter()
+            .map(|(method, count)| format!("{}={}", method, count))
+            .collect();
+        format!("Extraction: {}", parts.join(" "))
+    }
+
+    fn summary_line(&self, http: &fetcher::Fetcher) -> String {
+        let skipped: usize = self.skipped.values().sum();
+        let reasons = if self.skipped.is_empty() {
+            String::new()
+        } else {
+            let parts: Vec<String> = self
+                .skipped
+                .iter()
+                .map(|(reason, count)| format!("{}={}", reason, count))
+                .collect();
+            format!(" [{}]", parts.join(" "))
+        };
+        format!(
+            "Summary: attempted={} included={} recovered={} skipped={}{} failed={} retries={} cached={}",
+            self.attempted,
+            self.included,
+            self.recovered,
+            skipped,
+            reasons,
+            self.failed,
+            http.retry_count(),
+            http.cache_hit_count()
+        )
+    }
 }
 
 struct UrlCandidate {
@@ -237,6 +370,41 @@ struct UrlCandidate {
     source: &'static str,
     use_seen_state: bool,
     toc_section: Option<String>,
+    /// Author, date and feed title from the feed entry, for feed items.
+    feed_meta: Option<ArticleMeta>,
+}
+
+impl UrlCandidate {
+    /// Passed on the command line or in a URL file rather than found in a feed.
+    fn is_explicit(&self) -> bool {
+        matches!(self.source, "arg" | "file")
+    }
+}
+
+struct ItemOptions<'a> {
+    summarize: bool,
+    pattern: &'a str,
+    summarize_slots: &'a pipeline::Semaphore,
+    canonicalizer: &'a canonical::Canonicalizer,
+    ingest: &'a ingest::IngestOptions,
+    archive: Option<&'a archive::ArchiveFallback>,
+    quality: Option<&'a quality::QualityGate>,
+    verbose: bool,
+}
+
+enum ItemOutcome {
+    /// `canonical_url` comes from `<link rel="canonical">` or the final URL after redirects.
+    /// `recovered` items were built from an archived snapshot; `flagged` items failed
+    /// the quality check but were kept with a warning.
+    Included {
+        article: Box<PendingArticle>,
+        canonical_url: String,
+        recovered: bool,
+        flagged: bool,
+        extracted_by: Option<extractor::ExtractionMethod>,
+    },
+    Skipped(&'static str),
+    Failed,
 }
 
 struct PendingArticle {
@@ -245,6 +413,19 @@ struct PendingArticle {
     summary_for_cat: String,
     content_html: String,
     fallback_section: Option<String>,
+    meta: ArticleMeta,
+    source_pdf: Option<Vec<u8>>,
+}
+
+fn mark_seen_keys(state: Option<&mut state::StateDb>, use_seen_state: bool, keys: &[&str]) {
+    let Some(db) = state.filter(|_| use_seen_state) else {
+        return;
+    };
+    for key in keys {
+        if let Err(e) = db.mark_seen(key) {
+            eprintln!("Warning: failed to update state for {}: {}", key, e);
+        }
+    }
 }
 
 fn domain_from_url(url: &str) -> Option<String> {
@@ -290,12 +471,37 @@ fn main() {
         }
     };
 
+    if let Some(path) = cli.test_rule.as_deref() {
+        run_test_rule(path, cli.urls.first().map(String::as_str), config.as_ref());
+        return;
+    }
+
+    let mut fetch_settings = fetcher::FetchSettings::from_config(config.as_ref());
+    if cli.no_cache {
+        fetch_settings.cache_path = None;
+    }
+    if let Some(value) = cli.delay {
+        fetch_settings.host_delay = Duration::from_secs(value);
+    }
+    if cli.respect_robots {
+        fetch_settings.respect_robots = true;
+    }
+    fetch_settings.record_path = cli.record.as_deref().map(expand_tilde_path);
+    fetch_settings.replay_path = cli.replay.as_deref().map(expand_tilde_path);
+    if fetch_settings.replay_path.is_some() {
+        // Replayed runs are reproducible; waiting between archived responses is pointless.
+        fetch_settings.host_delay = Duration::ZERO;
+    }
+    let http = fetcher::install(fetch_settings).unwrap_or_else(|e| {
+        eprintln!("Error: failed to build HTTP client: {}", e);
+        std::process::exit(1);
+    });
+
     let input_file: Option<String> = cli.file.clone();
     let output_path: Option<String> = cli.output.as_deref().map(|p| {
         expand_tilde_path(p).to_string_lossy().into_owned()
     });
     let mut output_dir: Option<String> = config.as_ref().and_then(|c| c.output_dir.clone());
-    let mut delay_secs: u64 = config.as_ref().and_then(|c| c.delay).unwrap_or(0);
     let mut summarize = config.as_ref().and_then(|c| c.summarize).unwrap_or(false);
     let mut pattern: String = config
         .as_ref()
@@ -306,6 +512,19 @@ fn main() {
         .and_then(|c| c.page_size.as_deref())
         .map(parse_page_size)
         .unwrap_or(PageSize::Letter);
+    let mut link_style = config
+        .as_ref()
+        .and_then(|c| c.link_style.as_deref())
+        .map(parse_link_style)
+        .unwrap_or_default();
+    let mut fetch_jobs: usize = config
+        .as_ref()
+        .and_then(|c| c.fetch_concurrency)
+        .unwrap_or(4);
+    let mut summarize_jobs: usize = config
+        .as_ref()
+        .and_then(|c| c.summarize_concurrency)
+        .unwrap_or(2);
 
     let mut feeds_enabled = cli.feeds;
     let yt_watchlist_enabled = cli.yt_watchlist;
@@ -339,15 +558,21 @@ fn main() {
     let markdown_dir: Option<String> = cli.markdown_dir.clone();
     let stdin_enabled = cli.stdin;
     let mut feeds_file_flag_used = false;
-    if let Some(value) = cli.delay {
-        delay_secs = value;
-    }
     if let Some(value) = cli.page_size {
         page_size = parse_page_size(&value);
     }
+    if let Some(value) = cli.link_style {
+        link_style = parse_link_style(&value);
+    }
     if cli.summarize {
         summarize = true;
     }
+    if let Some(value) = cli.jobs {
+        fetch_jobs = value;
+    }
+    if let Some(value) = cli.summarize_jobs {
+        summarize_jobs = value;
+    }
     if let Some(value) = cli.pattern {
         pattern = value;
         summarize = true;
@@ -393,17 +618,17 @@ fn main() {
         None
     };
 
-    if let Some((mode, msg)) = clear_mode_for_exit {
-        if !explicit_input_requested {
-            match state::init_state_db(mode, state_db_path.take()) {
-                Ok(_) => {
-                    println!("{}", msg);
-                    return;
-                }
-                Err(e) => {
-                    eprintln!("Error: failed to clear state DB: {}", e);
-                    std::process::exit(1);
-                }
+    if let Some((mode, msg)) = clear_mode_for_exit
+        && !explicit_input_requested
+    {
+        match state::init_state_db(mode, state_db_path.take()) {
+            Ok(_) => {
+                println!("{}", msg);
+                return;
+            }
+            Err(e) => {
+                eprintln!("Error: failed to clear state DB: {}", e);
+                std::process::exit(1);
             }
         }
     }
@@ -467,11 +692,18 @@ fn main() {
     }
 
     let selected_source_kind = selected_sources.first().map(|s| s.kind);
+    let render = multipdf::RenderOptions {
+        link_style,
+        hyphenation: language::Hyphenation::from_config(config.as_ref()),
+        fonts: fonts::FontStacks::from_config(config.as_ref()),
+        images: images::ImageOptions::from_config(config.as_ref()),
+        ..Default::default()
+    };
 
     if let Some(path) = markdown_file {
         let output_path =
             output_path.unwrap_or_else(|| render_output_path("Note", summarize, output_dir.take()));
-        run_markdown_file_mode(&path, &output_path, summarize, &pattern, page_size);
+        run_markdown_file_mode(&path, &output_path, summarize, &pattern, page_size, render);
         return;
     }
 
@@ -487,6 +719,7 @@ fn main() {
             &pattern,
             markdown_limit,
             page_size,
+            render,
             no_categories,
         );
         return;
@@ -495,7 +728,7 @@ fn main() {
     if stdin_enabled {
         let output_path =
             output_path.unwrap_or_else(|| render_output_path("Note", summarize, output_dir.take()));
-        run_stdin_mode(&output_path, summarize, &pattern, page_size);
+        run_stdin_mode(&output_path, summarize, &pattern, page_size, render);
         return;
     }
 
@@ -506,6 +739,7 @@ fn main() {
             source: "arg",
             use_seen_state: false,
             toc_section: None,
+            feed_meta: None,
         })
         .collect();
 
@@ -529,6 +763,7 @@ fn main() {
                 source: "file",
                 use_seen_state: false,
                 toc_section: None,
+                feed_meta: None,
             });
         }
     }
@@ -557,7 +792,14 @@ fn main() {
         eprintln!("Page size: {}", page_size.as_str());
 
         let url = &direct_urls[0];
-        match process_url_to_pdf_with_options(url, &output_path, summarize, &pattern, page_size) {
+        match process_url_to_pdf_with_options(
+            url,
+            &output_path,
+            summarize,
+            &pattern,
+            page_size,
+            &render,
+        ) {
             Ok(_) => println!("Wrote {}", output_path),
             Err(e) => {
                 eprintln!("Error: {}", e);
@@ -590,10 +832,7 @@ fn main() {
         || config.as_ref().and_then(|c| c.categorize).map(|v| !v).unwrap_or(false);
 
     let mut pending: Vec<PendingArticle> = Vec::new();
-    let mut attempted = 0usize;
-    let mut included = 0usize;
-    let mut skipped = 0usize;
-    let mut failed = 0usize;
+    let mut counts = RunCounts::default();
 
     let clear_mode = if clear_state {
         state::ClearMode::All
@@ -605,7 +844,11 @@ fn main() {
         state::ClearMode::None
     };
 
-    let mut state = if !matches!(clear_mode, state::ClearMode::None) || feeds_enabled || yt_watchlist_enabled {
+    // Replayed runs neither consult nor update seen-state so they reproduce the recorded bundle.
+    let replaying = cli.replay.is_some() && matches!(clear_mode, state::ClearMode::None);
+    let mut state = if !replaying
+        && (!matches!(clear_mode, state::ClearMode::None) || feeds_enabled || yt_watchlist_enabled)
+    {
         match state::init_state_db(clear_mode, state_db_path.take()) {
             Ok(db) => Some(db),
             Err(e) => {
@@ -618,6 +861,7 @@ fn main() {
     };
 
     let mut seen_urls_in_run = HashSet::new();
+    let canonicalizer = canonical::Canonicalizer::from_config(config.as_ref());
 
     if feeds_enabled {
         let opml_path =
@@ -634,23 +878,16 @@ fn main() {
         if feed_sources.is_empty() {
             eprintln!("Warning: no feed URLs found in {}", opml_path);
         } else {
-            let client = Client::builder()
-                .user_agent("rmfeeder/0.1 (+https://example.com)")
-                .build()
-                .unwrap_or_else(|e| {
-                    eprintln!("Error: failed to build HTTP client: {}", e);
-                    std::process::exit(1);
-                });
-
             for feed_source in feed_sources {
-                match feeds::fetch_feed_links(&client, &feed_source.feed_url, feeds_limit) {
-                    Ok(links) => {
-                        for link in links {
+                match feeds::fetch_feed_entries(http, &feed_source.feed_url, feeds_limit) {
+                    Ok(entries) => {
+                        for entry in entries {
                             url_candidates.push(UrlCandidate {
-                                url: link,
+                                url: entry.url,
                                 source: "feeds",
                                 use_seen_state: true,
                                 toc_section: feed_source.section.clone(),
+                                feed_meta: Some(entry.meta),
                             });
                         }
                     }
@@ -662,19 +899,30 @@ fn main() {
         }
     }
 
+    // Dedupe and seen-state use canonical keys; the original URL is kept for messages.
+    let mut work: Vec<(UrlCandidate, String)> = Vec::new();
     for candidate in url_candidates {
-        if !seen_urls_in_run.insert(candidate.url.clone()) {
+        let key = canonicalizer.canonicalize(&candidate.url);
+        if !seen_urls_in_run.insert(key.clone()) {
             continue;
         }
 
-        attempted += 1;
+        counts.attempted += 1;
 
         if candidate.use_seen_state
             && let Some(db) = state.as_mut()
         {
-            match db.should_emit(&candidate.url) {
+            // Rows written before canonicalization hold the raw URL, so check both.
+            let emit = db.should_emit(&key).and_then(|emit| {
+                if emit && key != candidate.url {
+                    db.should_emit(&candidate.url)
+                } else {
+                    Ok(emit)
+                }
+            });
+            match emit {
                 Ok(false) => {
-                    skipped += 1;
+                    counts.skip("seen");
                     eprintln!(
                         "already seen, skipping item: {} [source={}]",
                         candidate.url, candidate.source
@@ -688,79 +936,87 @@ fn main() {
             }
         }
 
-        eprintln!("Fetching {}", candidate.url);
-        let normalized = match fetcher::normalize_url(&candidate.url) {
-            Ok(value) => value,
-            Err(e) => {
-                failed += 1;
-                eprintln!("Skipping {}: invalid URL: {}", candidate.url, e);
-                continue;
+        work.push((candidate, key));
+    }
+
+    let summarize_slots = pipeline::Semaphore::new(summarize_jobs);
+    let archive_fallback = archive::ArchiveFallback::from_config(config.as_ref());
+    let quality_gate = quality::QualityGate::from_config(config.as_ref());
+    let mut ingest_options = ingest::IngestOptions::from_config(config.as_ref());
+    if cli.browser_fallback {
+        match ingest_options.browser.as_mut() {
+            Some(fallback) => fallback.auto = true,
+            None => {
+                ingest_options.browser =
+                    browser::BrowserFallback::from_config(config.as_ref(), true)
             }
-        };
+        }
+    }
+    let item_options = ItemOptions {
+        summarize,
+        pattern: &pattern,
+        summarize_slots: &summarize_slots,
+        canonicalizer: &canonicalizer,
+        ingest: &ingest_options,
+        archive: archive_fallback.as_ref(),
+        quality: quality_gate.as_ref(),
+        verbose: cli.verbose,
+    };
+    let outcomes = pipeline::map_ordered(&work, fetch_jobs, |(candidate, _)| {
+        process_candidate(candidate, http, &item_options)
+    });
 
-        let html = match fetcher::fetch_html(&normalized) {
-            Ok(body) => body,
-            Err(e) => {
-                failed += 1;
-                if let Some(status) = e.status() {
-                    if status == StatusCode::FORBIDDEN {
-                        eprintln!("Skipping {}: got 403 Forbidden", candidate.url);
-                    } else {
-                        eprintln!("Skipping {}: HTTP {}", candidate.url, status);
+    // Results are applied in candidate order so the bundle and state DB stay deterministic.
+    for ((candidate, key), outcome) in work.iter().zip(outcomes) {
+        match outcome {
+            ItemOutcome::Included {
+                article,
+                canonical_url,
+                recovered,
+                flagged,
+                extracted_by,
+            } => {
+                // The page may reveal a canonical URL that an earlier item already used.
+                let mut keys = vec![key.as_str()];
+                if canonical_url != *key {
+                    keys.push(canonical_url.as_str());
+                    let duplicate_in_run = !seen_urls_in_run.insert(canonical_url.clone());
+                    let seen_before = candidate.use_seen_state
+                        && state
+                            .as_mut()
+                            .is_some_and(|db| matches!(db.should_emit(&canonical_url), Ok(false)));
+                    if duplicate_in_run || seen_before {
+                        let reason = if duplicate_in_run {
+                            "duplicate"
+                        } else {
+                            "seen"
+                        };
+                        counts.skip(reason);
+                        eprintln!(
+                            "{} as {}, skipping item: {} [source={}]",
+                            reason, canonical_url, candidate.url, candidate.source
+                        );
+                        // Remember the original URL so the next run skips it before fetching.
+                        mark_seen_keys(state.as_mut(), candidate.use_seen_state, &[key]);
+                        continue;
                     }
-                } else {
-                    eprintln!("Skipping {}: request error: {}", candidate.url, e);
                 }
-                continue;
-            }
-        };
-
-        let article = match extractor::extract_article(&html, Some(&normalized)) {
-            Some(value) => value,
-            None => {
-                failed += 1;
-                eprintln!("Skipping {}: extraction failed", candidate.url);
-                continue;
-            }
-        };
 
-        let title = article.title;
-        let content_html = if summarize {
-            match summarize_html(article.content.as_ref(), &normalized, &pattern) {
-                Ok(value) => value,
-                Err(e) => {
-                    failed += 1;
-                    eprintln!("Skipping {}: summary failed: {}", candidate.url, e);
-                    continue;
+                pending.push(*article);
+                counts.included += 1;
+                if recovered {
+                    counts.recovered += 1;
+                }
+                if let Some(method) = extracted_by {
+                    *counts.extracted_by.entry(method.as_str()).or_default() += 1;
+                }
+                // Flagged items stay unseen so a later run can pick up the full article.
+                if !flagged {
+                    mark_seen_keys(state.as_mut(), candidate.use_seen_state, &keys);
                 }
             }
-        } else {
-            article.content.to_string()
-        };
-
-        let source_hint = domain_from_url(&candidate.url)
-            .unwrap_or_else(|| candidate.source.to_string());
-        pending.push(PendingArticle {
-            summary_for_cat: title.clone(),
-            source_hint,
-            title,
-            content_html,
-            fallback_section: candidate.toc_section.clone(),
-        });
-        included += 1;
-
-        if candidate.use_seen_state
-            && let Some(db) = state.as_mut()
-            && let Err(e) = db.mark_seen(&candidate.url)
-        {
-            eprintln!(
-                "Warning: failed to update state for {}: {}",
-                candidate.url, e
-            );
-        }
-
-        if delay_secs > 0 {
-            thread::sleep(Duration::from_secs(delay_secs));
+            ItemOutcome::Skipped(reason) => counts.skip(reason),
+            ItemOutcome::Failed => counts.failed += 1,
         }
     }
 
@@ -780,13 +1036,13 @@ fn main() {
                 break;
             }
 
-            attempted += 1;
+            counts.attempted += 1;
             let state_key = format!("yt::{}", video.url);
 
             if let Some(db) = state.as_mut() {
                 match db.should_emit(&state_key) {
                     Ok(false) => {
-                        skipped += 1;
+                        counts.skip("seen");
                         eprintln!(
                             "already seen, skipping item: {} [source=yt-watchlist]",
                             video.url
@@ -804,7 +1060,7 @@ fn main() {
             let summary_text = match youtube::fetch_video_summary_text(&video.url, &yt_pattern) {
                 Ok(value) => value,
                 Err(e) => {
-                    failed += 1;
+                    counts.failed += 1;
                     eprintln!("Skipping {}: summary failed: {}", video.url, e);
                     continue;
                 }
@@ -833,8 +1089,10 @@ fn main() {
                 title: article_title,
                 content_html: body_html,
                 fallback_section: Some("YouTube Watchlist".to_string()),
+                meta: ArticleMeta::default(),
+                source_pdf: None,
             });
-            included += 1;
+            counts.included += 1;
             yt_included += 1;
 
             if let Some(db) = state.as_mut()
@@ -857,12 +1115,12 @@ fn main() {
 
     let articles = build_articles(pending, no_categories);
 
+    if cli.verbose && !counts.extracted_by.is_empty() {
+        eprintln!("{}", counts.extraction_line());
+    }
     if articles.is_empty() {
         eprintln!("Error: no items were included in output");
-        eprintln!(
-            "Summary: attempted={} included={} skipped={} failed={}",
-            attempted, included, skipped, failed
-        );
+        eprintln!("{}", counts.summary_line(http));
         std::process::exit(1);
     }
 
@@ -875,18 +1133,16 @@ fn main() {
     }
     eprintln!("Page size: {}", page_size.as_str());
 
-    match multipdf::generate_pdf_bundle_with_sections(
+    match multipdf::generate_pdf_bundle_with_render_options(
         &articles,
         &output_path,
         "rmfeeder ::<br>Reading Bundle",
         "Collected Articles",
         page_size,
+        render,
     ) {
         Ok(_) => {
-            eprintln!(
-                "Summary: attempted={} included={} skipped={} failed={}",
-                attempted, included, skipped, failed
-            );
+            eprintln!("{}", counts.summary_line(http));
             println!("Wrote {}", output_path);
         }
         Err(e) => {
@@ -896,12 +1152,213 @@ fn main() {
     }
 }
 
+/// Fetch, extract and optionally summarize one URL. Runs on pipeline worker threads.
+fn process_candidate(
+    candidate: &UrlCandidate,
+    http: &fetcher::Fetcher,
+    options: &ItemOptions<'_>,
+) -> ItemOutcome {
+    let normalized = match fetcher::normalize_url(&candidate.url) {
+        Ok(value) => value,
+        Err(e) => {
+            eprintln!("Skipping {}: invalid URL: {}", candidate.url, e);
+            return ItemOutcome::Failed;
+        }
+    };
+
+    if !http.robots_allowed(&normalized) {
+        eprintln!("Skipping {}: blocked by robots.txt", candidate.url);
+        return ItemOutcome::Skipped("robots");
+    }
+
+    eprintln!("Fetching {}", candidate.url);
+
+    let (mut response, snapshot) = match http.get(&normalized) {
+        Ok(response) => (response, None),
+        Err(e) => {
+            if let Some(fallback) = options.archive
+                && archive::ArchiveFallback::should_try(&e)
+            {
+                eprintln!(
+                    "Fetch of {} failed ({}); looking for an archived copy",
+                    candidate.url, e
+                );
+                match fallback.recover(http, &normalized) {
+                    Ok((snapshot, response)) => {
+                        eprintln!("Recovered {} from {}", candidate.url, snapshot.url);
+                        (response, Some(snapshot))
+                    }
+                    Err(archive_err) => {
+                        eprintln!(
+                            "Skipping {}: {}; no archived copy: {}",
+                            candidate.url, e, archive_err
+                        );
+                        return ItemOutcome::Failed;
+                    }
+                }
+            } else {
+                report_fetch_error(&candidate.url, &e);
+                return ItemOutcome::Failed;
+            }
+        }
+    };
+
+    let item = match ingest::ingest(&mut response, http, options.ingest) {
+        Ok(item) => item,
+        Err(e) => {
+            eprintln!("Skipping {}: {}", candidate.url, e);
+            return ItemOutcome::Failed;
+        }
+    };
+    if options.verbose
+        && let Some(method) = item.method
+    {
+        eprintln!("Extracted {} via {}", candidate.url, method);
+    }
+    let mut quality_issue = None;
+    if item.kind == ingest::ContentKind::Html
+        && let Some(gate) = options.quality
+        && let Some(issue) = gate.check_item(&item, Some(&response.text()), candidate.is_explicit())
+    {
+        if gate.action == quality::QualityAction::Skip {
+            eprintln!("Skipping {}: {}", candidate.url, issue);
+            return ItemOutcome::Skipped(issue.category());
+        }
+        eprintln!("Warning: {}: {}; keeping it unseen", candidate.url, issue);
+        quality_issue = Some(issue);
+    }
+    let canonical_url = if snapshot.is_some() {
+        // The response URL is the archive's; key the item by what was asked for.
+        options.canonicalizer.canonicalize(&normalized)
+    } else if item.kind == ingest::ContentKind::Html {
+        canonical::canonical_for_page(options.canonicalizer, &response.text(), &response.url)
+    } else {
+        options.canonicalizer.canonicalize(&response.url)
+    };
+
+    let title = item.title;
+    let summarized = options.summarize && item.kind.is_summarizable();
+    let content_html = if summarized {
+        let _permit = options.summarize_slots.acquire();
+        match summarize_html(&item.content_html, &normalized, options.pattern) {
+            Ok(value) => value,
+            Err(e) => {
+                eprintln!("Skipping {}: summary failed: {}", candidate.url, e);
+                return ItemOutcome::Failed;
+            }
+        }
+    } else {
+        item.content_html
+    };
+    // Archived pages resolve relative links against the original site, not the archive.
+    let base_url = if snapshot.is_some() {
+        normalized.as_str()
+    } else {
+        response.url.as_str()
+    };
+    let content_html = xhtml_sanitize::sanitize_content(&content_html, Some(base_url));
+
+    let mut meta = item.meta;
+    if let Some(feed_meta) = &candidate.feed_meta {
+        meta.merge_feed(feed_meta);
+    }
+    // A summary isn't necessarily in the page's language; go by its own text.
+    let declared = if summarized {
+        None
+    } else {
+        meta.language.as_deref()
+    };
+    meta.language = language::resolve(declared, &content_html);
+    let recovered = snapshot.is_some();
+    meta.archived = snapshot;
+    if options.verbose {
+        eprintln!(
+            "Language of {}: {}",
+            candidate.url,
+            meta.language.as_deref().unwrap_or("unknown")
+        );
+    }
+
+    let content_html = match &quality_issue {
+        Some(issue) => format!("{}\n{}", issue.warning_html(), content_html),
+        None => content_html,
+    };
+
+    let source_hint =
+        domain_from_url(&candidate.url).unwrap_or_else(|| candidate.source.to_string());
+    ItemOutcome::Included {
+        article: Box::new(PendingArticle {
+            summary_for_cat: title.clone(),
+            source_hint,
+            title,
+            content_html,
+            fallback_section: candidate.toc_section.clone(),
+            meta,
+            source_pdf: item.source_pdf,
+        }),
+        canonical_url,
+        recovered,
+        flagged: quality_issue.is_some(),
+        extracted_by: item.method,
+    }
+}
+
+fn report_fetch_error(url: &str, e: &fetcher::FetchError) {
+    if let Some(status) = e.status() {
+        if status == StatusCode::FORBIDDEN {
+            eprintln!("Skipping {}: got 403 Forbidden", url);
+        } else {
+            eprintln!("Skipping {}: HTTP {}", url, status);
+        }
+    } else {
+        eprintln!("Skipping {}: request error: {}", url, e);
+    }
+}
+
+fn run_test_rule(path: &str, url: Option<&str>, config: Option<&AppConfig>) {
+    let Some(url) = url else {
+        eprintln!("Error: --test-rule needs the page URL: --test-rule <file.html> <url>");
+        std::process::exit(2);
+    };
+    let html = fs::read(expand_tilde_path(path)).unwrap_or_else(|e| {
+        eprintln!("Error: failed to read {}: {}", path, e);
+        std::process::exit(1);
+    });
+    let html = charset::decode_html(&html, None);
+
+    let rules = site_rules::SiteRules::from_config(config);
+    let rule = rules.rule_for(url);
+    match rule {
+        Some(rule) => println!(
+            "Rule: {}{}",
+            rule.hosts.join(", "),
+            if rule.skip_readability {
+                " (skip_readability)"
+            } else {
+                ""
+            }
+        ),
+        None => println!("Rule: none"),
+    }
+
+    let Some(extractor::Extraction { article, method }) = rules.extract(&html, Some(url)) else {
+        eprintln!("Error: extraction failed for {}", path);
+        std::process::exit(1);
+    };
+    println!("Extracted by: {}", method);
+    println!("Title: {}", article.title);
+    println!("Length: {}", article.length);
+    println!("---");
+    println!("{}", article.content);
+}
+
 fn run_markdown_file_mode(
     path: &str,
     output_path: &str,
     summarize: bool,
     pattern: &str,
     page_size: PageSize,
+    render: multipdf::RenderOptions,
 ) {
     let path_buf = PathBuf::from(path);
     if !path_buf.is_file() {
@@ -923,8 +1380,11 @@ fn run_markdown_file_mode(
         &articles[0].title,
         &cover_subtitle,
         page_size,
-        false,
-        false,
+        multipdf::RenderOptions {
+            include_toc: false,
+            include_back_to_toc_links: false,
+            ..render
+        },
     ) {
         Ok(_) => println!("Wrote {}", output_path),
         Err(e) => {
@@ -934,7 +1394,10 @@ fn run_markdown_file_mode(
     }
 }
 
-fn build_articles(pending: Vec<PendingArticle>, no_categories: bool) -> Vec<multipdf::BundleArticle> {
+fn build_articles(
+    mut pending: Vec<PendingArticle>,
+    no_categories: bool,
+) -> Vec<multipdf::BundleArticle> {
     if no_categories || pending.len() <= 1 {
         return pending
             .into_iter()
@@ -942,6 +1405,8 @@ fn build_articles(pending: Vec<PendingArticle>, no_categories: bool) -> Vec<mult
                 section: p.fallback_section,
                 title: p.title,
                 content_html: p.content_html,
+                meta: p.meta,
+                source_pdf: p.source_pdf,
             })
             .collect();
     }
@@ -972,6 +1437,8 @@ fn build_articles(pending: Vec<PendingArticle>, no_categories: bool) -> Vec<mult
                         section: Some(group.name.clone()),
                         title: pending[idx].title.clone(),
                         content_html: pending[idx].content_html.clone(),
+                        meta: pending[idx].meta.clone(),
+                        source_pdf: pending[idx].source_pdf.take(),
                     });
                 }
             }
@@ -983,6 +1450,8 @@ fn build_articles(pending: Vec<PendingArticle>, no_categories: bool) -> Vec<mult
                     section: Some("Other".to_string()),
                     title: pending[idx].title.clone(),
                     content_html: pending[idx].content_html.clone(),
+                    meta: pending[idx].meta.clone(),
+                    source_pdf: pending[idx].source_pdf.take(),
                 });
             }
             let unclaimed: Vec<usize> = (0..n).filter(|i| !seen.contains(i)).collect();
@@ -996,6 +1465,8 @@ fn build_articles(pending: Vec<PendingArticle>, no_categories: bool) -> Vec<mult
                         section: Some("Other".to_string()),
                         title: pending[idx].title.clone(),
                         content_html: pending[idx].content_html.clone(),
+                        meta: pending[idx].meta.clone(),
+                        source_pdf: pending[idx].source_pdf.take(),
                     });
                 }
             }
@@ -1007,6 +1478,8 @@ fn build_articles(pending: Vec<PendingArticle>, no_categories: bool) -> Vec<mult
                         section: p.fallback_section,
                         title: p.title,
                         content_html: p.content_html,
+                        meta: p.meta,
+                        source_pdf: p.source_pdf,
                     })
                     .collect()
             } else {
@@ -1024,12 +1497,15 @@ fn build_articles(pending: Vec<PendingArticle>, no_categories: bool) -> Vec<mult
                     section: p.fallback_section,
                     title: p.title,
                     content_html: p.content_html,
+                    meta: p.meta,
+                    source_pdf: p.source_pdf,
                 })
                 .collect()
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn run_markdown_dir_mode(
     path: &str,
     output_path: &str,
@@ -1037,6 +1513,7 @@ fn run_markdown_dir_mode(
     pattern: &str,
     limit: Option<usize>,
     page_size: PageSize,
+    render: multipdf::RenderOptions,
     no_categories: bool,
 ) {
     let dir_path = PathBuf::from(path);
@@ -1079,6 +1556,8 @@ fn run_markdown_dir_mode(
             title: article.title,
             content_html: article.content_html,
             fallback_section: None,
+            meta: article.meta,
+            source_pdf: None,
         });
     }
 
@@ -1094,12 +1573,13 @@ fn run_markdown_dir_mode(
         dir_path.to_string_lossy(),
         articles.len()
     );
-    match multipdf::generate_pdf_bundle_with_sections(
+    match multipdf::generate_pdf_bundle_with_render_options(
         &articles,
         output_path,
         &bundle_title,
         &cover_subtitle,
         page_size,
+        render,
     ) {
         Ok(_) => println!("Wrote {}", output_path),
         Err(e) => {
@@ -1109,7 +1589,13 @@ fn run_markdown_dir_mode(
     }
 }
 
-fn run_stdin_mode(output_path: &str, summarize: bool, pattern: &str, page_size: PageSize) {
+fn run_stdin_mode(
+    output_path: &str,
+    summarize: bool,
+    pattern: &str,
+    page_size: PageSize,
+    render: multipdf::RenderOptions,
+) {
     let mut input = String::new();
     std::io::stdin()
         .read_to_string(&mut input)
@@ -1135,8 +1621,11 @@ fn run_stdin_mode(output_path: &str, summarize: bool, pattern: &str, page_size:
         &articles[0].title,
         cover_subtitle,
         page_size,
-        false,
-        false,
+        multipdf::RenderOptions {
+            include_toc: false,
+            include_back_to_toc_links: false,
+            ..render
+        },
     ) {
         Ok(_) => println!("Wrote {}", output_path),
         Err(e) => {
@@ -1195,11 +1684,14 @@ fn markdown_content_to_bundle_article(
     } else {
         rendered_html
     };
+    let content_html = xhtml_sanitize::sanitize_content(&content_html, None);
 
     Ok(multipdf::BundleArticle {
         section: None,
         title,
         content_html,
+        meta: ArticleMeta::default(),
+        source_pdf: None,
     })
 }
 
@@ -1238,6 +1730,16 @@ fn parse_page_size(value: &str) -> PageSize {
     })
 }
 
+fn parse_link_style(value: &str) -> LinkStyle {
+    LinkStyle::parse(value).unwrap_or_else(|| {
+        eprintln!(
+            "Error: --link-style must be one of: {}",
+            LinkStyle::VALUE_LIST
+        );
+        std::process::exit(1);
+    })
+}
+
 fn print_usage_and_exit(code: i32) -> ! {
     let mut command = CliArgs::command();
     if code == 0 {
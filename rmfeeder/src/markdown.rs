@@ -1,4 +1,6 @@
-use pulldown_cmark::{Options, Parser, html};
+use pulldown_cmark::{CowStr, Event, Options, Parser, html};
+
+use crate::math;
 
 pub fn strip_yaml_frontmatter(content: &str) -> String {
     if !content.starts_with("---\n") {
@@ -48,8 +50,13 @@ pub fn markdown_to_html(input: &str) -> String {
     options.insert(Options::ENABLE_TABLES);
     options.insert(Options::ENABLE_TASKLISTS);
     options.insert(Options::ENABLE_FOOTNOTES);
+    options.insert(Options::ENABLE_MATH);
 
-    let parser = Parser::new_ext(input, options);
+    let parser = Parser::new_ext(input, options).map(|event| match event {
+        Event::InlineMath(tex) => Event::InlineHtml(CowStr::from(math::tex_to_svg(&tex, false))),
+        Event::DisplayMath(tex) => Event::InlineHtml(CowStr::from(math::tex_to_svg(&tex, true))),
+        other => other,
+    });
     let mut out = String::new();
     html::push_html(&mut out, parser);
     out
@@ -96,4 +103,14 @@ mod tests {
         assert!(html.contains("type=\"checkbox\""));
         assert!(html.contains("<del>gone</del>"));
     }
+
+    #[test]
+    fn renders_tex_math_as_svg() {
+        let html = markdown_to_html("Euler: $e^{i\\pi}$\n\n$$\\frac{1}{2}$$\n\nCosts \\$5.");
+        assert!(html.contains("<span class=\"math\" role=\"img\" aria-label=\"e^{i\\pi}\"><svg"));
+        assert!(html.contains(
+            "<span class=\"math math-display\" role=\"img\" aria-label=\"\\frac{1}{2}\">"
+        ));
+        assert!(html.contains("Costs $5."));
+    }
 }
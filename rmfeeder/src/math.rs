@@ -0,0 +1,255 @@
+use dom_query::{Document, NodeRef};
+use ratex_layout::{LayoutOptions, layout, to_display_list};
+use ratex_svg::{SvgOptions, render_to_svg};
+use ratex_types::math_style::MathStyle;
+
+use crate::escape_html;
+
+/// Elements whose text is never scanned for `\(...\)`, `\[...\]` or `$$...$$`.
+const VERBATIM_ELEMENTS: &[&str] = &[
+    "pre", "code", "kbd", "samp", "script", "style", "math", "svg",
+];
+
+/// SVG user units per em. Glyph outlines are emitted at this scale and the
+/// `<svg>` is then sized in `em` so it follows the surrounding text.
+const UNITS_PER_EM: f64 = 40.0;
+
+/// Typeset TeX math as an inline `<svg>` with the glyphs drawn as paths, so
+/// WeasyPrint needs no math font. Layout follows KaTeX. The wrapper keeps the
+/// TeX source as its label. Unsupported input is shown as TeX code instead.
+pub fn tex_to_svg(tex: &str, display: bool) -> String {
+    let class = if display { "math math-display" } else { "math" };
+    let source = escape_html(tex.trim());
+    let ast = match ratex_parser::parser::parse(tex) {
+        Ok(ast) => ast,
+        Err(_) => return format!("<code class=\"{class} math-error\">{source}</code>"),
+    };
+    let style = if display {
+        MathStyle::Display
+    } else {
+        MathStyle::Text
+    };
+    let list = to_display_list(&layout(&ast, &LayoutOptions::default().with_style(style)));
+    let svg = render_to_svg(
+        &list,
+        &SvgOptions {
+            font_size: UNITS_PER_EM,
+            padding: 0.0,
+            embed_glyphs: true,
+            ..SvgOptions::default()
+        },
+    );
+    // Replace the point-sized root so the formula scales with the text and
+    // sits on the baseline.
+    let body = svg.split_once('>').map_or("</svg>", |(_, body)| body);
+    let total = list.height + list.depth;
+    format!(
+        "<span class=\"{class}\" role=\"img\" aria-label=\"{source}\">\
+         <svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {:.4} {:.4}\" \
+         width=\"{:.4}em\" height=\"{:.4}em\" style=\"vertical-align: -{:.4}em\">{body}</span>",
+        list.width * UNITS_PER_EM,
+        total * UNITS_PER_EM,
+        list.width,
+        total,
+        list.depth,
+    )
+}
+
+/// Rewrite math that needs client-side JavaScript into `<span data-tex>` holders
+/// before extraction: MathJax `<script type="math/tex">` blocks and KaTeX output
+/// (whose TeX source is in its annotation). Returns `None` when there is none.
+pub fn prepare_page(html: &str) -> Option<String> {
+    if !html.contains("math/tex") && !html.contains("katex") {
+        return None;
+    }
+    let doc = Document::from(html);
+    let mut changed = false;
+    for script in doc.select("script[type^=\"math/tex\"]").iter() {
+        let display = script
+            .attr("type")
+            .is_some_and(|t| t.contains("mode=display"));
+        script.replace_with_html(tex_holder(&script.text(), display));
+        changed = true;
+    }
+    doc.select(".MathJax_Preview").remove();
+    for (selector, display) in [(".katex-display", true), (".katex", false)] {
+        for node in doc.select(selector).iter() {
+            let tex = node
+                .select("annotation[encoding=\"application/x-tex\"]")
+                .text();
+            if tex.trim().is_empty() {
+                continue;
+            }
+            node.replace_with_html(tex_holder(&tex, display));
+            changed = true;
+        }
+    }
+    changed.then(|| doc.html().to_string())
+}
+
+fn tex_holder(tex: &str, display: bool) -> String {
+    format!(
+        "<span data-tex=\"{}\">{}</span>",
+        if display { "display" } else { "inline" },
+        escape_html(tex)
+    )
+}
+
+/// Typeset math in article HTML as SVG: `<span data-tex>` holders left by
+/// [`prepare_page`], and `\(...\)`, `\[...\]` and `$$...$$` in running text.
+/// Single `$` is not treated as math in web pages; it is far more often money.
+pub fn render_math_html(html: &str) -> String {
+    if !["data-tex", "\\(", "\\[", "$$"]
+        .iter()
+        .any(|marker| html.contains(marker))
+    {
+        return html.to_string();
+    }
+    let doc = Document::fragment(html);
+    let mut changed = false;
+    for holder in doc.select("span[data-tex]").iter() {
+        let display = holder.attr("data-tex").is_some_and(|v| &*v == "display");
+        holder.replace_with_html(tex_to_svg(&holder.text(), display));
+        changed = true;
+    }
+
+    let text_nodes: Vec<NodeRef> = doc
+        .root()
+        .descendants()
+        .into_iter()
+        .filter(|node| node.is_text() && !in_verbatim(node))
+        .collect();
+    for node in text_nodes {
+        let text = node.text();
+        let segments = split_delimited(&text);
+        if !segments.iter().any(|s| matches!(s, Segment::Math(..))) {
+            continue;
+        }
+        let html: String = segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => escape_html(text),
+                Segment::Math(tex, display) => tex_to_svg(tex, *display),
+            })
+            .collect();
+        node.replace_with_html(html);
+        changed = true;
+    }
+
+    if !changed {
+        return html.to_string();
+    }
+    doc.select("html").inner_html().to_string()
+}
+
+fn in_verbatim(node: &NodeRef) -> bool {
+    node.ancestors(None).iter().any(|ancestor| {
+        ancestor
+            .node_name()
+            .is_some_and(|name| VERBATIM_ELEMENTS.contains(&name.to_ascii_lowercase().as_str()))
+    })
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Text(String),
+    Math(String, bool),
+}
+
+fn split_delimited(text: &str) -> Vec<Segment> {
+    const DELIMITERS: [(&str, &str, bool); 3] = [
+        ("\\(", "\\)", false),
+        ("\\[", "\\]", true),
+        ("$$", "$$", true),
+    ];
+    let mut segments = Vec::new();
+    let mut rest = text;
+    loop {
+        let next = DELIMITERS
+            .iter()
+            .filter_map(|&(open, close, display)| {
+                rest.find(open).map(|idx| (idx, open, close, display))
+            })
+            .min_by_key(|(idx, ..)| *idx);
+        let Some((idx, open, close, display)) = next else {
+            break;
+        };
+        let after = &rest[idx + open.len()..];
+        let Some(end) = after.find(close) else {
+            break;
+        };
+        if idx > 0 {
+            segments.push(Segment::Text(rest[..idx].to_string()));
+        }
+        segments.push(Segment::Math(after[..end].to_string(), display));
+        rest = &after[end + close.len()..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest.to_string()));
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prepare_page, render_math_html, tex_to_svg};
+
+    #[test]
+    fn typesets_tex_as_sized_svg_paths() {
+        let math = tex_to_svg("x_1^2 + \\sqrt{y}", false);
+        assert!(
+            math.starts_with("<span class=\"math\" role=\"img\" aria-label=\"x_1^2 + \\sqrt{y}\">")
+        );
+        assert!(math.contains("<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 "));
+        assert!(math.contains("em\" style=\"vertical-align: -0."));
+        assert!(math.contains("<path d=\"M"));
+        assert!(!math.contains("<text") && !math.contains("pt\""));
+        assert!(math.ends_with("</svg></span>"));
+    }
+
+    #[test]
+    fn display_math_is_taller_than_inline_math() {
+        let height = |math: &str| -> f64 {
+            let start = math.find("height=\"").expect("height") + "height=\"".len();
+            let end = start + math[start..].find("em\"").expect("em");
+            math[start..end].parse().expect("number")
+        };
+        let inline = tex_to_svg("\\sum_{i=1}^n \\frac{1}{i}", false);
+        let display = tex_to_svg("\\sum_{i=1}^n \\frac{1}{i}", true);
+        assert!(display.starts_with("<span class=\"math math-display\""));
+        assert!(height(&display) > height(&inline));
+    }
+
+    #[test]
+    fn unsupported_tex_is_kept_as_escaped_code() {
+        assert_eq!(
+            tex_to_svg("a < \\unknowncmd", true),
+            "<code class=\"math math-display math-error\">a &lt; \\unknowncmd</code>"
+        );
+    }
+
+    #[test]
+    fn prepares_mathjax_scripts_and_katex_output() {
+        let page = r#"<p>Energy <script type="math/tex">E=mc^2</script>.</p>
+            <span class="katex-display"><span class="katex"><span class="katex-mathml"><math>
+            <semantics><mrow></mrow><annotation encoding="application/x-tex">\int_0^1 x</annotation>
+            </semantics></math></span><span class="katex-html">junk</span></span></span>"#;
+        let prepared = prepare_page(page).expect("prepared");
+        assert!(prepared.contains("<span data-tex=\"inline\">E=mc^2</span>"));
+        assert!(prepared.contains("<span data-tex=\"display\">\\int_0^1 x</span>"));
+        assert!(!prepared.contains("junk"));
+        assert!(prepare_page("<p>no math</p>").is_none());
+    }
+
+    #[test]
+    fn renders_holders_and_delimited_text() {
+        let html = "<p>Inline \\(a^2\\) and <span data-tex=\"display\">b</span> cost $$5$$.</p>\
+                    <pre>\\(not math\\)</pre>";
+        let out = render_math_html(html);
+        assert!(out.contains("Inline <span class=\"math\" role=\"img\" aria-label=\"a^2\"><svg"));
+        assert!(out.contains("<span class=\"math math-display\" role=\"img\" aria-label=\"b\">"));
+        assert!(out.contains("aria-label=\"5\""));
+        assert!(out.contains("<pre>\\(not math\\)</pre>"));
+        assert_eq!(render_math_html("<p>$5 and $6</p>"), "<p>$5 and $6</p>");
+    }
+}
@@ -1,19 +1,61 @@
 use std::error::Error;
 use std::fs::write;
-use std::process::Command;
 use std::thread;
 use std::time::Duration;
 
-use crate::{PageSize, escape_html, extractor, fetcher, summarize_html, temp_html_path};
+use crate::article_meta::ArticleMeta;
+use crate::fonts::FontStacks;
+use crate::images::{self, ImageBudget, ImageOptions};
+use crate::language::{self, Hyphenation};
+use crate::links::LinkStyle;
+use crate::offline;
+use crate::pdf_merge;
+use crate::{
+    PageSize, escape_html, extractor, fetcher, highlight, math, summarize_html, temp_html_path,
+    xhtml_sanitize,
+};
 use reqwest::StatusCode;
 
 const BASE_CSS: &str = include_str!("../styles.css");
 
+/// Page glyph for the "Back to TOC" link. An emoji here renders differently, or
+/// as a box, depending on the fonts installed.
+const BACK_ICON_SVG: &str = r##"<svg class="nav-icon" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 16 16" aria-hidden="true"><path d="M3.5 1.5h6l3 3v10h-9z" fill="none" stroke="#111" stroke-width="1.2" stroke-linejoin="round"/><path d="M9.5 1.5v3h3M5.5 8h5M5.5 10.5h5M5.5 13h3" fill="none" stroke="#111" stroke-width="1.2"/></svg>"##;
+
 #[derive(Debug, Clone)]
 pub struct BundleArticle {
     pub section: Option<String>,
     pub title: String,
     pub content_html: String,
+    /// Shown as a byline block under the title when not empty.
+    pub meta: ArticleMeta,
+    /// A fetched PDF whose pages follow the article's placeholder page.
+    pub source_pdf: Option<Vec<u8>>,
+}
+
+/// Layout switches for [`generate_pdf_bundle_with_render_options`] and
+/// [`crate::pdf::generate_pdf`].
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    pub include_toc: bool,
+    pub include_back_to_toc_links: bool,
+    pub link_style: LinkStyle,
+    pub hyphenation: Hyphenation,
+    pub fonts: FontStacks,
+    pub images: ImageOptions,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            include_toc: true,
+            include_back_to_toc_links: true,
+            link_style: LinkStyle::Inline,
+            hyphenation: Hyphenation::default(),
+            fonts: FontStacks::default(),
+            images: ImageOptions::default(),
+        }
+    }
 }
 
 pub fn generate_multi_pdf(
@@ -53,14 +95,15 @@ pub fn generate_multi_pdf(
             }
         };
 
-        let article = match extractor::extract_article(&html, Some(&normalized)) {
-            Some(value) => value,
+        let article = match extractor::extract(&html, Some(&normalized)) {
+            Some(value) => value.article,
             None => {
                 eprintln!("Skipping {}: extraction failed", url);
                 continue;
             }
         };
 
+        let meta = ArticleMeta::from_article(&article);
         let title = article.title;
         let content_html = if summarize {
             match summarize_html(article.content.as_ref(), &normalized, pattern) {
@@ -73,10 +116,13 @@ pub fn generate_multi_pdf(
         } else {
             article.content.to_string()
         };
+        let content_html = xhtml_sanitize::sanitize_content(&content_html, Some(&normalized));
         articles.push(BundleArticle {
             section: None,
             title,
             content_html,
+            meta,
+            source_pdf: None,
         });
 
         if delay_secs > 0 {
@@ -106,6 +152,8 @@ pub fn generate_pdf_bundle(
             section: None,
             title: title.clone(),
             content_html: content_html.clone(),
+            meta: ArticleMeta::default(),
+            source_pdf: None,
         })
         .collect();
     generate_pdf_bundle_with_sections(&mapped, output_path, cover_title, cover_subtitle, page_size)
@@ -124,8 +172,7 @@ pub fn generate_pdf_bundle_with_sections(
         cover_title,
         cover_subtitle,
         page_size,
-        true,
-        true,
+        RenderOptions::default(),
     )
 }
 
@@ -135,38 +182,54 @@ pub fn generate_pdf_bundle_with_render_options(
     cover_title: &str,
     cover_subtitle: &str,
     page_size: PageSize,
-    include_toc: bool,
-    include_back_to_toc_links: bool,
+    options: RenderOptions,
 ) -> Result<(), Box<dyn Error>> {
     if articles.is_empty() {
         return Err("No articles fetched".into());
     }
 
-    let full_html = build_bundle_html(
-        articles,
-        cover_title,
-        cover_subtitle,
-        page_size,
-        include_toc,
-        include_back_to_toc_links,
-    );
+    let mut budget = ImageBudget::new(&options.images);
+    let rendered: Vec<BundleArticle> = articles
+        .iter()
+        .map(|article| BundleArticle {
+            section: article.section.clone(),
+            title: article.title.clone(),
+            content_html: offline::inline_remote_assets(
+                &images::embed_images(
+                    &article.content_html,
+                    page_size,
+                    &options.images,
+                    &mut budget,
+                ),
+                &mut budget,
+            ),
+            meta: article.meta.clone(),
+            source_pdf: None,
+        })
+        .collect();
+    images::report_budget(&budget, &options.images);
+
+    let full_html = build_bundle_html(&rendered, cover_title, cover_subtitle, page_size, &options);
+    // Anchors match the article ids in build_bundle_html.
+    let sources: Vec<(String, &[u8])> = articles
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, article)| {
+            let body = article.source_pdf.as_deref()?;
+            Some((format!("article-{}", idx + 1), body))
+        })
+        .collect();
 
     let tmp_html = temp_html_path("rmfeeder_multi_tmp");
-    write(&tmp_html, full_html)?;
+    write(&tmp_html, &full_html)?;
 
-    // -------- Generate PDF via WeasyPrint --------
-    let status = Command::new("weasyprint")
-        .arg(&tmp_html)
-        .arg(output_path)
-        .status()?;
+    // -------- Generate PDF via WeasyPrint, then splice in source PDFs --------
+    let result = offline::render_pdf(&tmp_html, output_path)
+        .and_then(|()| pdf_merge::merge_sources(output_path, &sources));
 
     let _ = std::fs::remove_file(&tmp_html);
 
-    if !status.success() {
-        return Err("WeasyPrint PDF generation failed".into());
-    }
-
-    Ok(())
+    result
 }
 
 fn build_bundle_html(
@@ -174,8 +237,7 @@ fn build_bundle_html(
     cover_title: &str,
     cover_subtitle: &str,
     page_size: PageSize,
-    include_toc: bool,
-    include_back_to_toc_links: bool,
+    options: &RenderOptions,
 ) -> String {
     // -------- Build Cover Page --------
     let today = chrono::Local::now().format("%B %e, %Y").to_string();
@@ -194,7 +256,7 @@ fn build_bundle_html(
     );
 
     // -------- Build TOC --------
-    let toc_html = if include_toc {
+    let toc_html = if options.include_toc {
         let mut toc_items = String::new();
         let mut last_section: Option<&str> = None;
         for (idx, article) in articles.iter().enumerate() {
@@ -241,30 +303,36 @@ fn build_bundle_html(
         let id = format!("article-{}", idx + 1);
         let toc_entry_id = format!("toc-item-{}", idx + 1);
         let safe_title = escape_html(&article.title);
-        let back_to_toc_html = if include_back_to_toc_links && include_toc {
+        let body = math::render_math_html(&highlight::highlight_html(&article.content_html));
+        let lang = language::resolve(article.meta.language.as_deref(), &article.content_html);
+        let back_to_toc_html = if options.include_back_to_toc_links && options.include_toc {
             format!(
-                "<p><a class=\"back-home\" href=\"#{toc_entry_id}\">📄 Back to TOC</a></p>",
-                toc_entry_id = toc_entry_id
+                "<p><a class=\"back-home\" href=\"#{toc_entry_id}\">{icon}Back to TOC</a></p>",
+                toc_entry_id = toc_entry_id,
+                icon = BACK_ICON_SVG
             )
         } else {
             String::new()
         };
 
         article_blocks.push_str(&format!(
-            "<section id=\"{id}\" class=\"article-block\">
+            "<section id=\"{id}\" class=\"article-block\"{lang_attrs}>
                 <h1>{title}</h1>
+                {byline}
                 {body}
                 {back_to_toc_html}
             </section>\n",
             id = id,
+            lang_attrs = language::container_attrs(lang.as_deref(), &options.hyphenation),
             title = safe_title,
-            body = article.content_html,
+            byline = article.meta.byline_html(),
+            body = options.link_style.apply(&body, &format!("{}-link", id)),
             back_to_toc_html = back_to_toc_html
         ));
     }
 
     // -------- Combine HTML --------
-    let toc_anchor = if include_toc {
+    let toc_anchor = if options.include_toc {
         "<a id=\"toc\"></a>"
     } else {
         ""
@@ -277,6 +345,8 @@ fn build_bundle_html(
 <title>rmfeeder – Multi Article</title>
 <style>
 {base_css}
+{font_css}
+{highlight_css}
 {page_override_css}
 </style>
 </head>
@@ -288,6 +358,8 @@ fn build_bundle_html(
 </body>
 </html>",
         base_css = BASE_CSS,
+        font_css = options.fonts.css(),
+        highlight_css = highlight::theme_css(page_size),
         page_override_css = page_size.page_override_css(),
         cover = cover_html,
         toc_anchor = toc_anchor,
@@ -298,8 +370,11 @@ fn build_bundle_html(
 
 #[cfg(test)]
 mod tests {
-    use super::{BundleArticle, build_bundle_html};
+    use super::{BundleArticle, RenderOptions, build_bundle_html};
     use crate::PageSize;
+    use crate::article_meta::ArticleMeta;
+    use crate::language::Hyphenation;
+    use crate::links::LinkStyle;
 
     #[test]
     fn back_to_toc_links_target_their_own_toc_entry() {
@@ -308,11 +383,15 @@ mod tests {
                 section: Some("Section A".to_string()),
                 title: "First".to_string(),
                 content_html: "<p>First body</p>".to_string(),
+                meta: ArticleMeta::default(),
+                source_pdf: None,
             },
             BundleArticle {
                 section: Some("Section A".to_string()),
                 title: "Second".to_string(),
                 content_html: "<p>Second body</p>".to_string(),
+                meta: ArticleMeta::default(),
+                source_pdf: None,
             },
         ];
         let html = build_bundle_html(
@@ -320,14 +399,15 @@ mod tests {
             "Bundle",
             "Subtitle",
             PageSize::Letter,
-            true,
-            true,
+            &RenderOptions::default(),
         );
 
         assert!(html.contains("id=\"toc-item-1\" href=\"#article-1\""));
         assert!(html.contains("id=\"toc-item-2\" href=\"#article-2\""));
-        assert!(html.contains("href=\"#toc-item-1\">📄 Back to TOC</a>"));
-        assert!(html.contains("href=\"#toc-item-2\">📄 Back to TOC</a>"));
+        assert!(html.contains("href=\"#toc-item-1\"><svg class=\"nav-icon\""));
+        assert!(html.contains("href=\"#toc-item-2\"><svg class=\"nav-icon\""));
+        assert!(html.contains("</svg>Back to TOC</a>"));
+        assert!(!html.contains('📄'));
     }
 
     #[test]
@@ -336,17 +416,132 @@ mod tests {
             section: None,
             title: "Only".to_string(),
             content_html: "<p>Body</p>".to_string(),
+            meta: ArticleMeta::default(),
+            source_pdf: None,
         }];
         let html = build_bundle_html(
             &articles,
             "Bundle",
             "Subtitle",
             PageSize::Letter,
-            false,
-            true,
+            &RenderOptions {
+                include_toc: false,
+                ..RenderOptions::default()
+            },
         );
 
         assert!(!html.contains("Back to TOC"));
         assert!(!html.contains("toc-item-1"));
     }
+
+    #[test]
+    fn byline_block_follows_the_article_title() {
+        let articles = vec![BundleArticle {
+            section: None,
+            title: "Cited".to_string(),
+            content_html: "<p>Body</p>".to_string(),
+            meta: ArticleMeta {
+                byline: Some("Ada Lovelace".to_string()),
+                published: Some("2024-03-01".to_string()),
+                ..ArticleMeta::default()
+            },
+            source_pdf: None,
+        }];
+        let html = build_bundle_html(
+            &articles,
+            "Bundle",
+            "Subtitle",
+            PageSize::Letter,
+            &RenderOptions {
+                include_toc: false,
+                include_back_to_toc_links: false,
+                ..RenderOptions::default()
+            },
+        );
+
+        let title = html.find("<h1>Cited</h1>").expect("title");
+        let byline = html.find("class=\"article-byline\"").expect("byline");
+        let body = html.find("<p>Body</p>").expect("body");
+        assert!(title < byline && byline < body);
+        assert!(html.contains("By Ada Lovelace"));
+        assert!(html.contains("March 1, 2024"));
+    }
+
+    #[test]
+    fn article_blocks_carry_their_language() {
+        let articles = vec![
+            BundleArticle {
+                section: None,
+                title: "Deutsch".to_string(),
+                content_html: "<p>Body</p>".to_string(),
+                meta: ArticleMeta {
+                    language: Some("de_DE".to_string()),
+                    ..ArticleMeta::default()
+                },
+                source_pdf: None,
+            },
+            BundleArticle {
+                section: None,
+                title: "Unknown".to_string(),
+                content_html: "<p>Body</p>".to_string(),
+                meta: ArticleMeta::default(),
+                source_pdf: None,
+            },
+        ];
+        let html = build_bundle_html(
+            &articles,
+            "Bundle",
+            "Subtitle",
+            PageSize::A6,
+            &RenderOptions {
+                hyphenation: Hyphenation {
+                    enabled: true,
+                    languages: vec!["de".to_string()],
+                },
+                ..RenderOptions::default()
+            },
+        );
+
+        assert!(html.contains(
+            "<section id=\"article-1\" class=\"article-block\" lang=\"de-DE\" data-hyphenate=\"auto\">"
+        ));
+        assert!(html.contains("<section id=\"article-2\" class=\"article-block\">"));
+    }
+
+    #[test]
+    fn link_endnotes_close_each_article_block() {
+        let articles = vec![
+            BundleArticle {
+                section: None,
+                title: "One".to_string(),
+                content_html: "<p><a href=\"https://a.example/\">A</a></p>".to_string(),
+                meta: ArticleMeta::default(),
+                source_pdf: None,
+            },
+            BundleArticle {
+                section: None,
+                title: "Two".to_string(),
+                content_html: "<p><a href=\"https://b.example/\">B</a></p>".to_string(),
+                meta: ArticleMeta::default(),
+                source_pdf: None,
+            },
+        ];
+        let html = build_bundle_html(
+            &articles,
+            "Bundle",
+            "Subtitle",
+            PageSize::Letter,
+            &RenderOptions {
+                link_style: LinkStyle::Endnotes,
+                ..RenderOptions::default()
+            },
+        );
+
+        assert!(html.contains("A<sup class=\"link-ref\"><a href=\"#article-1-link-1\">1</a>"));
+        assert!(html.contains("B<sup class=\"link-ref\"><a href=\"#article-2-link-1\">1</a>"));
+        let notes = html.find("<li id=\"article-1-link-1\">").expect("endnote");
+        let back = html.find("href=\"#toc-item-1\">").expect("back link");
+        let next = html.find("id=\"article-2\"").expect("second article");
+        assert!(notes < back && back < next);
+    }
 }
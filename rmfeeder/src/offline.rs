@@ -0,0 +1,351 @@
+use std::error::Error;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::process::Command;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use dom_query::Document;
+
+use crate::fetcher;
+use crate::images::{self, ImageBudget};
+use crate::ingest::percent_decode;
+
+/// Elements that only load remote media; none of them print.
+const REMOTE_MEDIA: &str = "iframe, frame, embed, object, video, audio, source, track";
+
+/// Renders with a URL fetcher that only serves `data:` and `file:` URLs.
+/// Exits with [`NO_WEASYPRINT_MODULE`] when this Python can't import WeasyPrint.
+const RENDER_SCRIPT: &str = r#"
+import sys
+try:
+    from weasyprint import HTML, default_url_fetcher
+except ImportError:
+    sys.exit(90)
+
+def local_only(url, *args, **kwargs):
+    if url.startswith(("data:", "file:")):
+        return default_url_fetcher(url, *args, **kwargs)
+    raise ValueError("remote fetch blocked: " + url)
+
+HTML(filename=sys.argv[1], url_fetcher=local_only).write_pdf(sys.argv[2])
+"#;
+
+const NO_WEASYPRINT_MODULE: i32 = 90;
+
+/// Attributes WeasyPrint loads resources from. `href` on links is not loaded.
+const RESOURCE_ATTRS: &[&str] = &["src", "href", "poster", "data", "srcset", "style"];
+
+fn is_remote(url: &str) -> bool {
+    let lower = url.trim().to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://") || lower.starts_with("//")
+}
+
+/// Resolve the remote references left in an article body so WeasyPrint has
+/// nothing to fetch. Remaining remote images are downloaded as-is and inlined
+/// as data URIs while the budget allows; the rest become their alt text.
+/// Remote media elements, `srcset`s and posters are dropped.
+pub fn inline_remote_assets(html: &str, budget: &mut ImageBudget) -> String {
+    if !["http:", "https:", "//"]
+        .iter()
+        .any(|marker| html.contains(marker))
+    {
+        return html.to_string();
+    }
+    let doc = Document::fragment(html);
+    doc.select(REMOTE_MEDIA).remove();
+    doc.select("[poster]").remove_attr("poster");
+    doc.select("[srcset]").remove_attrs(&["srcset", "sizes"]);
+    for image in doc.select("image, use").iter() {
+        let remote = ["href", "xlink:href"]
+            .iter()
+            .filter_map(|attr| image.attr(attr))
+            .any(|url| is_remote(&url));
+        if remote {
+            image.remove();
+        }
+    }
+
+    for img in doc.select("img").iter() {
+        let Some(src) = img.attr("src").map(|s| s.trim().to_string()) else {
+            continue;
+        };
+        if !is_remote(&src) {
+            continue;
+        }
+        let url = if src.starts_with("//") {
+            format!("https:{}", src)
+        } else {
+            src
+        };
+        match download_image(&url) {
+            Some((mime, body)) if budget.take(body.len()) => {
+                img.set_attr(
+                    "src",
+                    &format!("data:{};base64,{}", mime, BASE64.encode(&body)),
+                );
+            }
+            _ => images::replace_with_alt(&img),
+        }
+    }
+    doc.select("html").inner_html().to_string()
+}
+
+/// Image bytes and MIME type, when `url` serves an image. SVGs that load
+/// remote resources themselves are refused.
+fn download_image(url: &str) -> Option<(String, Vec<u8>)> {
+    let response = fetcher::shared().get(url).ok()?;
+    let mime = response
+        .content_type
+        .as_deref()
+        .and_then(|ct| ct.split(';').next())
+        .map(|ct| ct.trim().to_ascii_lowercase())
+        .filter(|ct| ct.starts_with("image/"))
+        .or_else(|| {
+            image::guess_format(&response.body)
+                .ok()
+                .map(|format| format.to_mime_type().to_string())
+        })?;
+    if mime == "image/svg+xml"
+        && !remote_references(&String::from_utf8_lossy(&response.body)).is_empty()
+    {
+        return None;
+    }
+    Some((mime, response.body))
+}
+
+/// Remote URLs WeasyPrint would load from `html`: resource attributes, CSS
+/// `url()`s and `@import`s, including those inside inlined SVG images.
+pub fn remote_references(html: &str) -> Vec<String> {
+    let doc = Document::from(html);
+    let mut found = Vec::new();
+    for node in doc.select("*").nodes() {
+        if node.node_name().is_some_and(|name| &*name == "style") {
+            css_references(&node.text(), &mut found);
+        }
+        let is_link = node
+            .node_name()
+            .is_some_and(|name| matches!(&*name, "a" | "area"));
+        for attr in node.attrs() {
+            let name = &*attr.name.local;
+            if !RESOURCE_ATTRS.contains(&name) || (is_link && name == "href") {
+                continue;
+            }
+            match name {
+                "style" => css_references(&attr.value, &mut found),
+                "srcset" => attr
+                    .value
+                    .split(',')
+                    .filter_map(|candidate| candidate.split_whitespace().next())
+                    .for_each(|url| url_reference(url, &mut found)),
+                _ => url_reference(&attr.value, &mut found),
+            }
+        }
+    }
+    found
+}
+
+fn css_references(css: &str, found: &mut Vec<String>) {
+    for (idx, _) in css.match_indices("url(") {
+        let rest = &css[idx + "url(".len()..];
+        let end = rest.find(')').unwrap_or(rest.len());
+        url_reference(rest[..end].trim().trim_matches(['"', '\'']), found);
+    }
+    for (idx, _) in css.match_indices("@import") {
+        let rest = css[idx + "@import".len()..].trim_start();
+        if let Some(quoted) = rest.strip_prefix(['"', '\'']) {
+            let end = quoted.find(['"', '\'']).unwrap_or(quoted.len());
+            url_reference(&quoted[..end], found);
+        }
+    }
+}
+
+fn url_reference(url: &str, found: &mut Vec<String>) {
+    let url = url.trim();
+    if is_remote(url) {
+        found.push(url.to_string());
+    } else if let Some(svg) = svg_data(url) {
+        found.extend(remote_references(&svg));
+    }
+}
+
+/// The document in an `image/svg+xml` data URI.
+fn svg_data(url: &str) -> Option<String> {
+    let (header, data) = url.split_once(',')?;
+    let header = header.to_ascii_lowercase();
+    if !header.starts_with("data:image/svg+xml") {
+        return None;
+    }
+    if header.ends_with(";base64") {
+        let bytes = BASE64.decode(data.trim()).ok()?;
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    } else {
+        Some(percent_decode(data))
+    }
+}
+
+/// Render `html_path` to `output_path` with WeasyPrint, remote fetching
+/// disabled. When WeasyPrint isn't importable from `python3` (Homebrew and
+/// pipx give it its own interpreter), the `weasyprint` command is used instead.
+/// It can't be given a URL fetcher, so it only runs when the HTML has no
+/// remote references left.
+pub fn render_pdf(html_path: &Path, output_path: &str) -> Result<(), Box<dyn Error>> {
+    render_pdf_with(
+        OsStr::new("python3"),
+        OsStr::new("weasyprint"),
+        html_path,
+        output_path,
+    )
+}
+
+fn render_pdf_with(
+    python: &OsStr,
+    weasyprint: &OsStr,
+    html_path: &Path,
+    output_path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let status = Command::new(python)
+        .arg("-c")
+        .arg(RENDER_SCRIPT)
+        .arg(html_path)
+        .arg(output_path)
+        .status();
+    let status = match status {
+        Ok(status) if status.code() != Some(NO_WEASYPRINT_MODULE) => status,
+        _ => {
+            let html = std::fs::read_to_string(html_path)?;
+            if let Some(url) = remote_references(&html).first() {
+                return Err(format!(
+                    "WeasyPrint is only available as a command, which can't block remote \
+                     fetches, and the page still references {}",
+                    url
+                )
+                .into());
+            }
+            eprintln!(
+                "Warning: WeasyPrint isn't importable from python3; rendering with the \
+                 weasyprint command, which can't block remote fetches."
+            );
+            Command::new(weasyprint)
+                .arg(html_path)
+                .arg(output_path)
+                .status()?
+        }
+    };
+    if !status.success() {
+        return Err("WeasyPrint PDF generation failed".into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD as BASE64;
+
+    use super::{inline_remote_assets, remote_references, render_pdf_with};
+    use crate::images::{ImageBudget, ImageOptions};
+    use crate::test_support::{TestResponse, TestServer, stand_in_executable};
+
+    #[test]
+    fn leaves_nothing_remote_for_weasyprint() {
+        let options = ImageOptions {
+            budget_bytes: 0,
+            ..ImageOptions::default()
+        };
+        let mut budget = ImageBudget::new(&options);
+        let html = r#"<p><img src="data:image/png;base64,AA" srcset="https://t.example/a.png 2x">
+            <img src="notes/local.png"></p>
+            <picture><source srcset="https://t.example/b.webp"><img src="data:image/gif;base64,R0"></picture>
+            <video poster="https://t.example/p.jpg"></video>
+            <svg><image href="https://t.example/c.png"></image></svg>"#;
+        let out = inline_remote_assets(html, &mut budget);
+        assert!(!out.contains("t.example"));
+        assert!(out.contains("src=\"notes/local.png\""));
+        assert!(out.contains("src=\"data:image/gif;base64,R0\""));
+        assert!(out.contains("<svg></svg>"));
+    }
+
+    #[test]
+    fn bodies_without_remote_references_pass_through() {
+        let mut budget = ImageBudget::new(&ImageOptions::default());
+        let html = "<p><a href=\"#top\">Top</a></p>";
+        assert_eq!(inline_remote_assets(html, &mut budget), html);
+    }
+
+    #[test]
+    fn finds_remote_references_inside_inlined_svg() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><image href="https://t.example/x.png"/></svg>"#;
+        let html = format!(
+            r#"<a href="https://t.example/page">link</a><img src="data:image/svg+xml;base64,{}">
+            <p style="background: url('//t.example/bg.png')"></p>
+            <style>@import "https://t.example/a.css";</style>"#,
+            BASE64.encode(svg)
+        );
+        assert_eq!(
+            remote_references(&html),
+            [
+                "https://t.example/x.png",
+                "//t.example/bg.png",
+                "https://t.example/a.css"
+            ]
+        );
+        assert!(remote_references("<svg xmlns=\"http://www.w3.org/2000/svg\"></svg>").is_empty());
+    }
+
+    #[test]
+    fn command_fallback_renders_only_fully_inlined_html() {
+        let server = TestServer::start(|req| match req.path.as_str() {
+            "/plain.svg" => TestResponse::ok("image/svg+xml", "<svg><rect/></svg>"),
+            "/tracking.svg" => TestResponse::ok(
+                "image/svg+xml",
+                r#"<svg><image href="https://t.example/pixel.png"/></svg>"#,
+            ),
+            _ => TestResponse::status(404),
+        });
+        let html = format!(
+            r#"<p><img src="{}" alt="Plain"><img src="{}" alt="Chart"></p>"#,
+            server.url("/plain.svg"),
+            server.url("/tracking.svg")
+        );
+        let mut budget = ImageBudget::new(&ImageOptions::default());
+        let body = inline_remote_assets(&html, &mut budget);
+        assert!(body.contains("src=\"data:image/svg+xml;base64,"));
+        assert!(body.contains("<span class=\"missing-image\">[Chart]</span>"));
+
+        let dir = tempfile::tempdir().expect("tempdir");
+        let html_path = dir.path().join("article.html");
+        let output = dir.path().join("article.pdf");
+        let output = output.to_str().expect("utf-8 path");
+        let python = stand_in_executable("no-weasyprint-python", "exit 90");
+        let weasyprint = stand_in_executable("weasyprint", "cp \"$1\" \"$2\"");
+
+        std::fs::write(&html_path, &body).expect("write html");
+        render_pdf_with(
+            python.as_os_str(),
+            weasyprint.as_os_str(),
+            &html_path,
+            output,
+        )
+        .expect("rendered");
+        assert_eq!(std::fs::read_to_string(output).expect("output"), body);
+
+        std::fs::remove_file(output).expect("remove output");
+        std::fs::write(&html_path, &html).expect("write html");
+        let err = render_pdf_with(
+            python.as_os_str(),
+            weasyprint.as_os_str(),
+            &html_path,
+            output,
+        )
+        .expect_err("refused");
+        assert!(err.to_string().contains("/plain.svg"));
+        assert!(!std::path::Path::new(output).exists());
+
+        let missing = OsStr::new("/nonexistent/python3");
+        std::fs::write(&html_path, &body).expect("write html");
+        assert!(render_pdf_with(missing, weasyprint.as_os_str(), &html_path, output).is_ok());
+    }
+}
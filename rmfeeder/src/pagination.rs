@@ -0,0 +1,238 @@
+use std::collections::HashSet;
+
+use dom_query::Document;
+use url::Url;
+
+use crate::request_profile::glob_matches;
+
+/// Default cap on pages fetched for one article, including the first.
+pub const DEFAULT_MAX_PAGES: usize = 10;
+
+/// Query parameters commonly used for page numbers. Not `p`: WordPress uses
+/// `?p=<post id>` for plain permalinks.
+const PAGE_PARAMS: &[&str] = &["page", "pg", "pagenum"];
+
+/// Links inside pagination markup; only these are checked for incremented URLs.
+const PAGINATION_LINKS: &str = ".pagination a[href], .pager a[href], .page-links a[href], \
+     .page-numbers a[href], nav a[href]";
+
+/// Previous/next-post navigation, which links to other articles.
+const POST_NAVIGATION: &str = ".post-navigation a, .nav-previous a, .nav-next a";
+
+/// Block elements compared when suppressing content repeated across pages.
+const DEDUPE_BLOCKS: &str = "p, li, blockquote, pre, h1, h2, h3, h4, h5, h6, figcaption";
+
+/// Whether pagination should be followed for `url` given host-glob opt-outs.
+pub fn is_enabled_for(url: &str, excluded_hosts: &[String]) -> bool {
+    let Some(host) = Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_ascii_lowercase()))
+    else {
+        return false;
+    };
+    !excluded_hosts
+        .iter()
+        .any(|pattern| glob_matches(&pattern.to_ascii_lowercase(), &host))
+}
+
+/// The URL of the page after `page_url`: `rel="next"` first, then links in
+/// pagination markup that increment a `?page=N` parameter or a trailing `/N/`
+/// path segment.
+pub fn find_next_page(html: &str, page_url: &str) -> Option<String> {
+    let base = Url::parse(page_url).ok()?;
+    let doc = Document::from(html);
+
+    let same_site = |href: &str| -> Option<Url> {
+        let mut url = base.join(href.trim()).ok()?;
+        url.set_fragment(None);
+        let same_host = url.host_str() == base.host_str();
+        (same_host && comparable(&url) != comparable(&base)).then_some(url)
+    };
+
+    for link in doc.select(r#"link[rel~="next"], a[rel~="next"]"#).iter() {
+        if link.is(POST_NAVIGATION) {
+            continue;
+        }
+        if let Some(url) = link.attr("href").and_then(|href| same_site(&href)) {
+            return Some(url.into());
+        }
+    }
+
+    let expected: Vec<String> = incremented_urls(&base).iter().map(comparable).collect();
+    doc.select(PAGINATION_LINKS).iter().find_map(|anchor| {
+        if anchor.is(POST_NAVIGATION) {
+            return None;
+        }
+        let url = same_site(&anchor.attr("href")?)?;
+        expected.contains(&comparable(&url)).then(|| url.into())
+    })
+}
+
+/// Candidate URLs for the next page, derived from the current URL's page number.
+fn incremented_urls(url: &Url) -> Vec<Url> {
+    let mut candidates = Vec::new();
+
+    let pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+    let page_param = pairs
+        .iter()
+        .find(|(name, value)| PAGE_PARAMS.contains(&name.as_str()) && value.parse::<u32>().is_ok());
+    match page_param {
+        Some((name, value)) => {
+            let next = value.parse::<u32>().unwrap_or(1) + 1;
+            let mut next_url = url.clone();
+            next_url
+                .query_pairs_mut()
+                .clear()
+                .extend_pairs(pairs.iter().map(|(k, v)| {
+                    if k == name {
+                        (k.clone(), next.to_string())
+                    } else {
+                        (k.clone(), v.clone())
+                    }
+                }));
+            candidates.push(next_url);
+        }
+        None => {
+            for param in PAGE_PARAMS {
+                let mut next_url = url.clone();
+                next_url.query_pairs_mut().append_pair(param, "2");
+                candidates.push(next_url);
+            }
+        }
+    }
+
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(|s| s.filter(|seg| !seg.is_empty()).collect())
+        .unwrap_or_default();
+    let mut next_path: Vec<String> = segments.iter().map(ToString::to_string).collect();
+    match segments.last().and_then(|last| last.parse::<u32>().ok()) {
+        // Small numbers only, so `/2024/` or `/article/123456` are not taken for pages.
+        Some(n) if n < 100 && segments.len() > 1 => {
+            *next_path.last_mut().expect("non-empty path") = (n + 1).to_string();
+        }
+        _ => next_path.push("2".to_string()),
+    }
+    let mut next_url = url.clone();
+    next_url.set_path(&format!("/{}", next_path.join("/")));
+    candidates.push(next_url);
+    candidates
+}
+
+/// URL form used for comparisons: no fragment, no trailing slash.
+fn comparable(url: &Url) -> String {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    let path = url.path().trim_end_matches('/').to_string();
+    url.set_path(&path);
+    url.into()
+}
+
+/// Drops blocks whose text already appeared on an earlier page (repeated
+/// teasers, bylines, pull quotes).
+#[derive(Debug, Default)]
+pub struct ParagraphDeduper {
+    seen: HashSet<String>,
+}
+
+impl ParagraphDeduper {
+    /// Remove already-seen blocks from `content_html`, recording the rest.
+    pub fn filter(&mut self, content_html: &str) -> String {
+        let doc = Document::fragment(content_html);
+        for block in doc.select(DEDUPE_BLOCKS).iter() {
+            let key = normalize_text(&block.text());
+            if key.len() < 20 {
+                continue;
+            }
+            if !self.seen.insert(key) {
+                block.remove();
+            }
+        }
+        doc.select("html").inner_html().to_string()
+    }
+}
+
+fn normalize_text(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParagraphDeduper, find_next_page, is_enabled_for};
+
+    #[test]
+    fn prefers_rel_next_links() {
+        let html = r#"<html><head><link rel="next" href="/story?page=2"></head>
+            <body><a href="/other/2">2</a></body></html>"#;
+        assert_eq!(
+            find_next_page(html, "https://example.com/story").as_deref(),
+            Some("https://example.com/story?page=2")
+        );
+    }
+
+    #[test]
+    fn detects_incremented_query_and_path_links() {
+        let html = r#"<div class="pagination"><a href="/story?id=9&page=3">Next</a></div>"#;
+        assert_eq!(
+            find_next_page(html, "https://example.com/story?id=9&page=2").as_deref(),
+            Some("https://example.com/story?id=9&page=3")
+        );
+
+        let html = r#"<nav><a href="/mag/long-read/2/">2</a><a href="/mag/other">x</a></nav>"#;
+        assert_eq!(
+            find_next_page(html, "https://example.com/mag/long-read/").as_deref(),
+            Some("https://example.com/mag/long-read/2/")
+        );
+
+        let html = r#"<p class="page-links"><a href="https://example.com/mag/long-read/3">Next page</a></p>"#;
+        assert_eq!(
+            find_next_page(html, "https://example.com/mag/long-read/2").as_deref(),
+            Some("https://example.com/mag/long-read/3")
+        );
+    }
+
+    #[test]
+    fn next_post_links_are_not_pages() {
+        // WordPress plain permalinks: `?p=` is a post id, not a page number.
+        let html = r#"<nav class="navigation post-navigation"><div class="nav-links">
+            <div class="nav-next"><a href="/?p=124" rel="next">Next post</a></div></div></nav>"#;
+        assert_eq!(find_next_page(html, "https://blog.example/?p=123"), None);
+
+        let html = r#"<article><p>See <a href="/posts/43">the follow-up</a>.</p></article>"#;
+        assert_eq!(find_next_page(html, "https://blog.example/posts/42"), None);
+    }
+
+    #[test]
+    fn ignores_self_links_and_other_hosts() {
+        let html = r#"<link rel="next" href="https://cdn.example.net/story?page=2">
+            <a href="/story">Story</a><a href="/story#comments">Comments</a>"#;
+        assert_eq!(find_next_page(html, "https://example.com/story"), None);
+    }
+
+    #[test]
+    fn suppresses_paragraphs_repeated_across_pages() {
+        let mut deduper = ParagraphDeduper::default();
+        let first = deduper.filter(
+            "<p>Subscribe to our newsletter for more stories.</p><p>The first page body text.</p>",
+        );
+        assert!(first.contains("The first page body text."));
+
+        let second = deduper.filter(
+            "<p>Subscribe to our newsletter for more stories.</p><p>The second page body text.</p><p>Ok</p>",
+        );
+        assert!(!second.contains("Subscribe"));
+        assert!(second.contains("The second page body text."));
+        assert!(second.ends_with("<p>Ok</p>"));
+        assert!(!second.contains("<html>"));
+    }
+
+    #[test]
+    fn opt_out_uses_host_globs() {
+        let excluded = vec!["*.slow-site.com".to_string()];
+        assert!(!is_enabled_for("https://www.slow-site.com/a", &excluded));
+        assert!(is_enabled_for("https://example.com/a", &excluded));
+    }
+}
@@ -1,16 +1,24 @@
 use std::error::Error;
 use std::fs::write;
-use std::process::Command;
 
-use crate::{escape_html, temp_html_path, PageSize};
+use crate::article_meta::ArticleMeta;
+use crate::images::{self, ImageBudget};
+use crate::language;
+use crate::multipdf::RenderOptions;
+use crate::offline;
+use crate::pdf_merge;
+use crate::{PageSize, escape_html, highlight, math, temp_html_path};
 
 const BASE_CSS: &str = include_str!("../styles.css");
 
 pub fn generate_pdf(
     title: &str,
+    meta: &ArticleMeta,
     body_html: &str,
     output_path: &str,
     page_size: PageSize,
+    options: &RenderOptions,
+    source_pdf: Option<&[u8]>,
 ) -> Result<(), Box<dyn Error>> {
 
     let tmp_html = temp_html_path("rmfeeder_tmp");
@@ -20,6 +28,11 @@ pub fn generate_pdf(
 
     // Build HTML with a cover page, article header, and your CSS
     let safe_title = escape_html(title);
+    let lang = language::resolve(meta.language.as_deref(), body_html);
+    let mut budget = ImageBudget::new(&options.images);
+    let body_html = images::embed_images(body_html, page_size, &options.images, &mut budget);
+    let body_html = offline::inline_remote_assets(&body_html, &mut budget);
+    images::report_budget(&budget, &options.images);
     let full_html = format!(
 r#"<!DOCTYPE html>
 <html>
@@ -28,6 +41,8 @@ r#"<!DOCTYPE html>
 <title>{title}</title>
 <style>
 {base_css}
+{font_css}
+{highlight_css}
 {page_override_css}
 </style>
 </head>
@@ -42,10 +57,11 @@ r#"<!DOCTYPE html>
 </section>
 
 <!-- ===== ARTICLE CONTENT ===== -->
-<main class="article-content">
+<main id="article" class="article-content"{lang_attrs}>
 
   <header class="article-header">
     <h1 class="article-title">{title}</h1>
+    {byline}
   </header>
 
   {body}
@@ -56,24 +72,30 @@ r#"<!DOCTYPE html>
 </html>
 "#,
         title = safe_title,
+        byline = meta.byline_html(),
+        lang_attrs = language::container_attrs(lang.as_deref(), &options.hyphenation),
         base_css = BASE_CSS,
+        font_css = options.fonts.css(),
+        highlight_css = highlight::theme_css(page_size),
         page_override_css = page_size.page_override_css(),
         today = today,
-        body = body_html
+        body = options.link_style.apply(
+            &math::render_math_html(&highlight::highlight_html(&body_html)),
+            "link"
+        )
     );
 
-    write(&tmp_html, full_html)?;
+    write(&tmp_html, &full_html)?;
 
-    let status = Command::new("weasyprint")
-        .arg(&tmp_html)
-        .arg(output_path)
-        .status()?;
+    // A fetched PDF's own pages follow the page with the article header.
+    let sources: Vec<(String, &[u8])> = source_pdf
+        .map(|body| ("article".to_string(), body))
+        .into_iter()
+        .collect();
+    let result = offline::render_pdf(&tmp_html, output_path)
+        .and_then(|()| pdf_merge::merge_sources(output_path, &sources));
 
     let _ = std::fs::remove_file(&tmp_html);
 
-    if !status.success() {
-        return Err("WeasyPrint PDF generation failed".into());
-    }
-
-    Ok(())
+    result
 }
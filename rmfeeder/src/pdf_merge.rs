@@ -0,0 +1,422 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use lopdf::{Dictionary, Document, Object, ObjectId, StringFormat};
+
+/// Page attributes a page can inherit from its ancestors in the page tree.
+const INHERITABLE: &[&[u8]] = &[b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+/// Check that a fetched PDF can be merged and return its title (from the
+/// document info, when set) and the placeholder HTML that stands in for it in
+/// the bundle. The placeholder page carries the article header and TOC links;
+/// the PDF's own pages are inserted right after it by [`merge_sources`].
+pub fn placeholder(body: &[u8]) -> Result<(Option<String>, String), Box<dyn Error>> {
+    let doc = Document::load_mem(body).map_err(|e| format!("unreadable PDF: {}", e))?;
+    if doc.is_encrypted() {
+        return Err("encrypted PDFs can't be merged".into());
+    }
+    let pages = doc.get_pages().len();
+    if pages == 0 {
+        return Err("PDF has no pages".into());
+    }
+    let title = doc
+        .trailer
+        .get(b"Info")
+        .and_then(|info| doc.dereference(info))
+        .and_then(|(_, info)| info.as_dict())
+        .and_then(|info| info.get(b"Title"))
+        .and_then(lopdf::decode_text_string)
+        .ok()
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty());
+
+    let html = format!(
+        "<div class=\"pdf-source\"><p>PDF document, {pages} page{plural}. \
+         The original pages follow.</p></div>",
+        pages = pages,
+        plural = if pages == 1 { "" } else { "s" }
+    );
+    Ok((title, html))
+}
+
+/// Insert the pages of each source PDF into the rendered `output_path`, after
+/// the page holding its anchor (an element id in the rendered HTML, which
+/// WeasyPrint turns into a named destination).
+pub fn merge_sources(output_path: &str, sources: &[(String, &[u8])]) -> Result<(), Box<dyn Error>> {
+    if sources.is_empty() {
+        return Ok(());
+    }
+
+    let mut doc = Document::load(output_path)
+        .map_err(|e| format!("failed to reopen {} for merging: {}", output_path, e))?;
+    let mut dests = named_destinations(&doc);
+    for (anchor, body) in sources {
+        let after = destination_page(&doc, dests.get(anchor.as_bytes()));
+        if after.is_none() {
+            eprintln!(
+                "Warning: placeholder page for a merged PDF not found; appending its pages at the end"
+            );
+        }
+        if let Err(e) = insert_pdf(&mut doc, body, after, anchor, &mut dests) {
+            eprintln!("Warning: failed to merge PDF pages: {}", e);
+        }
+    }
+    set_named_destinations(&mut doc, dests)?;
+    doc.save(output_path)?;
+    Ok(())
+}
+
+/// Copy the pages of `body` into `doc` after `after` (or at the end). Named
+/// destinations of the source are kept under `prefix` so its internal links
+/// (sections, citations) still work.
+fn insert_pdf(
+    doc: &mut Document,
+    body: &[u8],
+    after: Option<ObjectId>,
+    prefix: &str,
+    dests: &mut BTreeMap<Vec<u8>, Object>,
+) -> Result<(), Box<dyn Error>> {
+    let mut source = Document::load_mem(body)?;
+    source.renumber_objects_with(doc.max_id + 1);
+    let pages: Vec<ObjectId> = source.page_iter().collect();
+    for &page in &pages {
+        flatten_inherited(&mut source, page)?;
+        prefix_link_destinations(&mut source, page, prefix)?;
+    }
+    for (name, dest) in named_destinations(&source) {
+        dests.insert(prefixed(prefix, &name), dest);
+    }
+
+    let info = source
+        .trailer
+        .get(b"Info")
+        .and_then(Object::as_reference)
+        .ok();
+    for (id, object) in source.objects {
+        let skip = Some(id) == info
+            || object
+                .type_name()
+                .is_ok_and(|name| matches!(name, b"Catalog" | b"Pages" | b"XRef" | b"ObjStm"));
+        if !skip {
+            doc.objects.insert(id, object);
+        }
+    }
+    doc.max_id = doc.max_id.max(source.max_id);
+
+    let (parent, index) = match after {
+        Some(page) => {
+            let parent = doc.get_dictionary(page)?.get(b"Parent")?.as_reference()?;
+            let kids = doc.get_dictionary(parent)?.get(b"Kids")?.as_array()?;
+            let index = kids
+                .iter()
+                .position(|kid| kid.as_reference().ok() == Some(page))
+                .ok_or("placeholder page missing from its parent")?;
+            (parent, index + 1)
+        }
+        None => {
+            let root = doc.catalog()?.get(b"Pages")?.as_reference()?;
+            let count = doc.get_dictionary(root)?.get(b"Kids")?.as_array()?.len();
+            (root, count)
+        }
+    };
+    for &page in &pages {
+        doc.get_dictionary_mut(page)?
+            .set("Parent", Object::Reference(parent));
+    }
+    let kids = doc
+        .get_dictionary_mut(parent)?
+        .get_mut(b"Kids")?
+        .as_array_mut()?;
+    kids.splice(
+        index..index,
+        pages.iter().map(|&page| Object::Reference(page)),
+    );
+
+    let added = pages.len() as i64;
+    let mut node = Some(parent);
+    while let Some(id) = node {
+        let dict = doc.get_dictionary_mut(id)?;
+        let count = dict.get(b"Count")?.as_i64()?;
+        dict.set("Count", count + added);
+        node = dict.get(b"Parent").and_then(Object::as_reference).ok();
+    }
+    Ok(())
+}
+
+/// Copy attributes the page inherits from its page-tree ancestors onto the
+/// page itself, since it gets a new parent.
+fn flatten_inherited(doc: &mut Document, page: ObjectId) -> Result<(), Box<dyn Error>> {
+    let mut inherited = Vec::new();
+    let dict = doc.get_dictionary(page)?;
+    for &key in INHERITABLE {
+        if dict.has(key) {
+            continue;
+        }
+        let mut parent = dict.get(b"Parent").and_then(Object::as_reference).ok();
+        while let Some(id) = parent {
+            let node = doc.get_dictionary(id)?;
+            if let Ok(value) = node.get(key) {
+                inherited.push((key, value.clone()));
+                break;
+            }
+            parent = node.get(b"Parent").and_then(Object::as_reference).ok();
+        }
+    }
+    let dict = doc.get_dictionary_mut(page)?;
+    for (key, value) in inherited {
+        dict.set(key, value);
+    }
+    Ok(())
+}
+
+/// Point the page's named-destination links at the prefixed names.
+fn prefix_link_destinations(
+    doc: &mut Document,
+    page: ObjectId,
+    prefix: &str,
+) -> Result<(), Box<dyn Error>> {
+    let annots = match doc.get_dictionary(page)?.get(b"Annots") {
+        Ok(Object::Array(annots)) => annots.clone(),
+        Ok(Object::Reference(id)) => doc.get_object(*id)?.as_array()?.clone(),
+        _ => return Ok(()),
+    };
+    for annot in annots {
+        let Ok(id) = annot.as_reference() else {
+            continue;
+        };
+        let Ok(annot) = doc.get_dictionary_mut(id) else {
+            continue;
+        };
+        if let Ok(dest) = annot.get_mut(b"Dest") {
+            prefix_name(dest, prefix);
+        }
+        if let Ok(action) = annot.get_mut(b"A").and_then(Object::as_dict_mut)
+            && action.get(b"S").and_then(Object::as_name).ok() == Some(b"GoTo".as_slice())
+            && let Ok(dest) = action.get_mut(b"D")
+        {
+            prefix_name(dest, prefix);
+        }
+    }
+    Ok(())
+}
+
+fn prefix_name(dest: &mut Object, prefix: &str) {
+    let name = match dest {
+        Object::String(name, _) | Object::Name(name) => name.clone(),
+        _ => return,
+    };
+    *dest = Object::String(prefixed(prefix, &name), StringFormat::Literal);
+}
+
+fn prefixed(prefix: &str, name: &[u8]) -> Vec<u8> {
+    let mut out = format!("{}-", prefix).into_bytes();
+    out.extend_from_slice(name);
+    out
+}
+
+/// Named destinations from the catalog's `Dests` name tree and the older
+/// `Dests` dictionary.
+fn named_destinations(doc: &Document) -> BTreeMap<Vec<u8>, Object> {
+    let mut dests = BTreeMap::new();
+    let Ok(catalog) = doc.catalog() else {
+        return dests;
+    };
+    if let Ok(Object::Dictionary(old)) = catalog
+        .get(b"Dests")
+        .and_then(|d| doc.dereference(d))
+        .map(|(_, d)| d)
+    {
+        for (name, dest) in old.iter() {
+            dests.insert(name.clone(), dest.clone());
+        }
+    }
+    if let Ok(Object::Dictionary(tree)) = catalog
+        .get(b"Names")
+        .and_then(|n| doc.dereference(n))
+        .and_then(|(_, n)| n.as_dict())
+        .and_then(|n| n.get(b"Dests"))
+        .and_then(|d| doc.dereference(d))
+        .map(|(_, d)| d)
+    {
+        collect_name_tree(doc, tree, &mut dests, 0);
+    }
+    dests
+}
+
+fn collect_name_tree(
+    doc: &Document,
+    node: &Dictionary,
+    dests: &mut BTreeMap<Vec<u8>, Object>,
+    depth: usize,
+) {
+    if depth > 32 {
+        return;
+    }
+    if let Ok(names) = node.get(b"Names").and_then(Object::as_array) {
+        for pair in names.chunks(2) {
+            if let [Object::String(name, _), dest] = pair {
+                dests.insert(name.clone(), dest.clone());
+            }
+        }
+    }
+    for kid in node
+        .get(b"Kids")
+        .and_then(Object::as_array)
+        .into_iter()
+        .flatten()
+    {
+        if let Ok(kid) = kid.as_reference().and_then(|id| doc.get_dictionary(id)) {
+            collect_name_tree(doc, kid, dests, depth + 1);
+        }
+    }
+}
+
+/// The page a destination (`[page /XYZ ...]` or `<< /D [...] >>`) points to.
+fn destination_page(doc: &Document, dest: Option<&Object>) -> Option<ObjectId> {
+    let (_, dest) = doc.dereference(dest?).ok()?;
+    let array = match dest {
+        Object::Dictionary(dict) => doc.dereference(dict.get(b"D").ok()?).ok()?.1,
+        other => other,
+    };
+    array.as_array().ok()?.first()?.as_reference().ok()
+}
+
+/// Replace the catalog's destinations with a single sorted name tree node.
+fn set_named_destinations(
+    doc: &mut Document,
+    dests: BTreeMap<Vec<u8>, Object>,
+) -> Result<(), Box<dyn Error>> {
+    let mut names = Vec::with_capacity(dests.len() * 2);
+    for (name, dest) in dests {
+        names.push(Object::String(name, StringFormat::Literal));
+        names.push(dest);
+    }
+    let tree = doc.add_object(Dictionary::from_iter([("Names", Object::Array(names))]));
+    let catalog = doc.catalog_mut()?;
+    catalog.remove(b"Dests");
+    let mut name_dict = match catalog.get(b"Names") {
+        Ok(Object::Dictionary(dict)) => dict.clone(),
+        _ => Dictionary::new(),
+    };
+    name_dict.set("Dests", Object::Reference(tree));
+    catalog.set("Names", Object::Dictionary(name_dict));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_sources, named_destinations, placeholder};
+    use lopdf::{Dictionary, Document, Object, Stream, StringFormat};
+
+    const FIXTURE: &[u8] = include_bytes!("../../testdata/pdf/two-pages.pdf");
+
+    /// A three-page stand-in for WeasyPrint output with `anchor` on page 2.
+    fn rendered_bundle(anchor: &str) -> Document {
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let mut kids = Vec::new();
+        for idx in 1..=3 {
+            let content = doc.add_object(Stream::new(
+                Dictionary::new(),
+                format!("BT (Bundle page {idx}) Tj ET").into_bytes(),
+            ));
+            kids.push(Object::Reference(doc.add_object(Dictionary::from_iter([
+                ("Type", Object::Name(b"Page".to_vec())),
+                ("Parent", Object::Reference(pages_id)),
+                ("Contents", Object::Reference(content)),
+                (
+                    "MediaBox",
+                    Object::Array(vec![0.into(), 0.into(), 400.into(), 600.into()]),
+                ),
+            ]))));
+        }
+        let dest = Object::Array(vec![
+            kids[1].clone(),
+            Object::Name(b"XYZ".to_vec()),
+            0.into(),
+            600.into(),
+            0.into(),
+        ]);
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(Dictionary::from_iter([
+                ("Type", Object::Name(b"Pages".to_vec())),
+                ("Kids", Object::Array(kids)),
+                ("Count", 3.into()),
+            ])),
+        );
+        let names = Dictionary::from_iter([(
+            "Names",
+            Object::Array(vec![
+                Object::String(anchor.as_bytes().to_vec(), StringFormat::Literal),
+                dest,
+            ]),
+        )]);
+        let catalog = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", Object::Reference(pages_id)),
+            (
+                "Names",
+                Object::Dictionary(Dictionary::from_iter([(
+                    "Dests",
+                    Object::Dictionary(names),
+                )])),
+            ),
+        ]));
+        doc.trailer.set("Root", Object::Reference(catalog));
+        doc
+    }
+
+    #[test]
+    fn source_pages_follow_their_placeholder_page() {
+        let (title, html) = placeholder(FIXTURE).expect("placeholder");
+        assert_eq!(title.as_deref(), Some("Two Page Fixture"));
+        assert!(html.contains("PDF document, 2 pages."));
+
+        let anchor = "article-2".to_string();
+        let path = std::env::temp_dir().join(format!("rmfeeder-merge-{}.pdf", std::process::id()));
+        rendered_bundle(&anchor).save(&path).expect("write bundle");
+        merge_sources(
+            path.to_str().expect("utf8 path"),
+            &[(anchor.clone(), FIXTURE)],
+        )
+        .expect("merge");
+        let merged = Document::load(&path).expect("reload");
+        std::fs::remove_file(&path).ok();
+
+        let pages: Vec<_> = merged.page_iter().collect();
+        let text: Vec<String> = pages
+            .iter()
+            .map(|&page| String::from_utf8_lossy(&merged.get_page_content(page)).into_owned())
+            .collect();
+        assert_eq!(pages.len(), 5);
+        assert!(text[1].contains("Bundle page 2"));
+        assert!(text[2].contains("Fixture page 1"));
+        assert!(text[3].contains("Fixture page 2"));
+        assert!(text[4].contains("Bundle page 3"));
+
+        // Inherited attributes travel with the page; its link keeps working.
+        let first = merged.get_dictionary(pages[2]).expect("page");
+        assert!(first.has(b"MediaBox") && first.has(b"Resources"));
+        let dests = named_destinations(&merged);
+        let link = format!("{anchor}-sec.2");
+        assert!(dests.contains_key(anchor.as_bytes()));
+        assert_eq!(
+            dests[link.as_bytes()].as_array().expect("dest")[0]
+                .as_reference()
+                .ok(),
+            Some(pages[3])
+        );
+        let annot = merged.get_page_annotations(pages[2]).expect("annots");
+        assert_eq!(
+            annot[0].get(b"Dest").and_then(Object::as_str).ok(),
+            Some(link.as_bytes())
+        );
+    }
+
+    #[test]
+    fn no_sources_leave_the_output_alone() {
+        merge_sources("/nonexistent/out.pdf", &[]).expect("no-op");
+        assert!(placeholder(b"%PDF-1.4 truncated").is_err());
+    }
+}
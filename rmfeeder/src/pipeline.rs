@@ -0,0 +1,126 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+
+/// Counting semaphore used to cap concurrent summarizer subprocesses.
+pub struct Semaphore {
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl Semaphore {
+    pub fn new(permits: usize) -> Self {
+        Self {
+            available: Mutex::new(permits.max(1)),
+            released: Condvar::new(),
+        }
+    }
+
+    pub fn acquire(&self) -> SemaphorePermit<'_> {
+        let mut available = self.available.lock().expect("semaphore lock");
+        while *available == 0 {
+            available = self.released.wait(available).expect("semaphore lock");
+        }
+        *available -= 1;
+        SemaphorePermit { semaphore: self }
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        let mut available = self.semaphore.available.lock().expect("semaphore lock");
+        *available += 1;
+        self.semaphore.released.notify_one();
+    }
+}
+
+/// Apply `f` to every item on up to `jobs` worker threads, returning results in
+/// the same order as `items` regardless of completion order.
+pub fn map_ordered<T, R, F>(items: &[T], jobs: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let workers = jobs.max(1).min(items.len());
+    if workers <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new((0..items.len()).map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                loop {
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(idx) else {
+                        break;
+                    };
+                    let result = f(item);
+                    results.lock().expect("pipeline results lock")[idx] = Some(result);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .expect("pipeline results lock")
+        .into_iter()
+        .map(|result| result.expect("every item is processed"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Semaphore, map_ordered};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn preserves_input_order_when_items_finish_out_of_order() {
+        let items: Vec<u64> = vec![40, 5, 30, 1, 20, 10];
+        let results = map_ordered(&items, 4, |ms| {
+            thread::sleep(Duration::from_millis(*ms));
+            ms * 2
+        });
+        assert_eq!(results, vec![80, 10, 60, 2, 40, 20]);
+    }
+
+    #[test]
+    fn never_runs_more_than_the_requested_number_of_jobs() {
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let items: Vec<usize> = (0..12).collect();
+        map_ordered(&items, 3, |_| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(10));
+            running.fetch_sub(1, Ordering::SeqCst);
+        });
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+    }
+
+    #[test]
+    fn semaphore_caps_concurrent_holders() {
+        let semaphore = Semaphore::new(2);
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let items: Vec<usize> = (0..8).collect();
+        map_ordered(&items, 8, |_| {
+            let _permit = semaphore.acquire();
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(10));
+            running.fetch_sub(1, Ordering::SeqCst);
+        });
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+}
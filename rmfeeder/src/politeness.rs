@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+/// Enforces a minimum spacing between requests to the same host while letting
+/// requests to different hosts proceed immediately.
+pub struct HostScheduler {
+    spacing: Duration,
+    next_slot: Mutex<HashMap<String, Instant>>,
+}
+
+impl HostScheduler {
+    pub fn new(spacing: Duration) -> Self {
+        Self {
+            spacing,
+            next_slot: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Block until `url`'s host may be contacted again, reserving the slot after it.
+    pub fn wait_turn(&self, url: &str) {
+        if self.spacing.is_zero() {
+            return;
+        }
+        let Some(host) = host_key(url) else {
+            return;
+        };
+
+        let slot = {
+            let mut next_slot = self.next_slot.lock().expect("host scheduler lock");
+            let now = Instant::now();
+            let slot = next_slot
+                .get(&host)
+                .copied()
+                .filter(|slot| *slot > now)
+                .unwrap_or(now);
+            next_slot.insert(host, slot + self.spacing);
+            slot
+        };
+
+        let now = Instant::now();
+        if slot > now {
+            thread::sleep(slot - now);
+        }
+    }
+}
+
+fn host_key(url: &str) -> Option<String> {
+    let parsed = Url::parse(url).ok()?;
+    let host = parsed.host_str()?.to_ascii_lowercase();
+    Some(host.trim_start_matches("www.").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HostScheduler;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn spaces_requests_to_the_same_host() {
+        let scheduler = HostScheduler::new(Duration::from_millis(80));
+        let start = Instant::now();
+        scheduler.wait_turn("https://example.com/a");
+        scheduler.wait_turn("https://www.example.com/b");
+        scheduler.wait_turn("https://example.com/c");
+        assert!(start.elapsed() >= Duration::from_millis(160));
+    }
+
+    #[test]
+    fn different_hosts_do_not_wait_on_each_other() {
+        let scheduler = HostScheduler::new(Duration::from_secs(30));
+        let start = Instant::now();
+        scheduler.wait_turn("https://a.example.com/");
+        scheduler.wait_turn("https://b.example.com/");
+        scheduler.wait_turn("https://c.example.org/");
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
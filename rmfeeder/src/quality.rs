@@ -0,0 +1,517 @@
+use std::fmt;
+
+use dom_query::{Document, Matcher};
+
+use crate::extractor::ExtractionMethod;
+use crate::ingest::IngestedItem;
+use crate::{AppConfig, escape_html};
+
+pub const DEFAULT_MIN_WORDS: usize = 50;
+pub const DEFAULT_MAX_LINK_DENSITY: f64 = 0.5;
+
+/// Wall phrases and selectors only count against content shorter than this; a
+/// long article with a leftover "already a subscriber?" box is still an article.
+const TEASER_WORDS: usize = 400;
+
+const PAYWALL_PHRASES: &[&str] = &[
+    "subscribe to continue reading",
+    "subscribe to read",
+    "subscribe to keep reading",
+    "this article is for subscribers",
+    "this content is for subscribers",
+    "available to subscribers only",
+    "already a subscriber",
+    "sign in to continue reading",
+    "log in to continue reading",
+    "create a free account to continue",
+    "you have reached your limit of free articles",
+];
+
+const PAYWALL_SELECTORS: &[&str] = &[
+    ".paywall",
+    "#paywall",
+    ".piano-offer",
+    ".tp-modal",
+    "[data-testid=\"paywall\"]",
+    ".subscriber-only",
+    ".meteredContent",
+];
+
+const CONSENT_PHRASES: &[&str] = &[
+    "we use cookies",
+    "accept all cookies",
+    "manage cookie preferences",
+    "cookie consent",
+    "before you continue to",
+    "we value your privacy",
+    "your privacy choices",
+];
+
+const CONSENT_SELECTORS: &[&str] = &[
+    "#onetrust-banner-sdk",
+    "#CybotCookiebotDialog",
+    ".fc-consent-root",
+    ".cookie-consent",
+    "#cookie-banner",
+    "#sp_message_container",
+];
+
+/// What to do with items that fail the check.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum QualityAction {
+    /// Leave the item out of the bundle.
+    Skip,
+    /// Keep the item with a warning banner above its body.
+    Warn,
+}
+
+impl QualityAction {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "skip" | "exclude" => Some(Self::Skip),
+            "warn" | "keep" => Some(Self::Warn),
+            _ => None,
+        }
+    }
+}
+
+/// Why an extracted article looks like a stub rather than the real thing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QualityIssue {
+    /// Matched a subscription wall phrase or selector.
+    Paywall(String),
+    /// Matched a cookie/consent wall phrase or selector.
+    ConsentWall(String),
+    TooShort {
+        words: usize,
+        min: usize,
+    },
+    LinkHeavy {
+        density: f64,
+        max: f64,
+    },
+}
+
+impl QualityIssue {
+    /// Skip category shown in the run summary.
+    pub fn category(&self) -> &'static str {
+        match self {
+            Self::Paywall(_) => "paywall",
+            Self::ConsentWall(_) => "consent-wall",
+            Self::TooShort { .. } => "too-short",
+            Self::LinkHeavy { .. } => "link-heavy",
+        }
+    }
+
+    /// Banner placed above the body of items kept with `quality_action = "warn"`.
+    pub fn warning_html(&self) -> String {
+        format!(
+            "<p class=\"quality-warning\">This may not be the full article: {}.</p>",
+            escape_html(&self.to_string())
+        )
+    }
+}
+
+impl fmt::Display for QualityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Paywall(found) => write!(f, "paywall detected ({})", found),
+            Self::ConsentWall(found) => write!(f, "consent wall detected ({})", found),
+            Self::TooShort { words, min } => write!(f, "only {} words (minimum {})", words, min),
+            Self::LinkHeavy { density, max } => write!(
+                f,
+                "{:.0}% of the text is links (maximum {:.0}%)",
+                density * 100.0,
+                max * 100.0
+            ),
+        }
+    }
+}
+
+/// Post-extraction check that catches subscription walls, consent walls and
+/// link-list stubs before they end up in a bundle.
+#[derive(Debug, Clone)]
+pub struct QualityGate {
+    pub min_words: usize,
+    /// Maximum share of text characters inside links, 0.0–1.0.
+    pub max_link_density: f64,
+    /// Extra paywall phrases (case-insensitive) on top of the built-in list.
+    pub phrases: Vec<String>,
+    /// Extra paywall selectors, matched like the built-in ones.
+    pub selectors: Vec<String>,
+    pub action: QualityAction,
+}
+
+impl Default for QualityGate {
+    fn default() -> Self {
+        Self {
+            min_words: DEFAULT_MIN_WORDS,
+            max_link_density: DEFAULT_MAX_LINK_DENSITY,
+            phrases: Vec::new(),
+            selectors: Vec::new(),
+            action: QualityAction::Skip,
+        }
+    }
+}
+
+impl QualityGate {
+    /// Build from config; `None` when `quality_check = false`.
+    pub fn from_config(config: Option<&AppConfig>) -> Option<Self> {
+        let Some(config) = config else {
+            return Some(Self::default());
+        };
+        if config.quality_check == Some(false) {
+            return None;
+        }
+        let action = match config.quality_action.as_deref() {
+            Some(value) => QualityAction::parse(value).unwrap_or_else(|| {
+                eprintln!(
+                    "Warning: unknown quality_action {:?}; expected skip or warn",
+                    value
+                );
+                QualityAction::Skip
+            }),
+            None => QualityAction::Skip,
+        };
+        let selectors = config
+            .quality_selectors
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|selector| {
+                let valid = Matcher::new(selector).is_ok();
+                if !valid {
+                    eprintln!("Warning: ignoring invalid quality selector {}", selector);
+                }
+                valid
+            })
+            .collect();
+        Some(Self {
+            min_words: config.quality_min_words.unwrap_or(DEFAULT_MIN_WORDS),
+            max_link_density: config
+                .quality_max_link_density
+                .unwrap_or(DEFAULT_MAX_LINK_DENSITY),
+            phrases: config
+                .quality_phrases
+                .clone()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|phrase| phrase.to_lowercase())
+                .collect(),
+            selectors,
+            action,
+        })
+    }
+
+    /// Check an ingested item. OpenGraph teasers are short by design and
+    /// `explicit` URLs (command line or URL file) were asked for by name, so
+    /// those only go through wall detection, not the length and link checks.
+    pub fn check_item(
+        &self,
+        item: &IngestedItem,
+        page_html: Option<&str>,
+        explicit: bool,
+    ) -> Option<QualityIssue> {
+        let walls_only = explicit || item.method == Some(ExtractionMethod::OpenGraph);
+        self.check_content(&item.content_html, page_html, !walls_only)
+    }
+
+    /// Check extracted `content_html`. `page_html` is the fetched page, searched
+    /// for wall selectors (which extraction usually strips) only when the content
+    /// is below `min_words`; many fully readable pages ship consent and paywall
+    /// wrappers in their markup.
+    pub fn check(&self, content_html: &str, page_html: Option<&str>) -> Option<QualityIssue> {
+        self.check_content(content_html, page_html, true)
+    }
+
+    fn check_content(
+        &self,
+        content_html: &str,
+        page_html: Option<&str>,
+        length_checks: bool,
+    ) -> Option<QualityIssue> {
+        let content = Document::fragment(content_html);
+        let text = content.select("html").text().to_string();
+        let words = count_words(&text);
+
+        if words < TEASER_WORDS
+            && let Some(issue) = self.find_wall(
+                &text,
+                &content,
+                page_html.filter(|_| words < self.min_words),
+            )
+        {
+            return Some(issue);
+        }
+        if !length_checks {
+            return None;
+        }
+        if words < self.min_words {
+            return Some(QualityIssue::TooShort {
+                words,
+                min: self.min_words,
+            });
+        }
+
+        let text_chars = count_visible_chars(&text);
+        let link_chars = count_visible_chars(&content.select("a").text());
+        if text_chars > 0 {
+            let density = link_chars as f64 / text_chars as f64;
+            if density > self.max_link_density {
+                return Some(QualityIssue::LinkHeavy {
+                    density,
+                    max: self.max_link_density,
+                });
+            }
+        }
+        None
+    }
+
+    fn find_wall(
+        &self,
+        text: &str,
+        content: &Document,
+        page_html: Option<&str>,
+    ) -> Option<QualityIssue> {
+        let lower = text
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+        let paywall_phrase = PAYWALL_PHRASES
+            .iter()
+            .copied()
+            .chain(self.phrases.iter().map(String::as_str))
+            .find(|phrase| lower.contains(phrase));
+        if let Some(phrase) = paywall_phrase {
+            return Some(QualityIssue::Paywall(format!("\"{}\"", phrase)));
+        }
+        if let Some(phrase) = CONSENT_PHRASES
+            .iter()
+            .find(|phrase| lower.contains(*phrase))
+        {
+            return Some(QualityIssue::ConsentWall(format!("\"{}\"", phrase)));
+        }
+
+        let page = page_html.map(Document::from);
+        let page = page.as_ref().unwrap_or(content);
+        let paywall_selector = PAYWALL_SELECTORS
+            .iter()
+            .copied()
+            .chain(self.selectors.iter().map(String::as_str))
+            .find(|selector| page.try_select(selector).is_some());
+        if let Some(selector) = paywall_selector {
+            return Some(QualityIssue::Paywall(selector.to_string()));
+        }
+        CONSENT_SELECTORS
+            .iter()
+            .find(|selector| page.try_select(selector).is_some())
+            .map(|selector| QualityIssue::ConsentWall(selector.to_string()))
+    }
+}
+
+/// Words in `text`. Scripts written without spaces between words (Chinese,
+/// Japanese, Thai, ...) count one word per two characters.
+fn count_words(text: &str) -> usize {
+    let mut words = 0;
+    let mut unspaced_chars = 0;
+    for token in text.split_whitespace() {
+        unspaced_chars += token.chars().filter(|c| is_unspaced_script(*c)).count();
+        if token
+            .chars()
+            .any(|c| c.is_alphanumeric() && !is_unspaced_script(c))
+        {
+            words += 1;
+        }
+    }
+    words + unspaced_chars.div_ceil(2)
+}
+
+fn is_unspaced_script(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x0E00..=0x0EFF // Thai, Lao
+            | 0x1000..=0x109F // Myanmar
+            | 0x1780..=0x17FF // Khmer
+            | 0x3040..=0x30FF // Hiragana, Katakana
+            | 0x31F0..=0x31FF // Katakana extensions
+            | 0x3400..=0x4DBF // CJK extension A
+            | 0x4E00..=0x9FFF // CJK unified ideographs
+            | 0xF900..=0xFAFF // CJK compatibility ideographs
+            | 0xFF66..=0xFF9D // Halfwidth katakana
+            | 0x20000..=0x2FA1F // CJK extensions B and later
+    )
+}
+
+fn count_visible_chars(text: &str) -> usize {
+    text.chars().filter(|c| !c.is_whitespace()).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QualityAction, QualityGate, QualityIssue};
+    use crate::extractor::ExtractionMethod;
+    use crate::fetcher::{FetchSettings, Fetcher};
+    use crate::ingest::{IngestOptions, IngestedItem, ingest};
+    use crate::test_support::{TestResponse, TestServer};
+
+    fn paragraph(words: usize) -> String {
+        format!("<p>{}</p>", "word ".repeat(words))
+    }
+
+    #[test]
+    fn full_articles_pass() {
+        let gate = QualityGate::default();
+        let html = format!(
+            "{}<p>See <a href=\"/x\">the report</a>.</p>",
+            paragraph(120)
+        );
+        assert_eq!(gate.check(&html, None), None);
+    }
+
+    #[test]
+    fn subscription_and_consent_walls_are_detected() {
+        let gate = QualityGate::default();
+        let teaser = format!(
+            "{}<p>Subscribe to   continue reading this story.</p>",
+            paragraph(60)
+        );
+        assert_eq!(
+            gate.check(&teaser, None).map(|issue| issue.category()),
+            Some("paywall")
+        );
+
+        let consent = "<p>We use cookies to improve your experience. Accept all cookies?</p>";
+        assert_eq!(
+            gate.check(consent, None).map(|issue| issue.category()),
+            Some("consent-wall")
+        );
+
+        let page = "<html><body><div id=\"onetrust-banner-sdk\"></div></body></html>";
+        assert_eq!(
+            gate.check(&paragraph(20), Some(page)),
+            Some(QualityIssue::ConsentWall(
+                "#onetrust-banner-sdk".to_string()
+            ))
+        );
+        // A readable article on a page that merely ships a banner passes; in the
+        // content itself the wrapper still counts.
+        assert_eq!(gate.check(&paragraph(80), Some(page)), None);
+        let walled = format!("<div class=\"paywall\">{}</div>", paragraph(80));
+        assert_eq!(
+            gate.check(&walled, Some(page)),
+            Some(QualityIssue::Paywall(".paywall".to_string()))
+        );
+
+        // A leftover box at the end of a long article is not a wall.
+        let long = format!("{}<p>Already a subscriber? Sign in.</p>", paragraph(500));
+        assert_eq!(gate.check(&long, None), None);
+    }
+
+    #[test]
+    fn short_and_link_heavy_content_fails() {
+        let gate = QualityGate {
+            phrases: vec!["members only".to_string()],
+            ..QualityGate::default()
+        };
+        assert_eq!(
+            gate.check(&paragraph(10), None),
+            Some(QualityIssue::TooShort { words: 10, min: 50 })
+        );
+
+        let links: String = (0..30)
+            .map(|i| format!("<li><a href=\"/{i}\">Another related headline</a></li>"))
+            .collect();
+        let html = format!("{}<ul>{}</ul>", paragraph(40), links);
+        assert_eq!(
+            gate.check(&html, None).map(|issue| issue.category()),
+            Some("link-heavy")
+        );
+
+        let custom = format!("{}<p>Members only.</p>", paragraph(80));
+        assert_eq!(
+            gate.check(&custom, None),
+            Some(QualityIssue::Paywall("\"members only\"".to_string()))
+        );
+    }
+
+    #[test]
+    fn scripts_without_spaces_are_counted_by_characters() {
+        let gate = QualityGate::default();
+        let chinese = format!(
+            "<p>{}</p>",
+            "这是一篇关于城市交通规划的长篇报道，记者走访了多个社区并采访了居民。".repeat(6)
+        );
+        assert_eq!(gate.check(&chinese, None), None);
+
+        let japanese = "<p>東京の天気は晴れです。</p>";
+        assert_eq!(
+            gate.check(japanese, None),
+            Some(QualityIssue::TooShort { words: 5, min: 50 })
+        );
+
+        let thai = format!(
+            "<p>{}</p>",
+            "รัฐบาลประกาศแผนพัฒนาพลังงานหมุนเวียนทั่วประเทศในอีกห้าปีข้างหน้า ".repeat(4)
+        );
+        assert_eq!(gate.check(&thai, None), None);
+    }
+
+    #[test]
+    fn open_graph_teasers_and_explicit_urls_skip_the_length_checks() {
+        let server = TestServer::start(|req| match req.path.as_str() {
+            "/teaser" => TestResponse::ok(
+                "text/html",
+                "<html><head><meta property=\"og:title\" content=\"Teaser Title\">\
+                 <meta property=\"og:description\" content=\"A short teaser for the story.\">\
+                 </head><body><div id=\"app\"></div></body></html>",
+            ),
+            _ => TestResponse::ok(
+                "text/html",
+                "<html><head><meta property=\"og:title\" content=\"Walled\">\
+                 <meta property=\"og:description\" content=\"Subscribe to continue reading.\">\
+                 </head><body></body></html>",
+            ),
+        });
+        let fetcher = Fetcher::new(FetchSettings::default()).expect("client");
+        let gate = QualityGate::default();
+
+        let mut response = fetcher.get(&server.url("/teaser")).expect("fetch");
+        let item = ingest(&mut response, &fetcher, &IngestOptions::default()).expect("ingest");
+        assert_eq!(item.method, Some(ExtractionMethod::OpenGraph));
+        let page = response.text();
+        assert_eq!(
+            gate.check(&item.content_html, Some(&page))
+                .map(|issue| issue.category()),
+            Some("too-short")
+        );
+        assert_eq!(gate.check_item(&item, Some(&page), false), None);
+
+        let explicit = IngestedItem {
+            method: Some(ExtractionMethod::Readability),
+            ..item
+        };
+        assert_eq!(gate.check_item(&explicit, Some(&page), true), None);
+        assert_eq!(
+            gate.check_item(&explicit, Some(&page), false)
+                .map(|issue| issue.category()),
+            Some("too-short")
+        );
+
+        // Walls still count.
+        let mut response = fetcher.get(&server.url("/walled")).expect("fetch");
+        let item = ingest(&mut response, &fetcher, &IngestOptions::default()).expect("ingest");
+        assert_eq!(
+            gate.check_item(&item, Some(&response.text()), true)
+                .map(|issue| issue.category()),
+            Some("paywall")
+        );
+    }
+
+    #[test]
+    fn parses_actions() {
+        assert_eq!(QualityAction::parse(" Warn "), Some(QualityAction::Warn));
+        assert_eq!(QualityAction::parse("skip"), Some(QualityAction::Skip));
+        assert_eq!(QualityAction::parse("drop"), None);
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use url::Url;
+
+/// The product token matched against `User-agent` lines.
+const AGENT_TOKEN: &str = "rmfeeder";
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+/// Allow/Disallow rules from one robots.txt that apply to rmfeeder.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RobotsRules {
+    rules: Vec<Rule>,
+}
+
+impl RobotsRules {
+    /// Parse robots.txt, keeping the group for `rmfeeder` if present, else `*`.
+    pub fn parse(body: &str) -> Self {
+        let mut specific: Vec<Rule> = Vec::new();
+        let mut wildcard: Vec<Rule> = Vec::new();
+        let mut found_specific = false;
+
+        let mut group_agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+
+        for raw_line in body.lines() {
+            let line = raw_line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim();
+
+            match key.as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        group_agents.clear();
+                        in_rules = false;
+                    }
+                    group_agents.push(value.to_ascii_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // An empty Disallow means "allow everything" and adds no rule.
+                    if value.is_empty() {
+                        continue;
+                    }
+                    let rule = Rule {
+                        allow: key == "allow",
+                        pattern: value.to_string(),
+                    };
+                    if group_agents.iter().any(|agent| agent == AGENT_TOKEN) {
+                        found_specific = true;
+                        specific.push(rule.clone());
+                    }
+                    if group_agents.iter().any(|agent| agent == "*") {
+                        wildcard.push(rule);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            rules: if found_specific { specific } else { wildcard },
+        }
+    }
+
+    /// Longest matching pattern wins; `Allow` wins ties.
+    pub fn is_allowed(&self, path: &str) -> bool {
+        let mut best: Option<(usize, bool)> = None;
+        for rule in &self.rules {
+            if !pattern_matches(&rule.pattern, path) {
+                continue;
+            }
+            let len = rule.pattern.len();
+            best = match best {
+                Some((best_len, best_allow))
+                    if best_len > len || (best_len == len && best_allow) =>
+                {
+                    Some((best_len, best_allow))
+                }
+                _ => Some((len, rule.allow)),
+            };
+        }
+        best.map(|(_, allow)| allow).unwrap_or(true)
+    }
+}
+
+/// Per-origin robots.txt cache. Rules are fetched once per run.
+#[derive(Default)]
+pub struct RobotsCache {
+    origins: Mutex<HashMap<String, Arc<RobotsRules>>>,
+}
+
+impl RobotsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check `url` against its origin's robots.txt, loading it via `load` on first use.
+    /// `load` returns `None` when robots.txt is missing or unreachable, which allows everything.
+    pub fn is_allowed(&self, url: &str, load: impl FnOnce(&str) -> Option<String>) -> bool {
+        let Ok(parsed) = Url::parse(url) else {
+            return true;
+        };
+        let origin = parsed.origin().ascii_serialization();
+
+        let cached = self
+            .origins
+            .lock()
+            .expect("robots cache lock")
+            .get(&origin)
+            .cloned();
+        let rules = match cached {
+            Some(rules) => rules,
+            None => {
+                let robots_url = format!("{}/robots.txt", origin);
+                let rules = Arc::new(
+                    load(&robots_url)
+                        .map(|body| RobotsRules::parse(&body))
+                        .unwrap_or_default(),
+                );
+                self.origins
+                    .lock()
+                    .expect("robots cache lock")
+                    .insert(origin, Arc::clone(&rules));
+                rules
+            }
+        };
+
+        let mut path = parsed.path().to_string();
+        if let Some(query) = parsed.query() {
+            path.push('?');
+            path.push_str(query);
+        }
+        rules.is_allowed(&path)
+    }
+}
+
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(stripped) => (stripped, true),
+        None => (pattern, false),
+    };
+    let parts: Vec<&str> = pattern.split('*').collect();
+
+    let Some(rest) = path.strip_prefix(parts[0]) else {
+        return false;
+    };
+    if parts.len() == 1 {
+        return !anchored || rest.is_empty();
+    }
+
+    let mut rest = rest;
+    for (idx, part) in parts.iter().enumerate().skip(1) {
+        let is_last = idx == parts.len() - 1;
+        if is_last && anchored {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RobotsCache, RobotsRules};
+    use std::cell::Cell;
+
+    const ROBOTS: &str = "\
+User-agent: *
+Disallow: /private/
+Allow: /private/open
+Disallow: /*.pdf$
+
+User-agent: SomeBot
+Disallow: /
+";
+
+    #[test]
+    fn applies_wildcard_group_with_longest_match() {
+        let rules = RobotsRules::parse(ROBOTS);
+        assert!(rules.is_allowed("/articles/1"));
+        assert!(!rules.is_allowed("/private/notes"));
+        assert!(rules.is_allowed("/private/open/door"));
+        assert!(!rules.is_allowed("/papers/a.pdf"));
+        assert!(rules.is_allowed("/papers/a.pdf?download=1"));
+    }
+
+    #[test]
+    fn prefers_a_group_naming_rmfeeder() {
+        let rules = RobotsRules::parse(
+            "User-agent: *\nDisallow: /\n\nUser-agent: rmfeeder\nUser-agent: other\nDisallow: /drafts\n",
+        );
+        assert!(rules.is_allowed("/posts/1"));
+        assert!(!rules.is_allowed("/drafts/2"));
+    }
+
+    #[test]
+    fn empty_disallow_allows_everything() {
+        let rules = RobotsRules::parse("User-agent: *\nDisallow:\n");
+        assert!(rules.is_allowed("/anything"));
+    }
+
+    #[test]
+    fn cache_loads_each_origin_once() {
+        let cache = RobotsCache::new();
+        let loads = Cell::new(0);
+        let load = |url: &str| {
+            assert_eq!(url, "https://example.com/robots.txt");
+            loads.set(loads.get() + 1);
+            Some("User-agent: *\nDisallow: /blocked".to_string())
+        };
+
+        assert!(!cache.is_allowed("https://example.com/blocked/1", load));
+        assert!(cache.is_allowed("https://example.com/ok", |_| unreachable!()));
+        assert_eq!(loads.get(), 1);
+        assert!(cache.is_allowed("https://other.example/blocked", |_| None));
+    }
+}
@@ -0,0 +1,319 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use dom_query::{Document, Matcher};
+use dom_smoothie::Article;
+use serde::Deserialize;
+use url::Url;
+
+use crate::extractor::{self, Extraction, ExtractionMethod};
+use crate::request_profile::glob_matches;
+use crate::{AppConfig, expand_tilde_path};
+
+/// Hand-written extraction settings for hosts where readability guesses wrong.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SiteRule {
+    /// Host globs the rule applies to, e.g. `["example.com", "*.example.com"]`.
+    pub hosts: Vec<String>,
+    /// Selector for the article body; all matches are kept, in document order.
+    pub content: Option<String>,
+    /// Selectors removed from the page before anything else runs.
+    pub remove: Vec<String>,
+    /// Selector whose text is used as the title.
+    pub title: Option<String>,
+    /// Use the `content` match as-is instead of running readability over it. The
+    /// page still goes through [`extractor::prepare_page`] first.
+    pub skip_readability: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RulesFile {
+    #[serde(default)]
+    site_rule: Vec<SiteRule>,
+}
+
+impl SiteRule {
+    pub fn matches_host(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        self.hosts
+            .iter()
+            .any(|pattern| glob_matches(&pattern.to_ascii_lowercase(), &host))
+    }
+
+    /// Extract `html` with this rule. `None` means the rule did not apply (no
+    /// content match, or readability found nothing) and the caller should fall back.
+    pub fn extract(&self, html: &str, url: Option<&str>) -> Option<Article> {
+        let prepared = extractor::prepare_page(html);
+        let doc = Document::from(prepared.as_deref().unwrap_or(html));
+        for selector in &self.remove {
+            if let Some(selection) = doc.try_select(selector) {
+                selection.remove();
+            }
+        }
+
+        let title = self
+            .title
+            .as_deref()
+            .and_then(|selector| doc.try_select(selector))
+            .map(|selection| collapse_whitespace(&selection.text()))
+            .filter(|title| !title.is_empty());
+
+        let content = match self.content.as_deref() {
+            Some(selector) => {
+                let matches = doc.try_select(selector)?;
+                let html: Vec<String> = matches.iter().map(|m| m.html().to_string()).collect();
+                Some(html.join("\n"))
+            }
+            None => None,
+        };
+
+        if self.skip_readability {
+            let content = content.unwrap_or_else(|| doc.select("body").inner_html().to_string());
+            let text = Document::fragment(content.as_str()).select("html").text();
+            let text_content = collapse_whitespace(&text);
+            let title =
+                title.unwrap_or_else(|| collapse_whitespace(&doc.select("head title").text()));
+            return Some(Article {
+                title,
+                byline: None,
+                length: text_content.chars().count(),
+                content: content.into(),
+                text_content: text_content.into(),
+                excerpt: None,
+                site_name: None,
+                dir: None,
+                lang: None,
+                published_time: None,
+                modified_time: None,
+                image: None,
+                favicon: None,
+                url: url.map(str::to_string),
+            });
+        }
+
+        // Keep the head so readability still sees the page title and metadata.
+        let narrowed = match content {
+            Some(content) => format!(
+                "<html><head>{}</head><body><article>{}</article></body></html>",
+                doc.select("head").inner_html(),
+                content
+            ),
+            None => doc.html().to_string(),
+        };
+        let mut article = extractor::extract_article(&narrowed, url)?;
+        if let Some(title) = title {
+            article.title = title;
+        }
+        Some(article)
+    }
+
+    /// Selectors in this rule that do not parse.
+    fn invalid_selectors(&self) -> Vec<&str> {
+        self.content
+            .iter()
+            .chain(self.title.iter())
+            .chain(self.remove.iter())
+            .map(String::as_str)
+            .filter(|selector| Matcher::new(selector).is_err())
+            .collect()
+    }
+}
+
+/// Site rules from the config file and the rules directory, in that order.
+#[derive(Debug, Clone, Default)]
+pub struct SiteRules {
+    rules: Vec<SiteRule>,
+}
+
+impl SiteRules {
+    pub fn new(rules: Vec<SiteRule>) -> Self {
+        let rules = rules
+            .into_iter()
+            .filter(|rule| {
+                let invalid = rule.invalid_selectors();
+                if !invalid.is_empty() {
+                    eprintln!(
+                        "Warning: ignoring site rule for {}: invalid selector {}",
+                        rule.hosts.join(", "),
+                        invalid.join(", ")
+                    );
+                }
+                invalid.is_empty()
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Collect `[[site_rule]]` entries from the config, then from every `*.toml`
+    /// file in `site_rules_dir` (sorted by name). Unreadable files are warned about.
+    pub fn from_config(config: Option<&AppConfig>) -> Self {
+        let mut rules = config.and_then(|c| c.site_rule.clone()).unwrap_or_default();
+        if let Some(dir) = config.and_then(|c| c.site_rules_dir.as_deref()) {
+            match load_dir(&expand_tilde_path(dir)) {
+                Ok(mut loaded) => rules.append(&mut loaded),
+                Err(e) => eprintln!("Warning: failed to read site rules from {}: {}", dir, e),
+            }
+        }
+        Self::new(rules)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The first rule whose hosts match `url`.
+    pub fn rule_for(&self, url: &str) -> Option<&SiteRule> {
+        let url = Url::parse(url).ok()?;
+        let host = url.host_str()?;
+        self.rules.iter().find(|rule| rule.matches_host(host))
+    }
+
+    /// Extract with the matching site rule, falling back to the extractor chain.
+    pub fn extract(&self, html: &str, url: Option<&str>) -> Option<Extraction> {
+        url.and_then(|u| self.rule_for(u))
+            .and_then(|rule| rule.extract(html, url))
+            .map(|article| Extraction {
+                article,
+                method: ExtractionMethod::SiteRule,
+            })
+            .or_else(|| extractor::extract(html, url))
+    }
+}
+
+fn load_dir(dir: &Path) -> Result<Vec<SiteRule>, Box<dyn Error>> {
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    paths.sort();
+
+    let mut rules = Vec::new();
+    for path in paths {
+        let parsed = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|raw| toml::from_str::<RulesFile>(&raw).map_err(|e| e.to_string()));
+        match parsed {
+            Ok(mut file) => rules.append(&mut file.site_rule),
+            Err(e) => eprintln!("Warning: skipping site rules {}: {}", path.display(), e),
+        }
+    }
+    Ok(rules)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SiteRule, SiteRules, load_dir};
+    use crate::extractor::ExtractionMethod;
+
+    const PAGE: &str = r#"<html><head><title>Daily | Paper</title></head><body>
+        <header><h1 class="headline">The Real Headline</h1></header>
+        <div class="lede">A lede paragraph that readability tends to drop.</div>
+        <div class="story"><p>Body paragraph one.</p><div class="ad">Buy now</div>
+        <p>Body paragraph two.</p></div>
+        <section id="comments"><p>First!</p></section></body></html>"#;
+
+    fn rule() -> SiteRule {
+        SiteRule {
+            hosts: vec!["*.paper.example".to_string()],
+            content: Some(".lede, .story".to_string()),
+            remove: vec![".ad".to_string(), "#comments".to_string()],
+            title: Some("h1.headline".to_string()),
+            skip_readability: true,
+        }
+    }
+
+    #[test]
+    fn skip_readability_keeps_selected_content_verbatim() {
+        let article = rule()
+            .extract(PAGE, Some("https://www.paper.example/a"))
+            .expect("article");
+        assert_eq!(article.title, "The Real Headline");
+        assert!(article.content.contains("A lede paragraph"));
+        assert!(article.content.contains("Body paragraph two."));
+        assert!(!article.content.contains("Buy now"));
+        assert!(!article.content.contains("First!"));
+        assert!(article.length > 0);
+    }
+
+    #[test]
+    fn skip_readability_content_is_prepared_like_extracted_content() {
+        let page = r#"<html><head><title>Code | Paper</title></head><body>
+            <div class="story"><p>Setup:</p>
+            <pre><code class="language-rust">fn main() {}</code></pre>
+            <img src="data:image/gif;base64,R0lGODlhAQABAAAAACw=" data-src="/chart.png" alt="Chart">
+            <iframe src="https://www.youtube.com/embed/abc123"></iframe></div></body></html>"#;
+        let article = rule()
+            .extract(page, Some("https://www.paper.example/code"))
+            .expect("article");
+        assert!(article.content.contains("data-lang=\"rust\""));
+        assert!(article.content.contains("src=\"/chart.png\""));
+        assert!(article.content.contains("data-embed"));
+        assert!(!article.content.contains("<iframe"));
+    }
+
+    #[test]
+    fn rules_are_matched_by_host_and_fall_back_to_the_extractor_chain() {
+        let rules = SiteRules::new(vec![rule()]);
+        assert!(rules.rule_for("https://www.paper.example/a").is_some());
+        assert!(rules.rule_for("https://other.example/a").is_none());
+
+        let missing = SiteRule {
+            content: Some("article.nope".to_string()),
+            ..rule()
+        };
+        assert!(missing.extract(PAGE, None).is_none());
+
+        let filler = "This paragraph is long enough for readability to keep it as content. ";
+        let page = format!(
+            "<html><head><title>Fallback</title></head><body><article><h1>Fallback</h1>\
+             <p>{filler}{filler}{filler}</p><p>{filler}{filler}{filler}</p></article></body></html>"
+        );
+        let extraction = SiteRules::new(vec![missing])
+            .extract(&page, Some("https://www.paper.example/a"))
+            .expect("fallback extraction");
+        assert_eq!(extraction.method, ExtractionMethod::Readability);
+        assert!(
+            extraction
+                .article
+                .content
+                .contains("long enough for readability")
+        );
+    }
+
+    #[test]
+    fn invalid_selectors_drop_the_rule() {
+        let broken = SiteRule {
+            content: Some("div[".to_string()),
+            ..rule()
+        };
+        assert!(SiteRules::new(vec![broken]).is_empty());
+    }
+
+    #[test]
+    fn loads_rule_files_from_a_directory() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        std::fs::write(
+            dir.path().join("b.toml"),
+            "[[site_rule]]\nhosts = [\"b.example\"]\nskip_readability = true\n",
+        )
+        .expect("write");
+        std::fs::write(
+            dir.path().join("a.toml"),
+            "[[site_rule]]\nhosts = [\"a.example\"]\ncontent = \"main\"\nremove = [\".ad\"]\n",
+        )
+        .expect("write");
+        std::fs::write(dir.path().join("notes.txt"), "ignored").expect("write");
+
+        let rules = load_dir(dir.path()).expect("load");
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].hosts, vec!["a.example".to_string()]);
+        assert_eq!(rules[0].remove, vec![".ad".to_string()]);
+        assert!(rules[1].skip_readability);
+    }
+}
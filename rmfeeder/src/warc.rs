@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use reqwest::StatusCode;
+
+use crate::fetcher::USER_AGENT;
+
+/// Maximum number of recorded redirects followed when replaying one URL.
+const MAX_REPLAY_REDIRECTS: usize = 10;
+
+/// An HTTP response as stored in, or read back from, a WARC `response` record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchivedResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub location: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// Appends WARC/1.1 `response` records for every fetched URL.
+pub struct WarcWriter {
+    out: BufWriter<File>,
+}
+
+impl WarcWriter {
+    /// Create (or truncate) `path` and write the leading `warcinfo` record.
+    pub fn create(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut writer = Self {
+            out: BufWriter::new(File::create(path)?),
+        };
+        let info = format!(
+            "software: {}\r\nformat: WARC File Format 1.1\r\n",
+            USER_AGENT
+        );
+        writer.write_record("warcinfo", None, "application/warc-fields", info.as_bytes())?;
+        Ok(writer)
+    }
+
+    /// Record the response for `url`. A response served from a different final URL is
+    /// stored as a redirect from `url` plus the response under the final URL.
+    pub fn record(
+        &mut self,
+        url: &str,
+        final_url: &str,
+        response: &ArchivedResponse,
+    ) -> Result<(), Box<dyn Error>> {
+        if url != final_url {
+            let redirect = ArchivedResponse {
+                status: 302,
+                content_type: None,
+                location: Some(final_url.to_string()),
+                body: Vec::new(),
+            };
+            self.write_response(url, &redirect)?;
+        }
+        self.write_response(final_url, response)?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    /// Record the headless-browser DOM of `url` as a `conversion` record, so
+    /// replay gets the same content the recorded run extracted.
+    pub fn record_rendered(&mut self, url: &str, html: &str) -> Result<(), Box<dyn Error>> {
+        self.write_record(
+            "conversion",
+            Some(url),
+            "text/html; charset=utf-8",
+            html.as_bytes(),
+        )?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn write_response(
+        &mut self,
+        url: &str,
+        response: &ArchivedResponse,
+    ) -> Result<(), Box<dyn Error>> {
+        let reason = StatusCode::from_u16(response.status)
+            .ok()
+            .and_then(|s| s.canonical_reason())
+            .unwrap_or("");
+        let mut block = format!("HTTP/1.1 {} {}\r\n", response.status, reason);
+        if let Some(content_type) = &response.content_type {
+            block.push_str(&format!("Content-Type: {}\r\n", content_type));
+        }
+        if let Some(location) = &response.location {
+            block.push_str(&format!("Location: {}\r\n", location));
+        }
+        block.push_str(&format!("Content-Length: {}\r\n\r\n", response.body.len()));
+
+        let mut payload = block.into_bytes();
+        payload.extend_from_slice(&response.body);
+        self.write_record(
+            "response",
+            Some(url),
+            "application/http; msgtype=response",
+            &payload,
+        )
+    }
+
+    fn write_record(
+        &mut self,
+        warc_type: &str,
+        target_uri: Option<&str>,
+        content_type: &str,
+        payload: &[u8],
+    ) -> Result<(), Box<dyn Error>> {
+        write!(self.out, "WARC/1.1\r\n")?;
+        write!(self.out, "WARC-Type: {}\r\n", warc_type)?;
+        write!(self.out, "WARC-Record-ID: <urn:uuid:{}>\r\n", record_id())?;
+        write!(
+            self.out,
+            "WARC-Date: {}\r\n",
+            chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ")
+        )?;
+        if let Some(uri) = target_uri {
+            write!(self.out, "WARC-Target-URI: {}\r\n", uri)?;
+        }
+        write!(self.out, "Content-Type: {}\r\n", content_type)?;
+        write!(self.out, "Content-Length: {}\r\n\r\n", payload.len())?;
+        self.out.write_all(payload)?;
+        write!(self.out, "\r\n\r\n")?;
+        Ok(())
+    }
+}
+
+/// Responses loaded from a WARC file, keyed by target URI.
+#[derive(Debug, Default)]
+pub struct WarcArchive {
+    responses: HashMap<String, ArchivedResponse>,
+    /// Browser-rendered DOMs from `conversion` records.
+    rendered: HashMap<String, String>,
+}
+
+impl WarcArchive {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let data = std::fs::read(path)?;
+        Self::parse(&data)
+    }
+
+    /// Parse uncompressed WARC data. Later records for the same URI win.
+    pub fn parse(data: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let mut responses = HashMap::new();
+        let mut rendered = HashMap::new();
+        let mut pos = 0;
+        while pos < data.len() {
+            if data[pos].is_ascii_whitespace() {
+                pos += 1;
+                continue;
+            }
+            let header_end =
+                find(&data[pos..], b"\r\n\r\n").ok_or("truncated WARC record header")? + pos;
+            let headers = parse_headers(&data[pos..header_end]);
+            let length: usize = headers
+                .get("content-length")
+                .ok_or("WARC record without Content-Length")?
+                .parse()?;
+            let block_start = header_end + 4;
+            let block_end = block_start + length;
+            if block_end > data.len() {
+                return Err("truncated WARC record body".into());
+            }
+
+            if headers.get("warc-type").map(String::as_str) == Some("response")
+                && let Some(uri) = headers.get("warc-target-uri")
+                && let Some(response) = parse_http_response(&data[block_start..block_end])
+            {
+                responses.insert(uri.clone(), response);
+            } else if headers.get("warc-type").map(String::as_str) == Some("conversion")
+                && let Some(uri) = headers.get("warc-target-uri")
+            {
+                let html = String::from_utf8_lossy(&data[block_start..block_end]);
+                rendered.insert(uri.clone(), html.into_owned());
+            }
+            pos = block_end;
+        }
+        Ok(Self {
+            responses,
+            rendered,
+        })
+    }
+
+    /// The browser-rendered DOM recorded for `url`, if any.
+    pub fn rendered(&self, url: &str) -> Option<&str> {
+        self.rendered.get(url).map(String::as_str)
+    }
+
+    /// Look up `url`, following recorded redirects. Returns the final URL and response.
+    pub fn lookup(&self, url: &str) -> Option<(String, &ArchivedResponse)> {
+        let mut current = url.to_string();
+        for _ in 0..=MAX_REPLAY_REDIRECTS {
+            let response = self.responses.get(&current)?;
+            match (&response.location, response.status) {
+                (Some(location), 300..=399) => {
+                    current = url::Url::parse(&current)
+                        .and_then(|base| base.join(location))
+                        .map(String::from)
+                        .unwrap_or_else(|_| location.clone());
+                }
+                _ => return Some((current, response)),
+            }
+        }
+        None
+    }
+
+    pub fn len(&self) -> usize {
+        self.responses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.responses.is_empty()
+    }
+}
+
+fn parse_http_response(block: &[u8]) -> Option<ArchivedResponse> {
+    let header_end = find(block, b"\r\n\r\n")?;
+    let head = std::str::from_utf8(&block[..header_end]).ok()?;
+    let mut lines = head.split("\r\n");
+    let status: u16 = lines.next()?.split_whitespace().nth(1)?.parse().ok()?;
+    let headers = parse_headers(&block[..header_end]);
+    Some(ArchivedResponse {
+        status,
+        content_type: headers.get("content-type").cloned(),
+        location: headers.get("location").cloned(),
+        body: block[header_end + 4..].to_vec(),
+    })
+}
+
+/// Parse `Name: value` lines, lowercasing names. The first line (version/status) is skipped.
+fn parse_headers(head: &[u8]) -> HashMap<String, String> {
+    String::from_utf8_lossy(head)
+        .split("\r\n")
+        .skip(1)
+        .filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            Some((name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// A random-looking v4-format UUID; uniqueness within a file is all WARC readers need.
+fn record_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    let seed = nanos ^ (std::process::id() as u64) << 32;
+    let a = splitmix(seed.wrapping_add(COUNTER.fetch_add(1, Ordering::Relaxed)));
+    let b = splitmix(a);
+    format!(
+        "{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+        a >> 32,
+        (a >> 16) & 0xffff,
+        a & 0x0fff,
+        ((b >> 48) & 0x3fff) | 0x8000,
+        b & 0xffff_ffff_ffff
+    )
+}
+
+fn splitmix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArchivedResponse, WarcArchive, WarcWriter};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn html(body: &str) -> ArchivedResponse {
+        ArchivedResponse {
+            status: 200,
+            content_type: Some("text/html; charset=utf-8".to_string()),
+            location: None,
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn records_round_trip_through_the_archive() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let path = std::env::temp_dir().join(format!("rmfeeder-warc-{nanos}.warc"));
+
+        let mut writer = WarcWriter::create(&path).expect("create warc");
+        writer
+            .record(
+                "https://example.com/a",
+                "https://example.com/a",
+                &html("<p>a\r\n\r\nb</p>"),
+            )
+            .expect("record a");
+        writer
+            .record(
+                "http://example.com/old",
+                "https://example.com/new",
+                &html("<p>new</p>"),
+            )
+            .expect("record redirect");
+        writer
+            .record(
+                "https://example.com/missing",
+                "https://example.com/missing",
+                &ArchivedResponse {
+                    status: 404,
+                    content_type: None,
+                    location: None,
+                    body: Vec::new(),
+                },
+            )
+            .expect("record 404");
+        writer
+            .record_rendered("https://example.com/a", "<main><p>rendered</p></main>")
+            .expect("record rendered DOM");
+        drop(writer);
+
+        let archive = WarcArchive::load(&path).expect("load warc");
+        assert_eq!(archive.len(), 4);
+
+        let (final_url, response) = archive.lookup("https://example.com/a").expect("a");
+        assert_eq!(final_url, "https://example.com/a");
+        assert_eq!(response, &html("<p>a\r\n\r\nb</p>"));
+
+        let (final_url, response) = archive.lookup("http://example.com/old").expect("old");
+        assert_eq!(final_url, "https://example.com/new");
+        assert_eq!(response.body, b"<p>new</p>");
+
+        let (_, response) = archive.lookup("https://example.com/missing").expect("404");
+        assert_eq!(response.status, 404);
+        assert!(archive.lookup("https://example.com/unknown").is_none());
+        assert_eq!(
+            archive.rendered("https://example.com/a"),
+            Some("<main><p>rendered</p></main>")
+        );
+        assert_eq!(archive.rendered("https://example.com/new"), None);
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn rejects_truncated_records() {
+        let data = b"WARC/1.1\r\nWARC-Type: response\r\nContent-Length: 50\r\n\r\nshort";
+        assert!(WarcArchive::parse(data).is_err());
+    }
+}
@@ -1,59 +1,219 @@
-use kuchiki::traits::*;
-use kuchiki::parse_html;
-
-/// Remove nodes entirely
-fn remove_by_selector(document: &kuchiki::NodeRef, selector: &str) {
-    if let Ok(nodes) = document.select(selector) {
-        for node in nodes {
-            node.as_node().detach();
+use dom_query::Document;
+use url::Url;
+
+use crate::embeds;
+
+/// Elements removed with their contents: scripts and interactive widgets. Embeds
+/// are handled separately and become placeholders.
+const ACTIVE_CONTENT: &str = "script, noscript, style, link, meta, base, template, frame, \
+     frameset, applet, form, input, button, select, textarea, dialog, canvas";
+
+/// Attributes holding a single URL.
+const URL_ATTRS: &[&str] = &[
+    "href",
+    "src",
+    "poster",
+    "cite",
+    "data",
+    "data-src",
+    "data-embed",
+];
+
+/// Inline style properties kept; everything else fights `styles.css` or the page box.
+const ALLOWED_STYLE_PROPERTIES: &[&str] = &[
+    "text-align",
+    "font-style",
+    "font-weight",
+    "text-decoration",
+    "vertical-align",
+    "list-style-type",
+];
+
+/// Clean extracted article HTML before it reaches WeasyPrint: drop active
+/// content and event handlers, absolutize URLs against `base_url`, keep only
+/// harmless inline styles, replace embeds with placeholders, and demote
+/// headings so none outranks the bundle `<h1>`.
+pub fn sanitize_content(html: &str, base_url: Option<&str>) -> String {
+    let doc = Document::fragment(html);
+    doc.select(ACTIVE_CONTENT).remove();
+
+    let base = base_url.and_then(|url| Url::parse(url).ok());
+    for element in doc.select("*").iter() {
+        let names: Vec<String> = element
+            .attrs()
+            .iter()
+            .map(|attr| attr.name.local.to_string())
+            .collect();
+        for name in names {
+            let lower = name.to_ascii_lowercase();
+            let Some(value) = element.attr(&name).map(|v| v.to_string()) else {
+                continue;
+            };
+            if lower.starts_with("on") {
+                element.remove_attr(&name);
+            } else if lower == "style" {
+                match clean_style(&value) {
+                    Some(style) => element.set_attr(&name, &style),
+                    None => element.remove_attr(&name),
+                }
+            } else if URL_ATTRS.contains(&lower.as_str()) {
+                match resolve_url(base.as_ref(), &value) {
+                    Some(url) => element.set_attr(&name, &url),
+                    None => element.remove_attr(&name),
+                }
+            } else if lower == "srcset" {
+                element.set_attr(&name, &resolve_srcset(base.as_ref(), &value));
+            }
         }
     }
+
+    embeds::replace_embeds(&doc);
+    demote_headings(&doc);
+    doc.select("html").inner_html().to_string()
+}
+
+/// Resolve `value` against `base`. Script URLs are dropped (`None`); fragments
+/// and non-http schemes are left alone.
+fn resolve_url(base: Option<&Url>, value: &str) -> Option<String> {
+    let trimmed = value.trim();
+    let scheme = trimmed
+        .split_once(':')
+        .map(|(scheme, _)| scheme.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    if matches!(scheme.as_str(), "javascript" | "vbscript") {
+        return None;
+    }
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        return Some(trimmed.to_string());
+    }
+    match base.and_then(|base| base.join(trimmed).ok()) {
+        Some(url) => Some(url.into()),
+        None => Some(trimmed.to_string()),
+    }
 }
 
-/// Remove the tag but keep its children
-fn unwrap_selector(document: &kuchiki::NodeRef, selector: &str) {
-    if let Ok(nodes) = document.select(selector) {
-        for matched in nodes {
-            let node = matched.as_node();
+fn resolve_srcset(base: Option<&Url>, value: &str) -> String {
+    value
+        .split(',')
+        .filter_map(|candidate| {
+            let mut parts = candidate.split_whitespace();
+            let url = resolve_url(base, parts.next()?)?;
+            let descriptor: Vec<&str> = parts.collect();
+            Some(if descriptor.is_empty() {
+                url
+            } else {
+                format!("{} {}", url, descriptor.join(" "))
+            })
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
 
-            // Move children before this node
-            for child in node.children() {
-                node.insert_before(child);
-            }
+/// Keep only allow-listed declarations; `None` when nothing survives.
+fn clean_style(style: &str) -> Option<String> {
+    let kept: Vec<String> = style
+        .split(';')
+        .filter_map(|declaration| {
+            let (property, value) = declaration.split_once(':')?;
+            let property = property.trim().to_ascii_lowercase();
+            let value = value.trim();
+            let safe = ALLOWED_STYLE_PROPERTIES.contains(&property.as_str())
+                && !value.is_empty()
+                && !value.to_ascii_lowercase().contains("url(")
+                && !value.to_ascii_lowercase().contains("expression(");
+            safe.then(|| format!("{}: {}", property, value))
+        })
+        .collect();
+    (!kept.is_empty()).then(|| kept.join("; "))
+}
 
-            node.detach();
+/// Shift all headings down so the highest one is at most `<h2>`.
+fn demote_headings(doc: &Document) {
+    let top = (1..=6).find(|level| doc.select(&format!("h{}", level)).exists());
+    let Some(top) = top.filter(|&level| level < 2) else {
+        return;
+    };
+    let shift = 2 - top;
+    // Deepest first, so a renamed heading is never matched again.
+    for level in (1..=6).rev() {
+        let target = (level + shift).min(6);
+        if target != level {
+            doc.select(&format!("h{}", level))
+                .rename(&format!("h{}", target));
         }
     }
 }
 
-pub fn sanitize_for_xhtml(html: &str) -> String {
-    let document = parse_html().one(html);
+#[cfg(test)]
+mod tests {
+    use super::sanitize_content;
+
+    const BASE: Option<&str> = Some("https://example.com/news/story.html");
 
-    // 🚫 Remove problematic elements
-    for selector in &[
-        "img",
-        "picture",
-        "figure",
-        "wbr",
-        "script",
-        "style",
-        "meta",
-        "link",
-    ] {
-        remove_by_selector(&document, selector);
+    #[test]
+    fn strips_active_content_and_event_handlers() {
+        let html = r#"<p onclick="steal()">Text<script>alert(1)</script></p>
+            <iframe src="https://ads.example"></iframe>
+            <form action="/subscribe"><input name="email"><button>Go</button></form>
+            <a href="javascript:void(0)" onmouseover="x()">link</a>"#;
+        let out = sanitize_content(html, BASE);
+        assert!(out.contains("<p>Text</p>"));
+        assert!(out.contains("<a>link</a>"));
+        for gone in [
+            "script",
+            "iframe",
+            "form",
+            "input",
+            "button",
+            "onclick",
+            "javascript",
+        ] {
+            assert!(!out.contains(gone), "{gone} survived: {out}");
+        }
     }
 
-    // 🚫 REMOVE ALL LINKS — THIS IS THE CRITICAL FIX
-    unwrap_selector(&document, "a");
+    #[test]
+    fn absolutizes_urls_against_the_article() {
+        let html = r##"<a href="../about">About</a><a href="#fn1">1</a>
+            <img src="/img/a.png" srcset="a-1x.png 1x, /b-2x.png 2x">
+            <a href="mailto:editor@example.com">Mail</a>"##;
+        let out = sanitize_content(html, BASE);
+        assert!(out.contains(r#"href="https://example.com/about""#));
+        assert!(out.contains(r##"href="#fn1""##));
+        assert!(out.contains(r#"src="https://example.com/img/a.png""#));
+        assert!(out.contains(
+            r#"srcset="https://example.com/news/a-1x.png 1x, https://example.com/b-2x.png 2x""#
+        ));
+        assert!(out.contains(r#"href="mailto:editor@example.com""#));
 
-    // 🚫 Unwrap HTML5 structural tags (flatten them)
-    for selector in &["main", "header", "footer", "section", "article", "nav"] {
-        unwrap_selector(&document, selector);
+        let relative = sanitize_content(r#"<img src="a.png">"#, None);
+        assert_eq!(relative, r#"<img src="a.png">"#);
     }
 
-    // Serialize DOM back to XHTML-ish HTML
-    let mut out = Vec::new();
-    document.serialize(&mut out).unwrap();
+    #[test]
+    fn keeps_only_harmless_inline_styles() {
+        let html = r#"<p style="position: fixed; TEXT-ALIGN: center; width: 900px">a</p>
+            <div style="background: url(x.png); font-size: 40px">b</div>"#;
+        let out = sanitize_content(html, BASE);
+        assert!(out.contains(r#"<p style="text-align: center">a</p>"#));
+        assert!(out.contains("<div>b</div>"));
+    }
+
+    #[test]
+    fn demotes_headings_below_the_bundle_title() {
+        let out = sanitize_content("<h1>Title</h1><h2>Part</h2><h6>Note</h6>", BASE);
+        assert_eq!(out, "<h2>Title</h2><h3>Part</h3><h6>Note</h6>");
+
+        let already = "<h2>Part</h2><h3>Sub</h3>";
+        assert_eq!(sanitize_content(already, BASE), already);
+    }
 
-    String::from_utf8(out).unwrap()
-}
\ No newline at end of file
+    #[test]
+    fn keeps_math_svg_intact() {
+        let math = crate::math::tex_to_svg("\\frac{a}{b}", true);
+        let out = sanitize_content(&math, BASE);
+        let head = &math[..math.find("<path").expect("path")];
+        assert!(out.starts_with(head));
+        assert_eq!(out.matches("<path").count(), math.matches("<path").count());
+    }
+}
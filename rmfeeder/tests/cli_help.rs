@@ -61,6 +61,15 @@ fn invalid_page_size_shows_allowed_values() {
         .stderr(predicate::str::contains("--page-size must be one of"));
 }
 
+#[test]
+fn invalid_link_style_shows_allowed_values() {
+    let (mut cmd, _home) = fresh_cmd();
+    cmd.args(["--link-style", "margin", "https://example.com"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--link-style must be one of"));
+}
+
 #[test]
 fn feeds_file_implies_feeds_mode() {
     let (mut cmd, _home) = fresh_cmd();
@@ -80,3 +89,60 @@ fn no_source_prints_help_and_fails() {
             "Source Input (choose exactly one):",
         ));
 }
+
+#[test]
+fn record_and_replay_conflict() {
+    let (mut cmd, home) = fresh_cmd();
+    let archive = home.path().join("run.warc");
+    cmd.args(["--record", archive.to_str().unwrap(), "--replay"])
+        .arg(&archive)
+        .arg("https://example.com");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn missing_replay_archive_is_reported() {
+    let (mut cmd, home) = fresh_cmd();
+    cmd.arg("--replay")
+        .arg(home.path().join("missing.warc"))
+        .arg("https://example.com");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("failed to read replay archive"));
+}
+
+#[test]
+fn test_rule_prints_the_rule_extraction() {
+    let (mut cmd, home) = fresh_cmd();
+    let config = home.path().join("rmfeeder.toml");
+    std::fs::write(
+        &config,
+        "[[site_rule]]\nhosts = [\"*.paper.example\"]\ncontent = \".story\"\n\
+         remove = [\".ad\"]\ntitle = \"h1\"\nskip_readability = true\n",
+    )
+    .expect("write config");
+    let page = home.path().join("page.html");
+    std::fs::write(
+        &page,
+        "<html><body><h1>Headline</h1><div class=\"story\"><p>Kept text.</p>\
+         <p class=\"ad\">Advert</p></div></body></html>",
+    )
+    .expect("write page");
+
+    cmd.arg("--config")
+        .arg(&config)
+        .arg("--test-rule")
+        .arg(&page)
+        .arg("https://www.paper.example/a");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Rule: *.paper.example (skip_readability)",
+        ))
+        .stdout(predicate::str::contains("Extracted by: site-rule"))
+        .stdout(predicate::str::contains("Title: Headline"))
+        .stdout(predicate::str::contains("<p>Kept text.</p>"))
+        .stdout(predicate::str::contains("Advert").not());
+}